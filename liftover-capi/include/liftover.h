@@ -0,0 +1,253 @@
+#ifndef LIFTOVER_H
+#define LIFTOVER_H
+
+/* This file is generated by cbindgen. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Error code returned by API functions.
+ */
+typedef enum LiftoverErrorCode {
+  LIFTOVER_ERROR_CODE_OK = 0,
+  LIFTOVER_ERROR_CODE_NULL_POINTER = 1,
+  LIFTOVER_ERROR_CODE_INVALID_STRING = 2,
+  LIFTOVER_ERROR_CODE_IO = 3,
+  LIFTOVER_ERROR_CODE_PARSE = 4,
+  LIFTOVER_ERROR_CODE_INVALID_ARGUMENT = 5,
+  LIFTOVER_ERROR_CODE_OTHER = 6,
+  LIFTOVER_ERROR_CODE_PANIC = 7,
+} LiftoverErrorCode;
+
+typedef enum LiftoverStrand {
+  LIFTOVER_STRAND_FORWARD = 0,
+  LIFTOVER_STRAND_REVERSE = 1,
+} LiftoverStrand;
+
+/**
+ * Result status of a lifted variant.
+ */
+typedef enum LiftoverVariantStatus {
+  LIFTOVER_VARIANT_STATUS_OK = 0,
+  LIFTOVER_VARIANT_STATUS_UNACCEPTABLE_LARGE_DELETION = 1,
+  LIFTOVER_VARIANT_STATUS_UNACCEPTABLE_LARGE_INSERTION = 2,
+  LIFTOVER_VARIANT_STATUS_UNKNOWN_SEQUENCE_NAME = 3,
+  LIFTOVER_VARIANT_STATUS_UNEXPECTED_REF = 4,
+} LiftoverVariantStatus;
+
+/**
+ * Opaque handle of position liftover.
+ */
+typedef struct LiftoverPositionLiftOver LiftoverPositionLiftOver;
+
+/**
+ * Opaque handle of variant liftover.
+ */
+typedef struct LiftoverVariantLiftOver LiftoverVariantLiftOver;
+
+typedef struct LiftoverPosition {
+  char *chromosome;
+  uint64_t position;
+  enum LiftoverStrand strand;
+  size_t chain_index;
+  /**
+   * ID of the chain in the chain file
+   */
+  char *chain_id;
+} LiftoverPosition;
+
+typedef struct LiftoverPositionList {
+  struct LiftoverPosition *items;
+  size_t len;
+} LiftoverPositionList;
+
+typedef struct LiftoverRegion {
+  char *chromosome;
+  uint64_t start;
+  uint64_t end;
+  enum LiftoverStrand strand;
+  size_t chain_index;
+  /**
+   * ID of the chain in the chain file
+   */
+  char *chain_id;
+} LiftoverRegion;
+
+typedef struct LiftoverRegionList {
+  struct LiftoverRegion *items;
+  size_t len;
+} LiftoverRegionList;
+
+/**
+ * A lifted variant.
+ *
+ * When `status` is not `LIFTOVER_VARIANT_STATUS_OK`, `reference` and `alternatives` are NULL.
+ * `chromosome`, `position` and `end` are set only for large deletion/insertion errors.
+ */
+typedef struct LiftoverVariant {
+  enum LiftoverVariantStatus status;
+  char *chromosome;
+  uint64_t position;
+  uint64_t end;
+  enum LiftoverStrand strand;
+  char *original_reference;
+  char *reference;
+  char **alternatives;
+  size_t alternatives_len;
+  bool reference_changed;
+} LiftoverVariant;
+
+typedef struct LiftoverVariantList {
+  struct LiftoverVariant *items;
+  size_t len;
+} LiftoverVariantList;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Return the message of the last error occurred in the current thread.
+ *
+ * The returned string is owned by the library and valid until the next API call in the thread.
+ * Every other API call clears the last error, so NULL is returned if the last call succeeded.
+ */
+const char *liftover_last_error_message(void);
+
+/**
+ * Load a chain file and create position liftover. Files ending with `.gz` are decompressed.
+ *
+ * # Safety
+ * `chain_path` must be a NUL-terminated string and `out` must be a valid pointer.
+ */
+enum LiftoverErrorCode liftover_position_liftover_open(const char *chain_path,
+                                                       struct LiftoverPositionLiftOver **out);
+
+/**
+ * Release position liftover.
+ *
+ * # Safety
+ * `handle` must be NULL or a handle created by `liftover_position_liftover_open`.
+ */
+void liftover_position_liftover_free(struct LiftoverPositionLiftOver *handle);
+
+/**
+ * Lift a zero-based position. All candidates are stored in `out`.
+ *
+ * # Safety
+ * `handle` must be a valid handle, `chromosome` must be a NUL-terminated string
+ * and `out` must be a valid pointer.
+ */
+enum LiftoverErrorCode liftover_position_liftover_lift_position(const struct LiftoverPositionLiftOver *handle,
+                                                                const char *chromosome,
+                                                                uint64_t position,
+                                                                struct LiftoverPositionList *out);
+
+/**
+ * Lift a zero-based half-open region. All candidates are stored in `out`.
+ *
+ * # Safety
+ * `handle` must be a valid handle, `chromosome` must be a NUL-terminated string
+ * and `out` must be a valid pointer.
+ */
+enum LiftoverErrorCode liftover_position_liftover_lift_region(const struct LiftoverPositionLiftOver *handle,
+                                                              const char *chromosome,
+                                                              uint64_t start,
+                                                              uint64_t end,
+                                                              struct LiftoverRegionList *out);
+
+/**
+ * Load a chain file and FASTA files and create variant liftover.
+ *
+ * FASTA files must be indexed. When `left_align` is true, gaps in the chain file are left aligned.
+ *
+ * # Safety
+ * Paths must be NUL-terminated strings and `out` must be a valid pointer.
+ */
+enum LiftoverErrorCode liftover_variant_liftover_open(const char *chain_path,
+                                                      const char *original_assembly,
+                                                      const char *new_assembly,
+                                                      bool left_align,
+                                                      struct LiftoverVariantLiftOver **out);
+
+/**
+ * Release variant liftover.
+ *
+ * # Safety
+ * `handle` must be NULL or a handle created by `liftover_variant_liftover_open`.
+ */
+void liftover_variant_liftover_free(struct LiftoverVariantLiftOver *handle);
+
+/**
+ * Lift a zero-based position with variant liftover.
+ *
+ * # Safety
+ * Same as `liftover_position_liftover_lift_position`.
+ */
+enum LiftoverErrorCode liftover_variant_liftover_lift_position(const struct LiftoverVariantLiftOver *handle,
+                                                               const char *chromosome,
+                                                               uint64_t position,
+                                                               struct LiftoverPositionList *out);
+
+/**
+ * Lift a zero-based half-open region with variant liftover.
+ *
+ * # Safety
+ * Same as `liftover_position_liftover_lift_region`.
+ */
+enum LiftoverErrorCode liftover_variant_liftover_lift_region(const struct LiftoverVariantLiftOver *handle,
+                                                             const char *chromosome,
+                                                             uint64_t start,
+                                                             uint64_t end,
+                                                             struct LiftoverRegionList *out);
+
+/**
+ * Lift a variant at a zero-based position. All candidates are stored in `out`.
+ *
+ * # Safety
+ * `handle` must be a valid handle, strings must be NUL-terminated, `alternatives` must point
+ * `alternatives_len` strings and `out` must be a valid pointer.
+ */
+enum LiftoverErrorCode liftover_variant_liftover_lift_variant(struct LiftoverVariantLiftOver *handle,
+                                                              const char *chromosome,
+                                                              uint64_t position,
+                                                              const char *reference,
+                                                              const char *const *alternatives,
+                                                              size_t alternatives_len,
+                                                              uint64_t acceptable_deletion,
+                                                              uint64_t acceptable_insertion,
+                                                              struct LiftoverVariantList *out);
+
+/**
+ * Release items of a position list.
+ *
+ * # Safety
+ * `list` must be NULL or a list filled by this library.
+ */
+void liftover_position_list_free(struct LiftoverPositionList *list);
+
+/**
+ * Release items of a region list.
+ *
+ * # Safety
+ * `list` must be NULL or a list filled by this library.
+ */
+void liftover_region_list_free(struct LiftoverRegionList *list);
+
+/**
+ * Release items of a variant list.
+ *
+ * # Safety
+ * `list` must be NULL or a list filled by this library.
+ */
+void liftover_variant_list_free(struct LiftoverVariantList *list);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LIFTOVER_H */
@@ -0,0 +1,683 @@
+//! C API for the liftover library.
+//!
+//! All coordinates are zero-based and regions are half-open as in the Rust library.
+//! Every handle and result list returned by this library must be released with the
+//! corresponding `*_free` function. Handles are not thread-safe.
+
+use bio::io::fasta::IndexedReader;
+use liftover::chain::{ChainFile, Strand};
+use liftover::poslift::PositionLiftOver;
+use liftover::variantlift::error::VariantLiftOverError;
+use liftover::variantlift::VariantLiftOver;
+use liftover::{LiftOverError, Variant};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::Read;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+#[cfg(test)]
+mod test;
+
+/// Error code returned by API functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiftoverErrorCode {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidString = 2,
+    Io = 3,
+    Parse = 4,
+    InvalidArgument = 5,
+    Other = 6,
+    Panic = 7,
+}
+
+/// Result status of a lifted variant.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiftoverVariantStatus {
+    Ok = 0,
+    UnacceptableLargeDeletion = 1,
+    UnacceptableLargeInsertion = 2,
+    UnknownSequenceName = 3,
+    UnexpectedRef = 4,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiftoverStrand {
+    Forward = 0,
+    Reverse = 1,
+}
+
+impl From<Strand> for LiftoverStrand {
+    fn from(strand: Strand) -> Self {
+        match strand {
+            Strand::Forward => LiftoverStrand::Forward,
+            Strand::Reverse => LiftoverStrand::Reverse,
+        }
+    }
+}
+
+/// Opaque handle of position liftover.
+pub struct LiftoverPositionLiftOver {
+    inner: PositionLiftOver,
+}
+
+/// Opaque handle of variant liftover.
+pub struct LiftoverVariantLiftOver {
+    inner: VariantLiftOver<IndexedReader<File>>,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiftoverPosition {
+    pub chromosome: *mut c_char,
+    pub position: u64,
+    pub strand: LiftoverStrand,
+    pub chain_index: usize,
+    /// ID of the chain in the chain file
+    pub chain_id: *mut c_char,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiftoverPositionList {
+    pub items: *mut LiftoverPosition,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiftoverRegion {
+    pub chromosome: *mut c_char,
+    pub start: u64,
+    pub end: u64,
+    pub strand: LiftoverStrand,
+    pub chain_index: usize,
+    /// ID of the chain in the chain file
+    pub chain_id: *mut c_char,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiftoverRegionList {
+    pub items: *mut LiftoverRegion,
+    pub len: usize,
+}
+
+/// A lifted variant.
+///
+/// When `status` is not `LIFTOVER_VARIANT_STATUS_OK`, `reference` and `alternatives` are NULL.
+/// `chromosome`, `position` and `end` are set only for large deletion/insertion errors.
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiftoverVariant {
+    pub status: LiftoverVariantStatus,
+    pub chromosome: *mut c_char,
+    pub position: u64,
+    pub end: u64,
+    pub strand: LiftoverStrand,
+    pub original_reference: *mut c_char,
+    pub reference: *mut c_char,
+    pub alternatives: *mut *mut c_char,
+    pub alternatives_len: usize,
+    pub reference_changed: bool,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct LiftoverVariantList {
+    pub items: *mut LiftoverVariant,
+    pub len: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+}
+
+fn fail(code: LiftoverErrorCode, message: String) -> LiftoverErrorCode {
+    set_last_error(message);
+    code
+}
+
+/// Clear the last error and run `f`. A panic is caught and reported as
+/// `LIFTOVER_ERROR_CODE_PANIC` instead of unwinding into C.
+fn guard(f: impl FnOnce() -> LiftoverErrorCode) -> LiftoverErrorCode {
+    LAST_ERROR.with(|x| *x.borrow_mut() = None);
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|e| {
+        let message = e
+            .downcast_ref::<&str>()
+            .map(|x| x.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        fail(
+            LiftoverErrorCode::Panic,
+            format!("Unexpected panic: {}", message),
+        )
+    })
+}
+
+fn liftover_error(e: LiftOverError) -> LiftoverErrorCode {
+    let code = match e {
+        LiftOverError::IoError(_) | LiftOverError::UnknownSequenceError(_, _) => {
+            LiftoverErrorCode::Io
+        }
+        LiftOverError::InvalidNumberOfHeader(_)
+        | LiftOverError::NoChainHeaderFound(_)
+        | LiftOverError::InvalidStrand(_)
+        | LiftOverError::InvalidChromosomeLength(_)
+        | LiftOverError::InvalidNumberOfColumns(_)
+        | LiftOverError::ParseIntError(_)
+        | LiftOverError::ParseStrandError => LiftoverErrorCode::Parse,
+        _ => LiftoverErrorCode::Other,
+    };
+    fail(code, e.to_string())
+}
+
+/// Return the message of the last error occurred in the current thread.
+///
+/// The returned string is owned by the library and valid until the next API call in the thread.
+/// Every other API call clears the last error, so NULL is returned if the last call succeeded.
+#[no_mangle]
+pub extern "C" fn liftover_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|x| {
+        x.borrow()
+            .as_ref()
+            .map(|x| x.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+unsafe fn to_str<'a>(value: *const c_char) -> Result<&'a str, LiftoverErrorCode> {
+    if value.is_null() {
+        return Err(fail(
+            LiftoverErrorCode::NullPointer,
+            "NULL pointer is passed".to_string(),
+        ));
+    }
+    CStr::from_ptr(value).to_str().map_err(|e| {
+        fail(
+            LiftoverErrorCode::InvalidString,
+            format!("String is not valid UTF-8: {}", e),
+        )
+    })
+}
+
+fn to_c_string(value: &[u8]) -> *mut c_char {
+    CString::new(value.to_vec()).unwrap_or_default().into_raw()
+}
+
+unsafe fn free_c_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+fn into_raw_list<T>(items: Vec<T>) -> (*mut T, usize) {
+    let len = items.len();
+    let items = Box::into_raw(items.into_boxed_slice()) as *mut T;
+    (items, len)
+}
+
+unsafe fn from_raw_list<T>(items: *mut T, len: usize) -> Vec<T> {
+    Box::from_raw(ptr::slice_from_raw_parts_mut(items, len)).into_vec()
+}
+
+fn open_chain(path: &str) -> Result<ChainFile, LiftoverErrorCode> {
+    let file =
+        File::open(path).map_err(|e| fail(LiftoverErrorCode::Io, format!("{}: {}", path, e)))?;
+    let reader: Box<dyn Read> = if path.ends_with(".gz") || path.ends_with(".bgz") {
+        Box::new(flate2::read::MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    ChainFile::load(reader).map_err(liftover_error)
+}
+
+fn open_fasta(path: &str) -> Result<IndexedReader<File>, LiftoverErrorCode> {
+    IndexedReader::from_file(&path).map_err(|e| {
+        fail(
+            LiftoverErrorCode::Io,
+            format!("Cannot load FASTA {} (.fai file is required): {}", path, e),
+        )
+    })
+}
+
+macro_rules! try_code {
+    ($e:expr) => {
+        match $e {
+            Ok(v) => v,
+            Err(code) => return code,
+        }
+    };
+}
+
+macro_rules! check_null {
+    ($($e:expr),+) => {
+        if $($e.is_null())||+ {
+            return fail(
+                LiftoverErrorCode::NullPointer,
+                "NULL pointer is passed".to_string(),
+            );
+        }
+    };
+}
+
+fn position_list(
+    position_lift: &PositionLiftOver,
+    chromosome: &str,
+    position: u64,
+) -> LiftoverPositionList {
+    let items: Vec<_> = position_lift
+        .lift_position(chromosome, position)
+        .into_iter()
+        .map(|x| LiftoverPosition {
+            chromosome: to_c_string(x.chromosome.name.as_bytes()),
+            position: x.position,
+            strand: x.strand.into(),
+            chain_index: x.chain_index,
+            chain_id: to_c_string(
+                position_lift.chain_list()[x.chain_index]
+                    .chain_id
+                    .as_bytes(),
+            ),
+        })
+        .collect();
+    let (items, len) = into_raw_list(items);
+    LiftoverPositionList { items, len }
+}
+
+fn region_list(
+    position_lift: &PositionLiftOver,
+    chromosome: &str,
+    start: u64,
+    end: u64,
+) -> LiftoverRegionList {
+    let items: Vec<_> = position_lift
+        .lift_region(chromosome, start..end)
+        .into_iter()
+        .map(|x| LiftoverRegion {
+            chromosome: to_c_string(x.chromosome.name.as_bytes()),
+            start: x.start,
+            end: x.end,
+            strand: x.strand.into(),
+            chain_index: x.chain_index,
+            chain_id: to_c_string(
+                position_lift.chain_list()[x.chain_index]
+                    .chain_id
+                    .as_bytes(),
+            ),
+        })
+        .collect();
+    let (items, len) = into_raw_list(items);
+    LiftoverRegionList { items, len }
+}
+
+/// Load a chain file and create position liftover. Files ending with `.gz` are decompressed.
+///
+/// # Safety
+/// `chain_path` must be a NUL-terminated string and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_position_liftover_open(
+    chain_path: *const c_char,
+    out: *mut *mut LiftoverPositionLiftOver,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(out);
+        let chain_path = try_code!(to_str(chain_path));
+        let chain = try_code!(open_chain(chain_path));
+        *out = Box::into_raw(Box::new(LiftoverPositionLiftOver {
+            inner: PositionLiftOver::new(chain),
+        }));
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Release position liftover.
+///
+/// # Safety
+/// `handle` must be NULL or a handle created by `liftover_position_liftover_open`.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_position_liftover_free(handle: *mut LiftoverPositionLiftOver) {
+    guard(|| {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+        LiftoverErrorCode::Ok
+    });
+}
+
+/// Lift a zero-based position. All candidates are stored in `out`.
+///
+/// # Safety
+/// `handle` must be a valid handle, `chromosome` must be a NUL-terminated string
+/// and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_position_liftover_lift_position(
+    handle: *const LiftoverPositionLiftOver,
+    chromosome: *const c_char,
+    position: u64,
+    out: *mut LiftoverPositionList,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(handle, out);
+        let chromosome = try_code!(to_str(chromosome));
+        *out = position_list(&(*handle).inner, chromosome, position);
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Lift a zero-based half-open region. All candidates are stored in `out`.
+///
+/// # Safety
+/// `handle` must be a valid handle, `chromosome` must be a NUL-terminated string
+/// and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_position_liftover_lift_region(
+    handle: *const LiftoverPositionLiftOver,
+    chromosome: *const c_char,
+    start: u64,
+    end: u64,
+    out: *mut LiftoverRegionList,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(handle, out);
+        let chromosome = try_code!(to_str(chromosome));
+        if end < start {
+            return fail(
+                LiftoverErrorCode::InvalidArgument,
+                format!("end should be larger than start: {}-{}", start, end),
+            );
+        }
+        *out = region_list(&(*handle).inner, chromosome, start, end);
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Load a chain file and FASTA files and create variant liftover.
+///
+/// FASTA files must be indexed. When `left_align` is true, gaps in the chain file are left aligned.
+///
+/// # Safety
+/// Paths must be NUL-terminated strings and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_variant_liftover_open(
+    chain_path: *const c_char,
+    original_assembly: *const c_char,
+    new_assembly: *const c_char,
+    left_align: bool,
+    out: *mut *mut LiftoverVariantLiftOver,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(out);
+        let chain_path = try_code!(to_str(chain_path));
+        let original_assembly = try_code!(to_str(original_assembly));
+        let new_assembly = try_code!(to_str(new_assembly));
+
+        let mut original_sequence = try_code!(open_fasta(original_assembly));
+        let mut new_sequence = try_code!(open_fasta(new_assembly));
+        let mut chain = try_code!(open_chain(chain_path));
+        if left_align {
+            chain = try_code!(chain
+                .left_align(&mut original_sequence, &mut new_sequence)
+                .map_err(liftover_error));
+        }
+        *out = Box::into_raw(Box::new(LiftoverVariantLiftOver {
+            inner: VariantLiftOver::new(chain, original_sequence, new_sequence),
+        }));
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Release variant liftover.
+///
+/// # Safety
+/// `handle` must be NULL or a handle created by `liftover_variant_liftover_open`.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_variant_liftover_free(handle: *mut LiftoverVariantLiftOver) {
+    guard(|| {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+        LiftoverErrorCode::Ok
+    });
+}
+
+/// Lift a zero-based position with variant liftover.
+///
+/// # Safety
+/// Same as `liftover_position_liftover_lift_position`.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_variant_liftover_lift_position(
+    handle: *const LiftoverVariantLiftOver,
+    chromosome: *const c_char,
+    position: u64,
+    out: *mut LiftoverPositionList,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(handle, out);
+        let chromosome = try_code!(to_str(chromosome));
+        *out = position_list((*handle).inner.position_liftover(), chromosome, position);
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Lift a zero-based half-open region with variant liftover.
+///
+/// # Safety
+/// Same as `liftover_position_liftover_lift_region`.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_variant_liftover_lift_region(
+    handle: *const LiftoverVariantLiftOver,
+    chromosome: *const c_char,
+    start: u64,
+    end: u64,
+    out: *mut LiftoverRegionList,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(handle, out);
+        let chromosome = try_code!(to_str(chromosome));
+        if end < start {
+            return fail(
+                LiftoverErrorCode::InvalidArgument,
+                format!("end should be larger than start: {}-{}", start, end),
+            );
+        }
+        *out = region_list((*handle).inner.position_liftover(), chromosome, start, end);
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Lift a variant at a zero-based position. All candidates are stored in `out`.
+///
+/// # Safety
+/// `handle` must be a valid handle, strings must be NUL-terminated, `alternatives` must point
+/// `alternatives_len` strings and `out` must be a valid pointer.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn liftover_variant_liftover_lift_variant(
+    handle: *mut LiftoverVariantLiftOver,
+    chromosome: *const c_char,
+    position: u64,
+    reference: *const c_char,
+    alternatives: *const *const c_char,
+    alternatives_len: usize,
+    acceptable_deletion: u64,
+    acceptable_insertion: u64,
+    out: *mut LiftoverVariantList,
+) -> LiftoverErrorCode {
+    guard(|| {
+        check_null!(handle, out);
+        if alternatives_len > 0 {
+            check_null!(alternatives);
+        }
+        let chromosome = try_code!(to_str(chromosome));
+        let reference = try_code!(to_str(reference));
+        let mut alternative = Vec::with_capacity(alternatives_len);
+        for i in 0..alternatives_len {
+            alternative.push(
+                try_code!(to_str(*alternatives.add(i)))
+                    .to_ascii_uppercase()
+                    .into_bytes(),
+            );
+        }
+        let variant = Variant {
+            chromosome: chromosome.to_string(),
+            position,
+            reference: reference.to_ascii_uppercase().into_bytes(),
+            alternative,
+        };
+
+        let lifted = try_code!((*handle)
+            .inner
+            .lift_variant(&variant, acceptable_deletion, acceptable_insertion)
+            .map_err(liftover_error));
+
+        let items: Vec<_> = lifted
+            .into_iter()
+            .map(|one| match one {
+                Ok(v) => {
+                    let (alternatives, alternatives_len) =
+                        into_raw_list(v.alternative.iter().map(|x| to_c_string(x)).collect());
+                    LiftoverVariant {
+                        status: LiftoverVariantStatus::Ok,
+                        chromosome: to_c_string(v.chromosome.as_bytes()),
+                        position: v.position,
+                        end: v.position + v.reference.len() as u64,
+                        strand: v.strand.into(),
+                        original_reference: to_c_string(&v.original_reference),
+                        reference: to_c_string(&v.reference),
+                        alternatives,
+                        alternatives_len,
+                        reference_changed: v.reference_changed,
+                    }
+                }
+                Err(e) => {
+                    let (status, chromosome, position, end) = match e {
+                        VariantLiftOverError::UnacceptableLargeDeletion {
+                            chromosome,
+                            start,
+                            end,
+                            ..
+                        } => (
+                            LiftoverVariantStatus::UnacceptableLargeDeletion,
+                            to_c_string(chromosome.as_bytes()),
+                            start,
+                            end,
+                        ),
+                        VariantLiftOverError::UnacceptableLargeInsertion {
+                            chromosome,
+                            start,
+                            end,
+                            ..
+                        } => (
+                            LiftoverVariantStatus::UnacceptableLargeInsertion,
+                            to_c_string(chromosome.as_bytes()),
+                            start,
+                            end,
+                        ),
+                        VariantLiftOverError::UnknownSequenceName(_) => (
+                            LiftoverVariantStatus::UnknownSequenceName,
+                            ptr::null_mut(),
+                            0,
+                            0,
+                        ),
+                        VariantLiftOverError::ReferenceSequenceIsNotMatch => {
+                            (LiftoverVariantStatus::UnexpectedRef, ptr::null_mut(), 0, 0)
+                        }
+                    };
+                    LiftoverVariant {
+                        status,
+                        chromosome,
+                        position,
+                        end,
+                        strand: LiftoverStrand::Forward,
+                        original_reference: ptr::null_mut(),
+                        reference: ptr::null_mut(),
+                        alternatives: ptr::null_mut(),
+                        alternatives_len: 0,
+                        reference_changed: false,
+                    }
+                }
+            })
+            .collect();
+        let (items, len) = into_raw_list(items);
+        *out = LiftoverVariantList { items, len };
+        LiftoverErrorCode::Ok
+    })
+}
+
+/// Release items of a position list.
+///
+/// # Safety
+/// `list` must be NULL or a list filled by this library.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_position_list_free(list: *mut LiftoverPositionList) {
+    guard(|| {
+        if list.is_null() || (*list).items.is_null() {
+            return LiftoverErrorCode::Ok;
+        }
+        for one in from_raw_list((*list).items, (*list).len) {
+            free_c_string(one.chromosome);
+            free_c_string(one.chain_id);
+        }
+        (*list).items = ptr::null_mut();
+        (*list).len = 0;
+        LiftoverErrorCode::Ok
+    });
+}
+
+/// Release items of a region list.
+///
+/// # Safety
+/// `list` must be NULL or a list filled by this library.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_region_list_free(list: *mut LiftoverRegionList) {
+    guard(|| {
+        if list.is_null() || (*list).items.is_null() {
+            return LiftoverErrorCode::Ok;
+        }
+        for one in from_raw_list((*list).items, (*list).len) {
+            free_c_string(one.chromosome);
+            free_c_string(one.chain_id);
+        }
+        (*list).items = ptr::null_mut();
+        (*list).len = 0;
+        LiftoverErrorCode::Ok
+    });
+}
+
+/// Release items of a variant list.
+///
+/// # Safety
+/// `list` must be NULL or a list filled by this library.
+#[no_mangle]
+pub unsafe extern "C" fn liftover_variant_list_free(list: *mut LiftoverVariantList) {
+    guard(|| {
+        if list.is_null() || (*list).items.is_null() {
+            return LiftoverErrorCode::Ok;
+        }
+        for one in from_raw_list((*list).items, (*list).len) {
+            free_c_string(one.chromosome);
+            free_c_string(one.original_reference);
+            free_c_string(one.reference);
+            if !one.alternatives.is_null() {
+                for alt in from_raw_list(one.alternatives, one.alternatives_len) {
+                    free_c_string(alt);
+                }
+            }
+        }
+        (*list).items = ptr::null_mut();
+        (*list).len = 0;
+        LiftoverErrorCode::Ok
+    });
+}
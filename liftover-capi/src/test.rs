@@ -0,0 +1,115 @@
+use super::*;
+
+const CHAIN: &CStr = c"../liftover-rs/testfiles/genomes/chain/GRCh38-to-GRCh37.chr22.chain";
+
+#[test]
+fn test_position_liftover() {
+    unsafe {
+        let mut handle = ptr::null_mut();
+        assert_eq!(
+            liftover_position_liftover_open(CHAIN.as_ptr(), &mut handle),
+            LiftoverErrorCode::Ok
+        );
+        assert!(!handle.is_null());
+
+        let mut positions = LiftoverPositionList {
+            items: ptr::null_mut(),
+            len: 0,
+        };
+        assert_eq!(
+            liftover_position_liftover_lift_position(
+                handle,
+                c"chr22".as_ptr(),
+                17513944,
+                &mut positions
+            ),
+            LiftoverErrorCode::Ok
+        );
+        assert_eq!(positions.len, 1);
+        let one = &*positions.items;
+        assert_eq!(CStr::from_ptr(one.chromosome).to_str().unwrap(), "chr22");
+        assert_eq!(one.position, 17992973);
+        assert_eq!(one.strand, LiftoverStrand::Forward);
+        assert_eq!(one.chain_index, 13);
+        assert_eq!(CStr::from_ptr(one.chain_id).to_str().unwrap(), "14");
+        liftover_position_list_free(&mut positions);
+        assert!(positions.items.is_null());
+
+        let mut regions = LiftoverRegionList {
+            items: ptr::null_mut(),
+            len: 0,
+        };
+        assert_eq!(
+            liftover_position_liftover_lift_region(
+                handle,
+                c"chr22".as_ptr(),
+                17513944,
+                17514000,
+                &mut regions
+            ),
+            LiftoverErrorCode::Ok
+        );
+        assert_eq!(regions.len, 1);
+        let one = &*regions.items;
+        assert_eq!(CStr::from_ptr(one.chromosome).to_str().unwrap(), "chr22");
+        assert_eq!((one.start, one.end), (17992973, 17993029));
+        assert_eq!(one.strand, LiftoverStrand::Forward);
+        assert_eq!(CStr::from_ptr(one.chain_id).to_str().unwrap(), "14");
+        liftover_region_list_free(&mut regions);
+
+        assert_eq!(
+            liftover_position_liftover_lift_region(
+                handle,
+                c"chr22".as_ptr(),
+                200,
+                100,
+                &mut regions
+            ),
+            LiftoverErrorCode::InvalidArgument
+        );
+        assert!(!liftover_last_error_message().is_null());
+
+        liftover_position_liftover_free(handle);
+    }
+}
+
+#[test]
+fn test_open_error() {
+    unsafe {
+        let mut handle = ptr::null_mut();
+        assert_eq!(
+            liftover_position_liftover_open(
+                c"../liftover-rs/testfiles/not-found.chain".as_ptr(),
+                &mut handle
+            ),
+            LiftoverErrorCode::Io
+        );
+        assert!(handle.is_null());
+        assert_eq!(
+            liftover_position_liftover_open(ptr::null(), &mut handle),
+            LiftoverErrorCode::NullPointer
+        );
+    }
+}
+
+#[test]
+fn test_last_error() {
+    unsafe {
+        let mut handle = ptr::null_mut();
+        assert_eq!(
+            liftover_position_liftover_open(ptr::null(), &mut handle),
+            LiftoverErrorCode::NullPointer
+        );
+        assert!(!liftover_last_error_message().is_null());
+        assert_eq!(
+            liftover_position_liftover_open(CHAIN.as_ptr(), &mut handle),
+            LiftoverErrorCode::Ok
+        );
+        assert!(liftover_last_error_message().is_null());
+        liftover_position_liftover_free(handle);
+    }
+
+    assert_eq!(guard(|| panic!("broken")), LiftoverErrorCode::Panic);
+    let message = unsafe { CStr::from_ptr(liftover_last_error_message()) };
+    assert_eq!(message.to_str().unwrap(), "Unexpected panic: broken");
+}
@@ -0,0 +1,370 @@
+//! Python bindings for the liftover library.
+//!
+//! All coordinates are zero-based and regions are half-open as in the Rust library.
+
+use bio::io::fasta::IndexedReader;
+use liftover::poslift::RegionChangeOp;
+use liftover::variantlift::error::VariantLiftOverError;
+use liftover::{chain, poslift, variantlift, LiftOverError, Variant};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::fs::File;
+use std::io::Read;
+
+#[cfg(test)]
+mod test;
+
+fn to_py_err(e: LiftOverError) -> PyErr {
+    match e {
+        LiftOverError::IoError(e) => PyIOError::new_err(e.to_string()),
+        LiftOverError::UnknownSequenceError(chrom, e) => {
+            PyIOError::new_err(format!("{}: {}", chrom, e))
+        }
+        _ => PyValueError::new_err(e.to_string()),
+    }
+}
+
+fn open_chain(path: &str) -> PyResult<Box<dyn Read>> {
+    let file = File::open(path).map_err(|e| PyIOError::new_err(format!("{}: {}", path, e)))?;
+    if path.ends_with(".gz") || path.ends_with(".bgz") {
+        Ok(Box::new(flate2::read::MultiGzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+fn open_fasta(path: &str) -> PyResult<IndexedReader<File>> {
+    IndexedReader::from_file(&path).map_err(|e| {
+        PyIOError::new_err(format!(
+            "Cannot load FASTA {} (.fai file is required): {}",
+            path, e
+        ))
+    })
+}
+
+/// Chain file loaded into memory.
+#[pyclass(module = "transanno")]
+#[derive(Debug, Clone)]
+pub struct ChainFile {
+    inner: chain::ChainFile,
+}
+
+#[pymethods]
+impl ChainFile {
+    /// Load a chain file. Files ending with `.gz` are decompressed.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        Ok(ChainFile {
+            inner: chain::ChainFile::load(open_chain(path)?).map_err(to_py_err)?,
+        })
+    }
+
+    /// Return a new chain file whose gaps are left aligned with FASTA files.
+    fn left_align(&self, original_assembly: &str, new_assembly: &str) -> PyResult<Self> {
+        let mut original_sequence = open_fasta(original_assembly)?;
+        let mut new_sequence = open_fasta(new_assembly)?;
+        Ok(ChainFile {
+            inner: self
+                .inner
+                .left_align(&mut original_sequence, &mut new_sequence)
+                .map_err(to_py_err)?,
+        })
+    }
+
+    /// List of (name, length) of original chromosomes.
+    #[getter]
+    fn original_chromosomes(&self) -> Vec<(String, u64)> {
+        self.inner
+            .original_chromosomes
+            .iter()
+            .map(|x| (x.name.to_string(), x.length))
+            .collect()
+    }
+
+    /// List of (name, length) of new chromosomes.
+    #[getter]
+    fn new_chromosomes(&self) -> Vec<(String, u64)> {
+        self.inner
+            .new_chromosomes
+            .iter()
+            .map(|x| (x.name.to_string(), x.length))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.chain_list.len()
+    }
+}
+
+/// Lift positions and regions with a chain file.
+#[pyclass(module = "transanno")]
+#[derive(Debug)]
+pub struct PositionLiftOver {
+    inner: poslift::PositionLiftOver,
+}
+
+#[pymethods]
+impl PositionLiftOver {
+    #[new]
+    fn new(chain: &ChainFile) -> Self {
+        PositionLiftOver {
+            inner: poslift::PositionLiftOver::new(chain.inner.clone()),
+        }
+    }
+
+    /// Load a chain file and create liftover.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        Ok(PositionLiftOver {
+            inner: poslift::PositionLiftOver::load(open_chain(path)?).map_err(to_py_err)?,
+        })
+    }
+
+    /// Lift a zero-based position. All candidates are returned.
+    fn lift_position<'py>(
+        &self,
+        py: Python<'py>,
+        chromosome: &str,
+        position: u64,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        position_results(py, &self.inner, chromosome, position)
+    }
+
+    /// Lift a zero-based half-open region. All candidates are returned.
+    fn lift_region<'py>(
+        &self,
+        py: Python<'py>,
+        chromosome: &str,
+        start: u64,
+        end: u64,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        region_results(py, &self.inner, chromosome, start, end)
+    }
+}
+
+/// Lift variants with a chain file and original/new assembly FASTA files.
+#[pyclass(module = "transanno")]
+#[derive(Debug)]
+pub struct VariantLiftOver {
+    inner: variantlift::VariantLiftOver<IndexedReader<File>>,
+}
+
+#[pymethods]
+impl VariantLiftOver {
+    #[new]
+    #[pyo3(signature = (chain, original_assembly, new_assembly, left_align = true))]
+    fn new(
+        chain: &ChainFile,
+        original_assembly: &str,
+        new_assembly: &str,
+        left_align: bool,
+    ) -> PyResult<Self> {
+        let mut original_sequence = open_fasta(original_assembly)?;
+        let mut new_sequence = open_fasta(new_assembly)?;
+        let chain = if left_align {
+            chain
+                .inner
+                .left_align(&mut original_sequence, &mut new_sequence)
+                .map_err(to_py_err)?
+        } else {
+            chain.inner.clone()
+        };
+        Ok(VariantLiftOver {
+            inner: variantlift::VariantLiftOver::new(chain, original_sequence, new_sequence),
+        })
+    }
+
+    /// Lift a zero-based position.
+    fn lift_position<'py>(
+        &self,
+        py: Python<'py>,
+        chromosome: &str,
+        position: u64,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        position_results(py, self.inner.position_liftover(), chromosome, position)
+    }
+
+    /// Lift a zero-based half-open region.
+    fn lift_region<'py>(
+        &self,
+        py: Python<'py>,
+        chromosome: &str,
+        start: u64,
+        end: u64,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        region_results(py, self.inner.position_liftover(), chromosome, start, end)
+    }
+
+    /// Lift a variant at a zero-based position.
+    ///
+    /// Each candidate is returned as a dict. A failed candidate has an `error` key.
+    #[pyo3(signature = (chromosome, position, reference, alternative, acceptable_deletion = 3, acceptable_insertion = 3))]
+    #[allow(clippy::too_many_arguments)]
+    fn lift_variant<'py>(
+        &mut self,
+        py: Python<'py>,
+        chromosome: &str,
+        position: u64,
+        reference: &str,
+        alternative: Vec<String>,
+        acceptable_deletion: u64,
+        acceptable_insertion: u64,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let variant = Variant {
+            chromosome: chromosome.to_string(),
+            position,
+            reference: reference.to_ascii_uppercase().into_bytes(),
+            alternative: alternative
+                .iter()
+                .map(|x| x.to_ascii_uppercase().into_bytes())
+                .collect(),
+        };
+        let lifted = self
+            .inner
+            .lift_variant(&variant, acceptable_deletion, acceptable_insertion)
+            .map_err(to_py_err)?;
+
+        let mut results = Vec::new();
+        for one in lifted {
+            let dict = PyDict::new(py);
+            match one {
+                Ok(v) => {
+                    dict.set_item("chromosome", v.chromosome)?;
+                    dict.set_item("position", v.position)?;
+                    dict.set_item("strand", v.strand.to_string())?;
+                    dict.set_item(
+                        "original_reference",
+                        String::from_utf8_lossy(&v.original_reference),
+                    )?;
+                    dict.set_item("reference", String::from_utf8_lossy(&v.reference))?;
+                    dict.set_item(
+                        "alternative",
+                        v.alternative
+                            .iter()
+                            .map(|x| String::from_utf8_lossy(x).to_string())
+                            .collect::<Vec<_>>(),
+                    )?;
+                    dict.set_item("reference_changed", v.reference_changed)?;
+                }
+                Err(e) => {
+                    dict.set_item("message", e.to_string())?;
+                    match e {
+                        VariantLiftOverError::UnacceptableLargeDeletion {
+                            chromosome,
+                            start,
+                            end,
+                            chain_id,
+                            gap_size,
+                        } => {
+                            dict.set_item("error", "UNACCEPTABLE_LARGE_DELETION")?;
+                            dict.set_item("chromosome", chromosome)?;
+                            dict.set_item("start", start)?;
+                            dict.set_item("end", end)?;
+                            dict.set_item("chain_id", chain_id)?;
+                            dict.set_item("gap_size", gap_size)?;
+                        }
+                        VariantLiftOverError::UnacceptableLargeInsertion {
+                            chromosome,
+                            start,
+                            end,
+                            chain_id,
+                            gap_size,
+                        } => {
+                            dict.set_item("error", "UNACCEPTABLE_LARGE_INSERTION")?;
+                            dict.set_item("chromosome", chromosome)?;
+                            dict.set_item("start", start)?;
+                            dict.set_item("end", end)?;
+                            dict.set_item("chain_id", chain_id)?;
+                            dict.set_item("gap_size", gap_size)?;
+                        }
+                        VariantLiftOverError::UnknownSequenceName(_) => {
+                            dict.set_item("error", "UNKNOWN_SEQUENCE_NAME")?;
+                        }
+                        VariantLiftOverError::ReferenceSequenceIsNotMatch => {
+                            dict.set_item("error", "UNEXPECTED_REF")?;
+                        }
+                    }
+                }
+            }
+            results.push(dict);
+        }
+        Ok(results)
+    }
+}
+
+fn position_results<'py>(
+    py: Python<'py>,
+    position_lift: &poslift::PositionLiftOver,
+    chromosome: &str,
+    position: u64,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    position_lift
+        .lift_position(chromosome, position)
+        .into_iter()
+        .map(|x| {
+            let dict = PyDict::new(py);
+            dict.set_item("chromosome", &x.chromosome.name)?;
+            dict.set_item("position", x.position)?;
+            dict.set_item("strand", x.strand.to_string())?;
+            dict.set_item("chain_index", x.chain_index)?;
+            dict.set_item(
+                "chain_id",
+                &position_lift.chain_list()[x.chain_index].chain_id,
+            )?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+fn region_results<'py>(
+    py: Python<'py>,
+    position_lift: &poslift::PositionLiftOver,
+    chromosome: &str,
+    start: u64,
+    end: u64,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    if end < start {
+        return Err(PyValueError::new_err(format!(
+            "end should be larger than start: {}-{}",
+            start, end
+        )));
+    }
+    position_lift
+        .lift_region(chromosome, start..end)
+        .into_iter()
+        .map(|x| {
+            let dict = PyDict::new(py);
+            dict.set_item("chromosome", &x.chromosome.name)?;
+            dict.set_item("start", x.start)?;
+            dict.set_item("end", x.end)?;
+            dict.set_item("strand", x.strand.to_string())?;
+            dict.set_item(
+                "changes",
+                x.changes
+                    .iter()
+                    .map(|y| match y {
+                        RegionChangeOp::Aligned(l) => ("aligned", *l),
+                        RegionChangeOp::Insertion(l) => ("insertion", *l),
+                        RegionChangeOp::Deletion(l) => ("deletion", *l),
+                    })
+                    .collect::<Vec<_>>(),
+            )?;
+            dict.set_item("chain_index", x.chain_index)?;
+            dict.set_item(
+                "chain_id",
+                &position_lift.chain_list()[x.chain_index].chain_id,
+            )?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+#[pymodule]
+fn transanno(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ChainFile>()?;
+    m.add_class::<PositionLiftOver>()?;
+    m.add_class::<VariantLiftOver>()?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use super::*;
+
+const CHAIN: &str = "../liftover-rs/testfiles/genomes/chain/GRCh38-to-GRCh37.chr22.chain";
+const GRCH38: &str = "../liftover-rs/testfiles/genomes/GRCh38/GRCh38.chr22.genome.fa";
+const GRCH37: &str = "../liftover-rs/testfiles/genomes/GRCh37/GRCh37.chr22.genome.fa";
+
+fn get<'py, T: FromPyObject<'py>>(dict: &Bound<'py, PyDict>, key: &str) -> T {
+    dict.get_item(key).unwrap().unwrap().extract().unwrap()
+}
+
+#[test]
+fn test_chain_file() {
+    pyo3::prepare_freethreaded_python();
+    let chain = ChainFile::load(CHAIN).unwrap();
+    assert!(chain.__len__() > 0);
+    assert!(chain
+        .original_chromosomes()
+        .contains(&("chr22".to_string(), 50818468)));
+    assert!(chain
+        .new_chromosomes()
+        .contains(&("chr22".to_string(), 51304566)));
+}
+
+#[test]
+fn test_position_liftover() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let lift = PositionLiftOver::load(CHAIN).unwrap();
+
+        let results = lift.lift_position(py, "chr22", 17513944).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(get::<String>(&results[0], "chromosome"), "chr22");
+        assert_eq!(get::<u64>(&results[0], "position"), 17992973);
+        assert_eq!(get::<String>(&results[0], "strand"), "+");
+        assert_eq!(get::<String>(&results[0], "chain_id"), "14");
+
+        let results = lift.lift_region(py, "chr22", 17513944, 17514000).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(get::<u64>(&results[0], "start"), 17992973);
+        assert_eq!(get::<u64>(&results[0], "end"), 17993029);
+        assert_eq!(
+            get::<Vec<(String, u64)>>(&results[0], "changes"),
+            vec![("aligned".to_string(), 56)]
+        );
+
+        assert!(lift.lift_position(py, "chr1", 100).unwrap().is_empty());
+    });
+}
+
+#[test]
+fn test_variant_liftover() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let chain = ChainFile::load(CHAIN).unwrap();
+        let mut lift = VariantLiftOver::new(&chain, GRCH38, GRCH37, true).unwrap();
+
+        let results = lift
+            .lift_variant(py, "chr22", 16367188, "G", vec!["A".to_string()], 3, 3)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(get::<String>(&results[0], "chromosome"), "chr22");
+        assert_eq!(get::<u64>(&results[0], "position"), 16847850);
+        assert_eq!(get::<String>(&results[0], "strand"), "+");
+        assert_eq!(get::<String>(&results[0], "reference"), "G");
+        assert_eq!(get::<Vec<String>>(&results[0], "alternative"), vec!["A"]);
+        assert!(!get::<bool>(&results[0], "reference_changed"));
+
+        let results = lift
+            .lift_variant(py, "chr22", 15690439, "A", vec!["G".to_string()], 3, 3)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(get::<u64>(&results[0], "position"), 16287522);
+        assert_eq!(get::<String>(&results[0], "strand"), "-");
+        assert_eq!(get::<String>(&results[0], "reference"), "T");
+        assert_eq!(get::<Vec<String>>(&results[0], "alternative"), vec!["C"]);
+    });
+}
+
+#[test]
+fn test_error() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let e = ChainFile::load("../target/test-output/py/not-found.chain").unwrap_err();
+        assert!(e.is_instance_of::<PyIOError>(py));
+
+        std::fs::create_dir_all("../target/test-output/py").unwrap();
+        std::fs::write("../target/test-output/py/invalid.chain", "chain 1 2\n").unwrap();
+        let e = ChainFile::load("../target/test-output/py/invalid.chain").unwrap_err();
+        assert!(e.is_instance_of::<PyValueError>(py));
+
+        let chain = ChainFile::load(CHAIN).unwrap();
+        let e = VariantLiftOver::new(&chain, "not-found.fa", GRCH37, true).unwrap_err();
+        assert!(e.is_instance_of::<PyIOError>(py));
+
+        let lift = PositionLiftOver::new(&chain);
+        let e = lift.lift_region(py, "chr22", 200, 100).unwrap_err();
+        assert!(e.is_instance_of::<PyValueError>(py));
+    });
+}
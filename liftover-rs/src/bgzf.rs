@@ -0,0 +1,144 @@
+//! Random access to BGZF compressed files with a `.gzi` block index.
+//!
+//! `.fai` offsets of a bgzip compressed FASTA file are offsets in uncompressed data, so
+//! [`BgzfReader`] seeks in uncompressed data and can be used with
+//! [`bio::io::fasta::IndexedReader`].
+
+use bgzip::index::BGZFIndex;
+use bgzip::read::{BGZFReader, IndexedBGZFReader};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// `.gzi` index with the first block only
+const FIRST_BLOCK_INDEX: [u8; 24] = [
+    1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// A reader of BGZF compressed data which seeks in uncompressed data.
+pub struct BgzfReader<R: Read + Seek> {
+    reader: IndexedBGZFReader<R>,
+}
+
+impl BgzfReader<BufReader<File>> {
+    /// Open a BGZF compressed file with `.gzi` index at `<path>.gzi`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut gzi_path = path.as_ref().as_os_str().to_owned();
+        gzi_path.push(".gzi");
+        let index =
+            BGZFIndex::from_reader(BufReader::new(File::open(&gzi_path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!(
+                        "Cannot open {}: {} (create it with `bgzip -r`)",
+                        Path::new(&gzi_path).display(),
+                        e
+                    ),
+                )
+            })?))?;
+        BgzfReader::new(BufReader::new(File::open(path)?), index)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    pub fn new(reader: R, index: BGZFIndex) -> io::Result<Self> {
+        // `.gzi` does not list the first block, so it is empty for data with a single block.
+        let index = if index.entries().is_empty() {
+            BGZFIndex::from_reader(&FIRST_BLOCK_INDEX[..])?
+        } else {
+            index
+        };
+        let reader = BGZFReader::new(reader).map_err(|e| e.into_io_error())?;
+        Ok(BgzfReader {
+            reader: IndexedBGZFReader::new(reader, index).map_err(|e| e.into_io_error())?,
+        })
+    }
+}
+
+impl<R: Read + Seek> fmt::Debug for BgzfReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BgzfReader").finish_non_exhaustive()
+    }
+}
+
+impl<R: Read + Seek> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R: Read + Seek> Seek for BgzfReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::GenomeSequence;
+    use bgzip::write::BGZFWriter;
+    use bgzip::Compression;
+    use bio::io::fasta::{Index, IndexedReader};
+    use std::io::{Cursor, Write};
+
+    /// Compress data into BGZF blocks of `block_size` bytes. Returns compressed data and index.
+    fn compress(data: &[u8], block_size: usize) -> io::Result<(Vec<u8>, BGZFIndex)> {
+        let mut compressed = Vec::new();
+        let mut writer = BGZFWriter::with_compress_unit_size(
+            &mut compressed,
+            Compression::default(),
+            block_size,
+            true,
+        )
+        .map_err(|e| e.into_io_error())?;
+        writer.write_all(data)?;
+        let index = writer.close()?.expect("index is created");
+        Ok((compressed, index))
+    }
+
+    #[test]
+    fn test_bgzf_reader() -> anyhow::Result<()> {
+        let data: Vec<u8> = (0..1000u32)
+            .map(|x| b"ACGT"[(x * 7 % 4) as usize])
+            .collect();
+        let (compressed, index) = compress(&data, 100)?;
+        let mut reader = BgzfReader::new(Cursor::new(compressed), index)?;
+
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        assert_eq!(buf, data);
+
+        for (start, length) in [(0, 10), (95, 10), (250, 300), (990, 10), (300, 0)] {
+            reader.seek(SeekFrom::Start(start))?;
+            let mut buf = vec![0; length];
+            reader.read_exact(&mut buf)?;
+            assert_eq!(buf, &data[start as usize..start as usize + length]);
+        }
+        reader.seek(SeekFrom::Start(1000))?;
+        assert_eq!(reader.read(&mut [0; 10])?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bgzf_fasta() -> anyhow::Result<()> {
+        let fasta = b">chr1\nACGTACGTAC\nGTTTGGCCAA\nTT\n>chr2\nCCCCCGGGGG\nAAAAATTTTT\n";
+        let fai = b"chr1\t22\t6\t10\t11\nchr2\t20\t37\t10\t11\n";
+        for block_size in [7, 1000] {
+            let (compressed, index) = compress(fasta, block_size)?;
+            let mut reader = IndexedReader::with_index(
+                BgzfReader::new(Cursor::new(compressed), index)?,
+                Index::new(&fai[..])?,
+            );
+            assert_eq!(
+                reader.get_sequence("chr1", 0, 22)?,
+                b"ACGTACGTACGTTTGGCCAATT"
+            );
+            assert_eq!(reader.get_sequence("chr2", 8, 13)?, b"GGAAA");
+            assert_eq!(reader.get_sequence("chr1", 9, 11)?, b"CG");
+            assert_eq!(reader.get_sequence("chr2", 0, 20)?, b"CCCCCGGGGGAAAAATTTTT");
+        }
+        Ok(())
+    }
+}
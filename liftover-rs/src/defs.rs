@@ -1,9 +1,13 @@
 use super::LiftOverError;
+use crate::bgzf::BgzfReader;
+use crate::twobit::TwoBitReader;
 use crate::vcfparse::VCFRecord;
-use bio::io::fasta::IndexedReader;
+use bio::io::fasta::{Index, IndexedReader};
 //use log::trace;
 use std::fmt;
-use std::io::{Read, Seek};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
 use std::str;
 
 pub trait GenomeSequence: std::fmt::Debug {
@@ -72,6 +76,71 @@ impl<R: Seek + Read + fmt::Debug> GenomeSequence for IndexedReader<R> {
     }
 }
 
+/// A genome sequence file. Indexed FASTA, bgzip compressed FASTA and UCSC 2bit files are
+/// supported.
+#[derive(Debug)]
+pub enum GenomeFile {
+    Fasta(IndexedReader<File>),
+    BgzfFasta(IndexedReader<BgzfReader<BufReader<File>>>),
+    TwoBit(TwoBitReader<BufReader<File>>),
+}
+
+impl GenomeFile {
+    /// Open a 2bit file if a file name ends with `.2bit`, a bgzip compressed FASTA file if a file
+    /// name ends with `.gz` or `.bgz`, or an indexed FASTA file otherwise. A bgzip compressed
+    /// FASTA file requires `.fai` and `.gzi` index files.
+    pub fn open<P: AsRef<Path> + fmt::Debug>(path: P) -> Result<Self, LiftOverError> {
+        let fasta_error = |e| LiftOverError::FastaOpenError(path.as_ref().display().to_string(), e);
+        match path.as_ref().extension().and_then(|x| x.to_str()) {
+            Some("2bit") => Ok(GenomeFile::TwoBit(TwoBitReader::from_file(&path)?)),
+            Some("gz") | Some("bgz") => {
+                let mut fai_path = path.as_ref().as_os_str().to_owned();
+                fai_path.push(".fai");
+                let index = Index::from_file(&fai_path).map_err(fasta_error)?;
+                Ok(GenomeFile::BgzfFasta(IndexedReader::with_index(
+                    BgzfReader::from_file(&path)?,
+                    index,
+                )))
+            }
+            _ => Ok(GenomeFile::Fasta(
+                IndexedReader::from_file(&path).map_err(fasta_error)?,
+            )),
+        }
+    }
+}
+
+impl GenomeSequence for GenomeFile {
+    fn sequence(
+        &mut self,
+        chromosome: &str,
+        start: u64,
+        stop: u64,
+        text: &mut Vec<u8>,
+    ) -> Result<(), LiftOverError> {
+        match self {
+            GenomeFile::Fasta(x) => x.sequence(chromosome, start, stop, text),
+            GenomeFile::BgzfFasta(x) => x.sequence(chromosome, start, stop, text),
+            GenomeFile::TwoBit(x) => x.sequence(chromosome, start, stop, text),
+        }
+    }
+
+    fn get_contig_list(&self) -> Vec<(String, u64)> {
+        match self {
+            GenomeFile::Fasta(x) => x.get_contig_list(),
+            GenomeFile::BgzfFasta(x) => x.get_contig_list(),
+            GenomeFile::TwoBit(x) => x.get_contig_list(),
+        }
+    }
+
+    fn get_contig_length(&self, chromosome: &str) -> Option<u64> {
+        match self {
+            GenomeFile::Fasta(x) => x.get_contig_length(chromosome),
+            GenomeFile::BgzfFasta(x) => x.get_contig_length(chromosome),
+            GenomeFile::TwoBit(x) => x.get_contig_length(chromosome),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Variant {
     pub chromosome: String,
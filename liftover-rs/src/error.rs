@@ -48,6 +48,8 @@ pub enum LiftOverError {
     QueryChromosomeLengthIsNotMatch(String),
     #[error("unknown sequence error: {0}: {1}")]
     UnknownSequenceError(String, std::io::Error),
+    #[error("Failed to open indexed FASTA: {0}: {1}")]
+    FastaOpenError(String, anyhow::Error),
     #[error("Failed to parse gene annotation")]
     GeneParseError(#[from] crate::geneparse::GeneParseError),
     #[error("Chromosome {0} is not found in FASTA")]
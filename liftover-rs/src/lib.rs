@@ -1,4 +1,5 @@
 pub mod bedparse;
+pub mod bgzf;
 pub mod chain;
 mod defs;
 mod error;
@@ -6,6 +7,7 @@ pub mod genelift;
 pub mod geneparse;
 pub mod normalize;
 pub mod poslift;
+pub mod twobit;
 pub mod variantlift;
 pub mod vcflift;
 pub mod vcfparse;
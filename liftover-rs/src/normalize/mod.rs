@@ -1,5 +1,41 @@
+mod vcf;
+
 use crate::{GenomeSequence, LiftOverError, Variant};
 use log::trace;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+pub use vcf::{
+    NormalizeReportWriter, NormalizeSummary, VCFNormalizeParameters, VCFNormalizeResult,
+    VCFNormalizer,
+};
+
+/// Names of normalization directions
+pub const NORMALIZE_DIRECTIONS: [&str; 2] = ["left", "right"];
+
+/// A direction to shift indels in repeated sequences.
+#[derive(
+    Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum NormalizeDirection {
+    /// Shift indels to the left-most (5') position. This is the VCF convention.
+    #[default]
+    Left,
+    /// Shift indels to the right-most (3') position. This is the HGVS convention.
+    Right,
+}
+
+impl FromStr for NormalizeDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(NormalizeDirection::Left),
+            "right" => Ok(NormalizeDirection::Right),
+            _ => Err(format!("Unknown normalize direction: {}", s)),
+        }
+    }
+}
 
 fn extend_to_left_if_empty_allele_exists<G: GenomeSequence>(
     chromosome: &str,
@@ -21,6 +57,61 @@ fn extend_to_left_if_empty_allele_exists<G: GenomeSequence>(
     }
 }
 
+fn extend_to_right_if_empty_allele_exists<G: GenomeSequence>(
+    chromosome: &str,
+    genome: &mut G,
+    position: u64,
+    alleles: &mut [Vec<u8>],
+) -> Result<bool, LiftOverError> {
+    let end = position + alleles[0].len() as u64;
+    let contig_length = genome
+        .get_contig_length(chromosome)
+        .ok_or_else(|| LiftOverError::ChromosomeNotFound(chromosome.to_string()))?;
+    if alleles.iter().any(|x| x.is_empty()) && end < contig_length {
+        let extend_seq = genome.get_sequence(chromosome, end, end + 1)?;
+        for one in alleles.iter_mut() {
+            if one != b"*" {
+                one.push(extend_seq[0]);
+            }
+        }
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+fn truncate_left_most_nucleotide_and_move_position(
+    position: &mut u64,
+    alleles: &mut [Vec<u8>],
+) -> bool {
+    if alleles.len() <= 1 || alleles.iter().filter(|x| x != &b"*").any(|x| x.is_empty()) {
+        return false;
+    }
+    let equal_length = alleles
+        .iter()
+        .skip(1)
+        .filter(|x| x != &b"*")
+        .map(|x| {
+            x.iter()
+                .zip(alleles[0].iter())
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+        .min();
+    if let Some(equal_length) = equal_length {
+        if equal_length > 0 {
+            for one_allele in alleles.iter_mut() {
+                if one_allele != b"*" {
+                    one_allele.drain(0..equal_length);
+                }
+            }
+            *position += equal_length as u64;
+            return true;
+        }
+    }
+    false
+}
+
 fn truncate_right_most_nucleotide_if_allele_ends_with_same(
     position: u64,
     alleles: &mut Vec<Vec<u8>>,
@@ -58,7 +149,17 @@ fn truncate_right_most_nucleotide_if_allele_ends_with_same(
 }
 
 impl Variant {
+    /// Left align and trim a variant (VCF convention).
     pub fn normalize<G: GenomeSequence>(&self, genome: &mut G) -> Result<Variant, LiftOverError> {
+        self.normalize_with_direction(genome, NormalizeDirection::Left)
+    }
+
+    /// Shift a variant to the left-most or the right-most position, and trim it.
+    pub fn normalize_with_direction<G: GenomeSequence>(
+        &self,
+        genome: &mut G,
+        direction: NormalizeDirection,
+    ) -> Result<Variant, LiftOverError> {
         // do not normalize if all alleles are equal
         if self.alternative.iter().all(|x| x == &self.reference) {
             return Ok(self.clone());
@@ -89,6 +190,16 @@ impl Variant {
             alleles.push(one.to_vec());
         }
 
+        if direction == NormalizeDirection::Right {
+            shift_to_right(&self.chromosome, genome, &mut position, &mut alleles)?;
+            return Ok(Variant {
+                chromosome: self.chromosome.clone(),
+                position,
+                reference: alleles.remove(0),
+                alternative: alleles,
+            });
+        }
+
         extend_to_left_if_empty_allele_exists(
             &self.chromosome,
             genome,
@@ -181,5 +292,52 @@ impl Variant {
     }
 }
 
+/// Shift alleles to the right-most position, and add a padding base before indels.
+fn shift_to_right<G: GenomeSequence>(
+    chromosome: &str,
+    genome: &mut G,
+    position: &mut u64,
+    alleles: &mut Vec<Vec<u8>>,
+) -> Result<(), LiftOverError> {
+    loop {
+        trace!("normalize looping {:?} {:?}", position, alleles);
+        let truncated = truncate_left_most_nucleotide_and_move_position(position, alleles);
+        let extended =
+            extend_to_right_if_empty_allele_exists(chromosome, genome, *position, alleles)?;
+        if !truncated && !extended {
+            break;
+        }
+    }
+
+    let minimum_length = alleles
+        .iter()
+        .filter(|x| x != &b"*")
+        .map(|x| x.len())
+        .min()
+        .unwrap();
+    let common_suffix_length = (0..minimum_length)
+        .take_while(|i| {
+            alleles
+                .iter()
+                .skip(1)
+                .filter(|x| x != &b"*")
+                .all(|x| x[x.len() - 1 - *i] == alleles[0][alleles[0].len() - 1 - *i])
+        })
+        .count();
+    // keep a padding base after indels if no base exists before them
+    let common_suffix_length = if *position == 0 {
+        common_suffix_length.min(minimum_length.saturating_sub(1))
+    } else {
+        common_suffix_length
+    };
+    for one_allele in alleles.iter_mut() {
+        if one_allele != b"*" {
+            one_allele.truncate(one_allele.len() - common_suffix_length);
+        }
+    }
+    extend_to_left_if_empty_allele_exists(chromosome, genome, position, alleles)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod test;
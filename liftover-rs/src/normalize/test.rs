@@ -303,3 +303,54 @@ fn test_truncate_left_most_nucleotide_if_allele_starts_with_same2() {
         }
     );
 }
+
+#[test]
+fn test_normalize_direction() -> anyhow::Result<()> {
+    // 0-based positions:   012345678901234567890123
+    let fasta = b">chr1\nGATCTTTTTTGCAGCAGCAGTACG\n";
+    let fai = b"chr1\t24\t6\t24\t25\n";
+    let mut genome = IndexedReader::new(std::io::Cursor::new(&fasta[..]), &fai[..])?;
+
+    for (original, left, right) in [
+        (
+            Variant::new("chr1", 3, b"CT", &[b"C"]),
+            Variant::new("chr1", 3, b"CT", &[b"C"]),
+            Variant::new("chr1", 8, b"TT", &[b"T"]),
+        ),
+        (
+            Variant::new("chr1", 13, b"G", &[b"GCAG"]),
+            Variant::new("chr1", 9, b"T", &[b"TGCA"]),
+            Variant::new("chr1", 19, b"G", &[b"GCAG"]),
+        ),
+        (
+            Variant::new("chr1", 22, b"CG", &[b"C"]),
+            Variant::new("chr1", 22, b"CG", &[b"C"]),
+            Variant::new("chr1", 22, b"CG", &[b"C"]),
+        ),
+        (
+            Variant::new("chr1", 0, b"GA", &[b"A"]),
+            Variant::new("chr1", 0, b"GA", &[b"A"]),
+            Variant::new("chr1", 0, b"GA", &[b"A"]),
+        ),
+        (
+            Variant::new("chr1", 20, b"TAC", &[b"CAT"]),
+            Variant::new("chr1", 20, b"TAC", &[b"CAT"]),
+            Variant::new("chr1", 20, b"TAC", &[b"CAT"]),
+        ),
+    ] {
+        assert_eq!(original.normalize(&mut genome)?, left);
+        assert_eq!(
+            original.normalize_with_direction(&mut genome, NormalizeDirection::Right)?,
+            right
+        );
+        assert_eq!(
+            left.normalize_with_direction(&mut genome, NormalizeDirection::Right)?,
+            right
+        );
+        assert_eq!(
+            right.normalize_with_direction(&mut genome, NormalizeDirection::Left)?,
+            left
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,637 @@
+//! Normalize, split and decompose records of a VCF file.
+
+use super::NormalizeDirection;
+use crate::vcflift::{
+    is_breakend_allele, is_symbolic_allele, split_record, SortingWriter, VCFHeaderRewriteTarget,
+    VCFLiftOverParameters,
+};
+use crate::vcfparse::{
+    CompleteVCFRecord, PartialVCFRecord, VCFHeader, VCFHeaderItem, VCFReader, VCFRecord, VCFWriter,
+};
+use crate::{GenomeSequence, LiftOverError, Variant};
+use log::info;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{self, prelude::*};
+use std::str;
+
+const REF_MISMATCH: &str = "REF_MISMATCH";
+const UNKNOWN_CONTIG: &str = "UNKNOWN_CONTIG";
+const NORMALIZED: &str = "NORMALIZED";
+const SPLIT: &str = "SPLIT";
+const DECOMPOSED: &str = "DECOMPOSED";
+
+static NORMALIZE_VCF_HEADER: Lazy<VCFHeaderItem> = Lazy::new(|| {
+    VCFHeaderItem::parse(b"##INFO=<ID=OLD_VARIANT,Number=1,Type=String,Description=\"Original CHROM:POS:REF/ALT before normalization\">", 0).unwrap()
+});
+
+static NORMALIZE_FAILED_VCF_HEADER: Lazy<VCFHeaderItem> = Lazy::new(|| {
+    VCFHeaderItem::parse(b"##INFO=<ID=FAILED_REASON,Number=.,Type=String,Description=\"Reason of normalization failure\">", 0).unwrap()
+});
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
+pub struct VCFNormalizeParameters {
+    pub split_multi_allelic: bool,
+    pub decompose: bool,
+    pub sort: bool,
+    pub sort_buffer_size: usize,
+    pub direction: NormalizeDirection,
+}
+
+impl VCFNormalizeParameters {
+    pub fn new() -> Self {
+        VCFNormalizeParameters {
+            split_multi_allelic: false,
+            decompose: false,
+            sort: false,
+            sort_buffer_size: 512 * 1024 * 1024,
+            direction: NormalizeDirection::Left,
+        }
+    }
+
+    /// Split multi-allelic records into bi-allelic records before normalization.
+    pub fn split_multi_allelic(mut self, split_multi_allelic: bool) -> Self {
+        self.split_multi_allelic = split_multi_allelic;
+        self
+    }
+
+    /// Decompose bi-allelic MNPs and complex variants into SNVs and indels.
+    pub fn decompose(mut self, decompose: bool) -> Self {
+        self.decompose = decompose;
+        self
+    }
+
+    /// Sort records by contig order in the header and position.
+    pub fn sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Maximum size of records in memory while sorting.
+    pub fn sort_buffer_size(mut self, sort_buffer_size: usize) -> Self {
+        self.sort_buffer_size = sort_buffer_size;
+        self
+    }
+
+    /// Shift indels to the left-most or the right-most position.
+    pub fn direction(mut self, direction: NormalizeDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl Default for VCFNormalizeParameters {
+    fn default() -> Self {
+        VCFNormalizeParameters::new()
+    }
+}
+
+/// Summary of `VCFNormalizer::normalize_vcf`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NormalizeSummary {
+    pub total: u64,
+    /// records which were written without any change
+    pub unchanged: u64,
+    /// records which were left aligned or trimmed
+    pub normalized: u64,
+    pub split: u64,
+    pub decomposed: u64,
+    pub ref_mismatch: u64,
+    pub unknown_contig: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VCFNormalizeResult<'a> {
+    /// Normalized records with kinds of changes
+    Succeeded(Vec<(CompleteVCFRecord<'a>, Vec<&'static str>)>),
+    Failed(&'static str),
+}
+
+/// Write one line per changed or failed record.
+pub struct NormalizeReportWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> NormalizeReportWriter<W> {
+    pub fn new(writer: W) -> Result<Self, LiftOverError> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .quote_style(csv::QuoteStyle::Never)
+            .escape(b'\\')
+            .from_writer(writer);
+        writer.write_record([
+            "Line",
+            "Chromosome",
+            "Position",
+            "Reference",
+            "Alternative",
+            "New position",
+            "New reference",
+            "New alternative",
+            "Changes",
+        ])?;
+        Ok(NormalizeReportWriter { writer })
+    }
+
+    pub fn write<R: VCFRecord>(
+        &mut self,
+        line: u32,
+        record: &PartialVCFRecord,
+        new_record: Option<&R>,
+        changes: &[&str],
+    ) -> Result<(), LiftOverError> {
+        let missing: &[u8] = b".";
+        self.writer.write_record([
+            line.to_string().as_bytes(),
+            record.contig(),
+            record.position().to_string().as_bytes(),
+            record.reference(),
+            &record.alternative().join(&b","[..]),
+            new_record
+                .map(|x| x.position().to_string().into_bytes())
+                .unwrap_or_else(|| missing.to_vec())
+                .as_slice(),
+            new_record.map(|x| x.reference()).unwrap_or(missing),
+            &new_record
+                .map(|x| x.alternative().join(&b","[..]))
+                .unwrap_or_else(|| missing.to_vec()),
+            changes.join(",").as_bytes(),
+        ])?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), LiftOverError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct VCFNormalizer<G: GenomeSequence> {
+    genome: G,
+    param: VCFNormalizeParameters,
+}
+
+impl<G: GenomeSequence> VCFNormalizer<G> {
+    pub fn new(genome: G, param: VCFNormalizeParameters) -> Self {
+        VCFNormalizer { genome, param }
+    }
+
+    pub fn normalize_header(&self, header: &VCFHeader) -> VCFHeader {
+        let mut header = header.clone();
+        header.header_items.push(NORMALIZE_VCF_HEADER.clone());
+        header
+    }
+
+    /// Check REF with the genome, split the record if `split_multi_allelic` is set, and normalize
+    /// each record. Records with symbolic alleles or breakends are returned as is.
+    pub fn normalize_record<'a>(
+        &mut self,
+        record: &PartialVCFRecord<'a>,
+        split_target: &VCFHeaderRewriteTarget,
+    ) -> Result<VCFNormalizeResult<'a>, LiftOverError> {
+        let chromosome = str::from_utf8(record.contig()).unwrap();
+        let contig_length = if let Some(length) = self.genome.get_contig_length(chromosome) {
+            length
+        } else {
+            return Ok(VCFNormalizeResult::Failed(UNKNOWN_CONTIG));
+        };
+        let start = record.position() - 1;
+        let end = start + record.reference().len() as u64;
+        if end > contig_length
+            || self.genome.get_sequence(chromosome, start, end)?
+                != record.reference().to_ascii_uppercase()
+        {
+            return Ok(VCFNormalizeResult::Failed(REF_MISMATCH));
+        }
+
+        let record = record.clone().complete_parse()?;
+        if record
+            .alternative
+            .iter()
+            .any(|x| is_symbolic_allele(x) || is_breakend_allele(x))
+        {
+            return Ok(VCFNormalizeResult::Succeeded(vec![(record, vec![])]));
+        }
+
+        let split = if self.param.split_multi_allelic && record.alternative.len() > 1 {
+            split_record(&record, split_target)
+                .into_iter()
+                .map(|x| (x, vec![SPLIT]))
+                .collect()
+        } else {
+            vec![(record, vec![])]
+        };
+
+        let mut results = Vec::new();
+        for (one, changes) in split {
+            let decomposed = if self.param.decompose && one.alternative.len() == 1 {
+                decompose_record(&one)
+            } else {
+                None
+            };
+            let pieces = if let Some(decomposed) = decomposed {
+                decomposed
+                    .into_iter()
+                    .map(|x| {
+                        let mut changes = changes.clone();
+                        changes.push(DECOMPOSED);
+                        (x, changes)
+                    })
+                    .collect()
+            } else {
+                vec![(one, changes)]
+            };
+            for (mut piece, mut changes) in pieces {
+                if self.normalize_alleles(&mut piece)? {
+                    changes.push(NORMALIZED);
+                }
+                results.push((piece, changes));
+            }
+        }
+        Ok(VCFNormalizeResult::Succeeded(results))
+    }
+
+    /// Left align and trim alleles. Returns true if the record is changed.
+    fn normalize_alleles(&mut self, record: &mut CompleteVCFRecord) -> Result<bool, LiftOverError> {
+        if record.alternative.iter().any(|x| x.as_ref() == b".") {
+            return Ok(false);
+        }
+        let mut variant: Variant = (&*record).into();
+        variant.reference.make_ascii_uppercase();
+        let normalized =
+            variant.normalize_with_direction(&mut self.genome, self.param.direction)?;
+        if normalized == variant {
+            return Ok(false);
+        }
+        record.position = normalized.position + 1;
+        record.reference = Cow::Owned(normalized.reference);
+        record.alternative = normalized.alternative.into_iter().map(Cow::Owned).collect();
+        Ok(true)
+    }
+
+    pub fn normalize_vcf<R: Read, W1: Write, W2: Write, W3: Write>(
+        &mut self,
+        reader: R,
+        writer: W1,
+        failed_writer: Option<W2>,
+        report_writer: Option<W3>,
+    ) -> Result<NormalizeSummary, LiftOverError> {
+        let mut vcf_reader = VCFReader::new(reader)?;
+        let header = self.normalize_header(&vcf_reader.header);
+        let mut failed_writer = failed_writer
+            .map(|x| {
+                let mut failed_header = vcf_reader.header.clone();
+                failed_header
+                    .header_items
+                    .push(NORMALIZE_FAILED_VCF_HEADER.clone());
+                VCFWriter::new(x, failed_header)
+            })
+            .transpose()?;
+        let mut report = report_writer.map(NormalizeReportWriter::new).transpose()?;
+
+        let summary = if self.param.sort {
+            let contigs: Vec<_> = header
+                .header_items
+                .iter()
+                .filter(|x| x.key == b"contig")
+                .filter_map(|x| x.detail.get(&b"ID"[..]).cloned())
+                .collect();
+            let mut vcf_writer = VCFWriter::new(
+                SortingWriter::new(
+                    io::BufWriter::new(writer),
+                    contigs,
+                    self.param.sort_buffer_size,
+                ),
+                header,
+            )?;
+            let summary = self.normalize_vcf_records(
+                &mut vcf_reader,
+                &mut vcf_writer,
+                &mut failed_writer,
+                &mut report,
+            )?;
+            vcf_writer
+                .into_inner()
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .finish()?
+                .flush()?;
+            summary
+        } else {
+            let mut vcf_writer = VCFWriter::new(writer, header)?;
+            let summary = self.normalize_vcf_records(
+                &mut vcf_reader,
+                &mut vcf_writer,
+                &mut failed_writer,
+                &mut report,
+            )?;
+            vcf_writer.into_inner().flush()?;
+            summary
+        };
+        if let Some(failed_writer) = failed_writer {
+            failed_writer.into_inner().flush()?;
+        }
+        if let Some(report) = report.as_mut() {
+            report.flush()?;
+        }
+
+        eprintln!("         Total record: {}", summary.total);
+        eprintln!("     Unchanged record: {}", summary.unchanged);
+        eprintln!("    Normalized record: {}", summary.normalized);
+        eprintln!("         Split record: {}", summary.split);
+        eprintln!("    Decomposed record: {}", summary.decomposed);
+        eprintln!("  REF mismatch record: {}", summary.ref_mismatch);
+        eprintln!("Unknown contig record: {}", summary.unknown_contig);
+
+        Ok(summary)
+    }
+
+    fn normalize_vcf_records<R: Read, W1: Write, W2: Write, W3: Write>(
+        &mut self,
+        vcf_reader: &mut VCFReader<io::BufReader<R>>,
+        vcf_writer: &mut VCFWriter<W1>,
+        failed_writer: &mut Option<VCFWriter<W2>>,
+        report: &mut Option<NormalizeReportWriter<W3>>,
+    ) -> Result<NormalizeSummary, LiftOverError> {
+        // Number of values of INFO/FORMAT are required to split records.
+        let split_target =
+            VCFHeaderRewriteTarget::new(&vcf_reader.header, &VCFLiftOverParameters::new());
+        let mut summary = NormalizeSummary::default();
+
+        while let Some(record) = vcf_reader.next_record()? {
+            summary.total += 1;
+            match self.normalize_record(&record, &split_target)? {
+                VCFNormalizeResult::Succeeded(results) => {
+                    let changes: Vec<_> = results.iter().flat_map(|x| x.1.iter()).collect();
+                    if changes.is_empty() {
+                        summary.unchanged += 1;
+                    }
+                    if changes.contains(&&NORMALIZED) {
+                        summary.normalized += 1;
+                    }
+                    if changes.contains(&&SPLIT) {
+                        summary.split += 1;
+                    }
+                    if changes.contains(&&DECOMPOSED) {
+                        summary.decomposed += 1;
+                    }
+
+                    for (mut one, changes) in results {
+                        if !changes.is_empty() {
+                            if let Some(report) = report.as_mut() {
+                                report.write(record.line, &record, Some(&one), &changes)?;
+                            }
+                            one.info.push((
+                                Cow::Borrowed(b"OLD_VARIANT"),
+                                vec![Cow::Owned(old_variant(&record))],
+                            ));
+                        }
+                        vcf_writer.write_record(&one)?;
+                    }
+                }
+                VCFNormalizeResult::Failed(reason) => {
+                    if reason == REF_MISMATCH {
+                        summary.ref_mismatch += 1;
+                    } else {
+                        summary.unknown_contig += 1;
+                    }
+                    if let Some(report) = report.as_mut() {
+                        report.write::<PartialVCFRecord>(record.line, &record, None, &[reason])?;
+                    }
+                    if let Some(failed_writer) = failed_writer.as_mut() {
+                        let mut failed = record.clone();
+                        let info = failed.unparsed_info.to_mut();
+                        if info == b"." {
+                            info.clear();
+                        } else {
+                            info.push(b';');
+                        }
+                        write!(info, "FAILED_REASON={}", reason)?;
+                        failed_writer.write_record(&failed)?;
+                    } else {
+                        vcf_writer.write_record(&record)?;
+                    }
+                }
+            }
+
+            if summary.total % 1_000_000 == 0 {
+                info!(
+                    "Processed {} entries at {}:{}",
+                    summary.total,
+                    str::from_utf8(record.contig()).unwrap(),
+                    record.position()
+                );
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Format a record as `CHROM:POS:REF/ALT`. Multiple ALT alleles are separated with `/`.
+fn old_variant<R: VCFRecord>(record: &R) -> Vec<u8> {
+    let mut value = Vec::new();
+    value.extend_from_slice(record.contig());
+    write!(value, ":{}:", record.position()).unwrap();
+    value.extend_from_slice(record.reference());
+    for one in record.alternative() {
+        value.push(b'/');
+        value.extend_from_slice(one);
+    }
+    value
+}
+
+/// An offset from the first base, REF and ALT of a primitive variant
+type Primitive = (usize, Vec<u8>, Vec<u8>);
+
+/// Decompose alleles into SNVs and one indel. Returns `None` if alleles are already primitive.
+fn decompose_alleles(reference: &[u8], alternative: &[u8]) -> Option<Vec<Primitive>> {
+    if alternative == b"*"
+        || reference.is_empty()
+        || alternative.is_empty()
+        || (reference.len() == 1 && alternative.len() == 1)
+    {
+        return None;
+    }
+
+    // keep one base to represent an indel
+    let suffix = reference
+        .iter()
+        .rev()
+        .zip(alternative.iter().rev())
+        .take(reference.len().min(alternative.len()) - 1)
+        .take_while(|(x, y)| x == y)
+        .count();
+    let reference = &reference[..reference.len() - suffix];
+    let alternative = &alternative[..alternative.len() - suffix];
+    let length = reference.len().min(alternative.len());
+
+    let mut primitives: Vec<_> = (0..length)
+        .filter(|i| reference[*i] != alternative[*i])
+        .map(|i| (i, vec![reference[i]], vec![alternative[i]]))
+        .collect();
+    if reference.len() != alternative.len() {
+        let mut indel_alternative = vec![reference[length - 1]];
+        indel_alternative.extend_from_slice(&alternative[length..]);
+        primitives.push((
+            length - 1,
+            reference[length - 1..].to_vec(),
+            indel_alternative,
+        ));
+    }
+
+    // a single indel is left to normalization
+    if primitives.is_empty() || (primitives.len() == 1 && reference.len() != alternative.len()) {
+        return None;
+    }
+    Some(primitives)
+}
+
+/// Decompose a bi-allelic record into primitive records. Genotypes and INFO are kept as is.
+fn decompose_record<'a>(record: &CompleteVCFRecord<'a>) -> Option<Vec<CompleteVCFRecord<'a>>> {
+    decompose_alleles(&record.reference, &record.alternative[0]).map(|primitives| {
+        primitives
+            .into_iter()
+            .map(|(offset, reference, alternative)| {
+                let mut one = record.clone();
+                one.position += offset as u64;
+                one.reference = Cow::Owned(reference);
+                one.alternative = vec![Cow::Owned(alternative)];
+                one
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bio::io::fasta::IndexedReader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_decompose_alleles() {
+        assert_eq!(decompose_alleles(b"A", b"G"), None);
+        assert_eq!(decompose_alleles(b"AT", b"A"), None);
+        assert_eq!(decompose_alleles(b"A", b"AT"), None);
+        assert_eq!(
+            decompose_alleles(b"A", b"GT"),
+            Some(vec![
+                (0, b"A".to_vec(), b"G".to_vec()),
+                (0, b"A".to_vec(), b"AT".to_vec()),
+            ])
+        );
+        assert_eq!(
+            decompose_alleles(b"ACG", b"AGT"),
+            Some(vec![
+                (1, b"C".to_vec(), b"G".to_vec()),
+                (2, b"G".to_vec(), b"T".to_vec()),
+            ])
+        );
+        assert_eq!(
+            decompose_alleles(b"ACGT", b"TA"),
+            Some(vec![
+                (0, b"A".to_vec(), b"T".to_vec()),
+                (1, b"C".to_vec(), b"A".to_vec()),
+                (1, b"CGT".to_vec(), b"C".to_vec()),
+            ])
+        );
+        assert_eq!(
+            decompose_alleles(b"AC", b"GTT"),
+            Some(vec![
+                (0, b"A".to_vec(), b"G".to_vec()),
+                (1, b"C".to_vec(), b"T".to_vec()),
+                (1, b"C".to_vec(), b"CT".to_vec()),
+            ])
+        );
+        // common suffix is removed
+        assert_eq!(
+            decompose_alleles(b"ACCT", b"GT"),
+            Some(vec![
+                (0, b"A".to_vec(), b"G".to_vec()),
+                (0, b"ACC".to_vec(), b"A".to_vec()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_normalize_vcf() -> Result<(), LiftOverError> {
+        // 1-based positions:   123456789012345678901234
+        let fasta = b">chr1\nGATCTTTTTTGCAGCAGCAGTACG\n";
+        let fai = b"chr1\t24\t6\t24\t25\n";
+        let genome = IndexedReader::new(Cursor::new(&fasta[..]), &fai[..])?;
+        let mut normalizer = VCFNormalizer::new(
+            genome,
+            VCFNormalizeParameters::new()
+                .split_multi_allelic(true)
+                .decompose(true),
+        );
+
+        let input = b"##fileformat=VCFv4.2
+##contig=<ID=chr1,length=24>
+##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1
+chr1\t1\tsnv\tG\tA\t.\t.\tAF=0.1\tGT\t0/1
+chr1\t9\tdel\tTT\tT\t.\t.\tAF=0.1\tGT\t0/1
+chr1\t17\tins\tG\tGCAG\t.\t.\tAF=0.1\tGT\t0/1
+chr1\t11\tmulti\tG\tA,GC\t.\t.\tAF=0.1,0.2\tGT\t1/2
+chr1\t21\tmnp\tTAC\tCAT\t.\t.\t.\tGT\t0/1
+chr1\t2\tbad\tC\tT\t.\t.\t.\tGT\t0/1
+chr2\t2\tunknown\tC\tT\t.\t.\t.\tGT\t0/1
+";
+        let mut output = Vec::new();
+        let mut failed = Vec::new();
+        let mut report = Vec::new();
+        let summary = normalizer.normalize_vcf(
+            &input[..],
+            &mut output,
+            Some(&mut failed),
+            Some(&mut report),
+        )?;
+        let output = String::from_utf8(output).unwrap();
+        let records: Vec<_> = output.lines().filter(|x| !x.starts_with('#')).collect();
+        assert_eq!(
+            records,
+            vec![
+                "chr1\t1\tsnv\tG\tA\t.\t.\tAF=0.1\tGT\t0/1",
+                "chr1\t4\tdel\tCT\tC\t.\t.\tAF=0.1;OLD_VARIANT=chr1:9:TT/T\tGT\t0/1",
+                "chr1\t10\tins\tT\tTGCA\t.\t.\tAF=0.1;OLD_VARIANT=chr1:17:G/GCAG\tGT\t0/1",
+                "chr1\t11\tmulti\tG\tA\t.\t.\tAF=0.1;OLD_VARIANT=chr1:11:G/A/GC\tGT\t0/1",
+                "chr1\t11\tmulti\tG\tGC\t.\t.\tAF=0.2;OLD_VARIANT=chr1:11:G/A/GC\tGT\t0/1",
+                "chr1\t21\tmnp\tT\tC\t.\t.\tOLD_VARIANT=chr1:21:TAC/CAT\tGT\t0/1",
+                "chr1\t23\tmnp\tC\tT\t.\t.\tOLD_VARIANT=chr1:21:TAC/CAT\tGT\t0/1",
+            ]
+        );
+        assert_eq!(
+            summary,
+            NormalizeSummary {
+                total: 7,
+                unchanged: 1,
+                normalized: 2,
+                split: 1,
+                decomposed: 1,
+                ref_mismatch: 1,
+                unknown_contig: 1,
+            }
+        );
+        let failed = String::from_utf8(failed).unwrap();
+        assert_eq!(
+            failed
+                .lines()
+                .filter(|x| !x.starts_with('#'))
+                .collect::<Vec<_>>(),
+            vec![
+                "chr1\t2\tbad\tC\tT\t.\t.\tFAILED_REASON=REF_MISMATCH\tGT\t0/1",
+                "chr2\t2\tunknown\tC\tT\t.\t.\tFAILED_REASON=UNKNOWN_CONTIG\tGT\t0/1",
+            ]
+        );
+        let report = String::from_utf8(report).unwrap();
+        assert_eq!(report.lines().count(), 9);
+        assert!(report.contains("7\tchr1\t9\tTT\tT\t4\tCT\tC\tNORMALIZED\n"));
+
+        Ok(())
+    }
+}
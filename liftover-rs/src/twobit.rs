@@ -0,0 +1,319 @@
+//! Read UCSC 2bit genome files.
+//!
+//! See <https://genome.ucsc.edu/FAQ/FAQformat.html#format7> for the file format.
+
+use crate::{GenomeSequence, LiftOverError};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const TWOBIT_SIGNATURE: u32 = 0x1A41_2743;
+const BASES: [u8; 4] = [b'T', b'C', b'A', b'G'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TwoBitSequence {
+    name: String,
+    offset: u64,
+    length: u64,
+}
+
+/// Header of a sequence record
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TwoBitRecord {
+    /// start and end positions of N blocks
+    n_blocks: Vec<(u64, u64)>,
+    /// offset of packed DNA
+    dna_offset: u64,
+}
+
+/// A reader of UCSC 2bit genome files.
+///
+/// Bases in N blocks are returned as `N`. Soft-masked bases are returned in upper case like
+/// sequences from indexed FASTA files.
+#[derive(Debug)]
+pub struct TwoBitReader<R: Read + Seek> {
+    reader: R,
+    big_endian: bool,
+    sequences: Vec<TwoBitSequence>,
+    /// index and header of the last read sequence
+    current: Option<(usize, TwoBitRecord)>,
+}
+
+impl TwoBitReader<BufReader<File>> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, LiftOverError> {
+        TwoBitReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> TwoBitReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, LiftOverError> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        let big_endian = if u32::from_le_bytes(buf) == TWOBIT_SIGNATURE {
+            false
+        } else if u32::from_be_bytes(buf) == TWOBIT_SIGNATURE {
+            true
+        } else {
+            return Err(invalid_data("Invalid 2bit signature").into());
+        };
+        let mut twobit = TwoBitReader {
+            reader,
+            big_endian,
+            sequences: Vec::new(),
+            current: None,
+        };
+
+        // version 1 uses 64 bit offsets
+        let long_offset = match twobit.read_u32()? {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid_data("Unsupported 2bit version").into()),
+        };
+        let sequence_count = twobit.read_u32()?;
+        twobit.read_u32()?;
+
+        for _ in 0..sequence_count {
+            let mut name_size = [0; 1];
+            twobit.reader.read_exact(&mut name_size)?;
+            let mut name = vec![0; name_size[0] as usize];
+            twobit.reader.read_exact(&mut name)?;
+            let offset = if long_offset {
+                twobit.read_u64()?
+            } else {
+                twobit.read_u32()? as u64
+            };
+            twobit.sequences.push(TwoBitSequence {
+                name: String::from_utf8(name).map_err(|_| invalid_data("Invalid sequence name"))?,
+                offset,
+                length: 0,
+            });
+        }
+
+        for i in 0..twobit.sequences.len() {
+            twobit
+                .reader
+                .seek(SeekFrom::Start(twobit.sequences[i].offset))?;
+            twobit.sequences[i].length = twobit.read_u32()? as u64;
+        }
+
+        Ok(twobit)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(buf)
+        } else {
+            u32::from_le_bytes(buf)
+        })
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(buf)
+        } else {
+            u64::from_le_bytes(buf)
+        })
+    }
+
+    fn read_u32_list(&mut self, count: usize) -> io::Result<Vec<u64>> {
+        (0..count).map(|_| Ok(self.read_u32()? as u64)).collect()
+    }
+
+    fn read_record(&mut self, index: usize) -> io::Result<TwoBitRecord> {
+        self.reader
+            .seek(SeekFrom::Start(self.sequences[index].offset + 4))?;
+        let n_block_count = self.read_u32()? as usize;
+        let n_block_starts = self.read_u32_list(n_block_count)?;
+        let n_block_sizes = self.read_u32_list(n_block_count)?;
+        let mask_block_count = self.read_u32()? as i64;
+        // skip mask block starts, mask block sizes and a reserved field
+        let dna_offset = self
+            .reader
+            .seek(SeekFrom::Current(mask_block_count * 8 + 4))?;
+        Ok(TwoBitRecord {
+            n_blocks: n_block_starts
+                .into_iter()
+                .zip(n_block_sizes)
+                .map(|(start, size)| (start, start + size))
+                .collect(),
+            dna_offset,
+        })
+    }
+
+    fn fetch(&mut self, chromosome: &str, start: u64, stop: u64) -> io::Result<Vec<u8>> {
+        let index = self
+            .sequences
+            .iter()
+            .position(|x| x.name == chromosome)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Unknown sequence name"))?;
+        if start > stop || stop > self.sequences[index].length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "2bit read interval was out of bounds",
+            ));
+        }
+        if self.current.as_ref().map(|x| x.0) != Some(index) {
+            let record = self.read_record(index)?;
+            self.current = Some((index, record));
+        }
+        let dna_offset = self.current.as_ref().unwrap().1.dna_offset;
+
+        let mut packed = vec![0; (stop.div_ceil(4) - start / 4) as usize];
+        self.reader.seek(SeekFrom::Start(dna_offset + start / 4))?;
+        self.reader.read_exact(&mut packed)?;
+
+        let mut sequence: Vec<u8> = (start..stop)
+            .map(|i| {
+                let byte = packed[(i / 4 - start / 4) as usize];
+                BASES[((byte >> (6 - 2 * (i % 4))) & 3) as usize]
+            })
+            .collect();
+        let record = &self.current.as_ref().unwrap().1;
+        for (block_start, block_end) in record.n_blocks.iter() {
+            let overlap_start = (*block_start).max(start);
+            let overlap_end = (*block_end).min(stop);
+            if overlap_start < overlap_end {
+                sequence[(overlap_start - start) as usize..(overlap_end - start) as usize]
+                    .fill(b'N');
+            }
+        }
+        Ok(sequence)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<R: Read + Seek + std::fmt::Debug> GenomeSequence for TwoBitReader<R> {
+    fn sequence(
+        &mut self,
+        chromosome: &str,
+        start: u64,
+        stop: u64,
+        text: &mut Vec<u8>,
+    ) -> Result<(), LiftOverError> {
+        let sequence = self
+            .fetch(chromosome, start, stop)
+            .map_err(|e| LiftOverError::UnknownSequenceError(chromosome.to_string(), e))?;
+        text.clear();
+        text.extend_from_slice(&sequence);
+        Ok(())
+    }
+
+    fn get_contig_list(&self) -> Vec<(String, u64)> {
+        self.sequences
+            .iter()
+            .map(|x| (x.name.clone(), x.length))
+            .collect()
+    }
+
+    fn get_contig_length(&self, chromosome: &str) -> Option<u64> {
+        self.sequences
+            .iter()
+            .find(|x| x.name == chromosome)
+            .map(|x| x.length)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Encode sequences into 2bit format. `N` is written as N blocks, and lower case bases are
+    /// written as mask blocks.
+    fn encode(sequences: &[(&str, &[u8])]) -> Vec<u8> {
+        fn blocks(sequence: &[u8], f: impl Fn(u8) -> bool) -> Vec<(u32, u32)> {
+            let mut blocks: Vec<(u32, u32)> = Vec::new();
+            for (i, x) in sequence.iter().enumerate() {
+                if !f(*x) {
+                    continue;
+                }
+                match blocks.last_mut() {
+                    Some((start, size)) if *start + *size == i as u32 => *size += 1,
+                    _ => blocks.push((i as u32, 1)),
+                }
+            }
+            blocks
+        }
+
+        let mut records = Vec::new();
+        for (_, sequence) in sequences {
+            let mut record = Vec::new();
+            record.extend_from_slice(&(sequence.len() as u32).to_le_bytes());
+            for blocks in [
+                blocks(sequence, |x| x == b'N'),
+                blocks(sequence, |x| x.is_ascii_lowercase()),
+            ] {
+                record.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+                for (start, _) in blocks.iter() {
+                    record.extend_from_slice(&start.to_le_bytes());
+                }
+                for (_, size) in blocks.iter() {
+                    record.extend_from_slice(&size.to_le_bytes());
+                }
+            }
+            record.extend_from_slice(&0u32.to_le_bytes());
+            for chunk in sequence.chunks(4) {
+                let mut byte = 0u8;
+                for (i, x) in chunk.iter().enumerate() {
+                    let code = BASES
+                        .iter()
+                        .position(|y| *y == x.to_ascii_uppercase())
+                        .unwrap_or(0);
+                    byte |= (code as u8) << (6 - 2 * i);
+                }
+                record.push(byte);
+            }
+            records.push(record);
+        }
+
+        let mut data = Vec::new();
+        for x in [TWOBIT_SIGNATURE, 0, sequences.len() as u32, 0] {
+            data.extend_from_slice(&x.to_le_bytes());
+        }
+        let mut offset = 16
+            + sequences
+                .iter()
+                .map(|(name, _)| 1 + name.len() + 4)
+                .sum::<usize>();
+        for ((name, _), record) in sequences.iter().zip(records.iter()) {
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += record.len();
+        }
+        for record in records {
+            data.extend_from_slice(&record);
+        }
+        data
+    }
+
+    #[test]
+    fn test_twobit_reader() -> Result<(), LiftOverError> {
+        let data = encode(&[("chr1", b"ACGTNNNNacgtTTGCAa"), ("chr2", b"NNGATTACA")]);
+        let mut twobit = TwoBitReader::new(Cursor::new(data))?;
+        assert_eq!(
+            twobit.get_contig_list(),
+            vec![("chr1".to_string(), 18), ("chr2".to_string(), 9)]
+        );
+        assert_eq!(twobit.get_contig_length("chr2"), Some(9));
+        assert_eq!(twobit.get_contig_length("chr3"), None);
+
+        assert_eq!(twobit.get_sequence("chr1", 0, 18)?, b"ACGTNNNNACGTTTGCAA");
+        assert_eq!(twobit.get_sequence("chr1", 3, 9)?, b"TNNNNA");
+        assert_eq!(twobit.get_sequence("chr2", 1, 7)?, b"NGATTA");
+        assert_eq!(twobit.get_sequence("chr1", 17, 18)?, b"A");
+        assert_eq!(twobit.get_sequence("chr1", 5, 5)?, b"");
+        assert!(twobit.get_sequence("chr1", 10, 19).is_err());
+        assert!(twobit.get_sequence("chr3", 0, 1).is_err());
+
+        assert!(TwoBitReader::new(Cursor::new(b"hoge".to_vec())).is_err());
+        Ok(())
+    }
+}
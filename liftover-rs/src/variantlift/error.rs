@@ -2,17 +2,23 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum VariantLiftOverError {
-    #[error("Unacceptable large deletion")]
+    #[error("Unacceptable large deletion ({} bp in chain {})", gap_size, chain_id)]
     UnacceptableLargeDeletion {
         chromosome: String,
         start: u64,
         end: u64,
+        chain_id: String,
+        /// length of the largest deleted gap in the original assembly
+        gap_size: u64,
     },
-    #[error("Unacceptable large insertion")]
+    #[error("Unacceptable large insertion ({} bp in chain {})", gap_size, chain_id)]
     UnacceptableLargeInsertion {
         chromosome: String,
         start: u64,
         end: u64,
+        chain_id: String,
+        /// length of the largest inserted gap in the new assembly
+        gap_size: u64,
     },
     #[error("Unknown sequence name: {}", _0)]
     UnknownSequenceName(String),
@@ -1,4 +1,5 @@
 use crate::chain::*;
+use crate::normalize::NormalizeDirection;
 use crate::poslift::*;
 use crate::{reverse_complement, GenomeSequence, LiftOverError, Variant};
 use std::fmt::Debug;
@@ -41,6 +42,37 @@ impl<'a> From<&LiftedVariant> for Variant {
     }
 }
 
+impl LiftedVariant {
+    /// Normalize a lifted variant in the new assembly with a direction. The original reference
+    /// allele is shifted together with other alleles.
+    pub fn normalize_with_direction<G: GenomeSequence>(
+        &self,
+        new_sequence: &mut G,
+        direction: NormalizeDirection,
+    ) -> Result<LiftedVariant, LiftOverError> {
+        let mut variant = Variant {
+            chromosome: self.chromosome.clone(),
+            position: self.position,
+            reference: self.reference.clone(),
+            alternative: vec![self.original_reference.clone()]
+                .into_iter()
+                .chain(self.alternative.iter().cloned())
+                .collect(),
+        }
+        .normalize_with_direction(new_sequence, direction)?;
+        let original_reference = variant.alternative.remove(0);
+        Ok(LiftedVariant {
+            chromosome: variant.chromosome,
+            position: variant.position,
+            strand: self.strand,
+            original_reference,
+            reference: variant.reference,
+            alternative: variant.alternative,
+            reference_changed: self.reference_changed,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct VariantLiftOver<G: GenomeSequence> {
     original_sequence: G,
@@ -75,6 +107,14 @@ impl<G: GenomeSequence> VariantLiftOver<G> {
         &self.lift_position
     }
 
+    pub fn original_sequence_mut(&mut self) -> &mut G {
+        &mut self.original_sequence
+    }
+
+    pub fn new_sequence_mut(&mut self) -> &mut G {
+        &mut self.new_sequence
+    }
+
     // Input variant should be left aligned
     pub fn lift_variant(
         &mut self,
@@ -169,23 +209,38 @@ impl<G: GenomeSequence> VariantLiftOver<G> {
                 }
             };
 
-            if one_target_list.iter().any(|x| !is_acceptable_deletion(x)) {
+            let chain_id = &self.lift_position.chain_list()[one_region.chain_index].chain_id;
+            if let Some(gap_size) = one_target_list
+                .iter()
+                .filter(|x| !is_acceptable_deletion(x))
+                .map(|x| x.original_len())
+                .max()
+            {
                 result.push(Err(
                     error::VariantLiftOverError::UnacceptableLargeDeletion {
                         chromosome: one_region.chromosome.name.to_string(),
                         start: one_region.start,
                         end: one_region.end,
+                        chain_id: chain_id.to_string(),
+                        gap_size,
                     },
                 ));
                 continue;
             }
 
-            if one_target_list.iter().any(|x| !is_acceptable_insertion(x)) {
+            if let Some(gap_size) = one_target_list
+                .iter()
+                .filter(|x| !is_acceptable_insertion(x))
+                .map(|x| x.new_len())
+                .max()
+            {
                 result.push(Err(
                     error::VariantLiftOverError::UnacceptableLargeInsertion {
                         chromosome: one_region.chromosome.name.to_string(),
                         start: one_region.start,
                         end: one_region.end,
+                        chain_id: chain_id.to_string(),
+                        gap_size,
                     },
                 ));
                 continue;
@@ -0,0 +1,359 @@
+//! Handling of INFO tags with positional annotations such as VEP `CSQ` and snpEff `ANN`.
+
+use super::VCFRecordWrapper;
+use crate::vcfparse::{PartialVCFRecord, VCFHeaderItem, VCFRecord};
+use crate::LiftOverError;
+use log::warn;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str;
+
+const STALE_NOTE: &[u8] = b"(Not updated by liftOver) ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InfoHandling {
+    /// Remove the tag from lifted records and the header
+    Drop,
+    /// Keep the tag as is, and note that the tag is stale in the header
+    MarkStale,
+    /// Rewrite `Allele` sub-field of `|` separated annotations with lifted alleles. Annotations of
+    /// an allele which became REF are removed.
+    RewriteAllele,
+}
+
+/// INFO tags which are not kept as is in lifted records
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VCFLiftOverInfoHandling {
+    pub tags: HashMap<Vec<u8>, InfoHandling>,
+}
+
+impl VCFLiftOverInfoHandling {
+    pub fn new() -> Self {
+        VCFLiftOverInfoHandling::default()
+    }
+
+    pub fn tag(mut self, tag: &str, handling: InfoHandling) -> Self {
+        self.tags.insert(tag.as_bytes().to_vec(), handling);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Remove or mark INFO header lines of lifted VCF. Returns an index of `Allele` sub-field of
+    /// each tag to rewrite.
+    pub(super) fn rewrite_header(
+        &self,
+        header_items: &mut Vec<VCFHeaderItem>,
+    ) -> Result<HashMap<Vec<u8>, usize>, LiftOverError> {
+        let mut allele_index = HashMap::new();
+        let mut new_header_items = Vec::new();
+        for item in header_items.drain(..) {
+            let handling = if item.key == b"INFO" {
+                item.detail
+                    .get(&b"ID"[..])
+                    .and_then(|x| self.tags.get(x).map(|y| (x.to_vec(), *y)))
+            } else {
+                None
+            };
+            match handling {
+                Some((_, InfoHandling::Drop)) => (),
+                Some((_, InfoHandling::MarkStale)) => {
+                    new_header_items.push(mark_stale(&item)?);
+                }
+                Some((id, InfoHandling::RewriteAllele)) => {
+                    match item
+                        .detail
+                        .get(&b"Description"[..])
+                        .and_then(|x| allele_field_index(x))
+                    {
+                        Some(index) => {
+                            allele_index.insert(id, index);
+                        }
+                        None => warn!(
+                            "Allele sub-field of INFO {} is not found in the header. This tag is not rewritten.",
+                            str::from_utf8(&id).unwrap_or("")
+                        ),
+                    }
+                    new_header_items.push(item);
+                }
+                None => new_header_items.push(item),
+            }
+        }
+        *header_items = new_header_items;
+        Ok(allele_index)
+    }
+
+    /// Remove tags to drop from a lifted record.
+    pub(super) fn drop_info(&self, record: &mut VCFRecordWrapper) {
+        for (tag, handling) in self.tags.iter() {
+            if *handling == InfoHandling::Drop {
+                update_info(record, tag, |_| None);
+            }
+        }
+    }
+}
+
+/// Add a note into Description of an INFO header line.
+fn mark_stale(item: &VCFHeaderItem) -> Result<VCFHeaderItem, LiftOverError> {
+    let description = &b"Description=\""[..];
+    let mut line = b"##INFO=".to_vec();
+    match item
+        .value
+        .windows(description.len())
+        .position(|x| x == description)
+    {
+        Some(index) => {
+            line.extend_from_slice(&item.value[..index + description.len()]);
+            line.extend_from_slice(STALE_NOTE);
+            line.extend_from_slice(&item.value[index + description.len()..]);
+        }
+        None => return Ok(item.clone()),
+    }
+    Ok(VCFHeaderItem::parse(&line, 0)?)
+}
+
+/// Find an index of `Allele` in `Format: Allele|Consequence|...` of a description.
+fn allele_field_index(description: &[u8]) -> Option<usize> {
+    let description = str::from_utf8(description).ok()?;
+    let format = &description[description.find(':')? + 1..];
+    format
+        .split('|')
+        .position(|x| x.trim_matches(|c: char| c.is_whitespace() || c == '\'') == "Allele")
+}
+
+/// Apply `f` to values of INFO `tag`. The tag is removed if `f` returns `None`.
+fn update_info<F: FnMut(&[&[u8]]) -> Option<Vec<Vec<u8>>>>(
+    record: &mut VCFRecordWrapper,
+    tag: &[u8],
+    mut f: F,
+) {
+    match record {
+        VCFRecordWrapper::Partial(ref mut p) => {
+            if !p
+                .unparsed_info
+                .split(|x| *x == b';')
+                .any(|x| x.split(|y| *y == b'=').next() == Some(tag))
+            {
+                return;
+            }
+            let mut items: Vec<Vec<u8>> = Vec::new();
+            for one in p.unparsed_info.split(|x| *x == b';') {
+                let key_length = one.iter().position(|x| *x == b'=').unwrap_or(one.len());
+                if &one[..key_length] != tag {
+                    items.push(one.to_vec());
+                    continue;
+                }
+                let values: Vec<_> = if key_length == one.len() {
+                    vec![]
+                } else {
+                    one[key_length + 1..].split(|x| *x == b',').collect()
+                };
+                if let Some(new_values) = f(&values) {
+                    let mut item = tag.to_vec();
+                    item.push(b'=');
+                    item.extend_from_slice(&new_values.join(&b","[..]));
+                    items.push(item);
+                }
+            }
+            p.unparsed_info = Cow::Owned(if items.is_empty() {
+                b".".to_vec()
+            } else {
+                items.join(&b";"[..])
+            });
+        }
+        VCFRecordWrapper::Complete(ref mut c) => {
+            let mut info = Vec::new();
+            for (key, values) in c.info.drain(..) {
+                if key.as_ref() != tag {
+                    info.push((key, values));
+                    continue;
+                }
+                let values: Vec<_> = values.iter().map(|x| x.as_ref()).collect();
+                if let Some(new_values) = f(&values) {
+                    info.push((key, new_values.into_iter().map(Cow::Owned).collect()));
+                }
+            }
+            c.info = info;
+        }
+    }
+}
+
+/// Alleles in VEP style. A first base shared by all alleles is removed, and an empty allele is
+/// written as `-`.
+fn vep_alleles<T: AsRef<[u8]>>(reference: &[u8], alternative: &[T]) -> Vec<Vec<u8>> {
+    let trim = !reference.is_empty()
+        && alternative
+            .iter()
+            .all(|x| x.as_ref().first() == reference.first());
+    alternative
+        .iter()
+        .map(|x| {
+            let x = if trim { &x.as_ref()[1..] } else { x.as_ref() };
+            if x.is_empty() {
+                b"-".to_vec()
+            } else {
+                x.to_vec()
+            }
+        })
+        .collect()
+}
+
+/// Rewrite `Allele` sub-fields of annotations in a lifted record. `lifted_alternative` is lifted
+/// sequences of ALT alleles of `original`.
+pub(super) fn rewrite_allele_info(
+    record: &mut VCFRecordWrapper,
+    original: &PartialVCFRecord,
+    lifted_alternative: &[Vec<u8>],
+    allele_index: &HashMap<Vec<u8>, usize>,
+) {
+    if allele_index.is_empty() || original.alternative().len() != lifted_alternative.len() {
+        return;
+    }
+    let original_vep = vep_alleles(original.reference(), original.alternative());
+    let new_reference = record.reference().to_vec();
+    let new_alternative: Vec<Vec<u8>> = record.alternative().iter().map(|x| x.to_vec()).collect();
+    let new_vep = vep_alleles(&new_reference, &new_alternative);
+
+    // (original raw allele, original VEP allele) -> (lifted raw allele, lifted VEP allele)
+    let allele_map: Vec<_> = original
+        .alternative()
+        .iter()
+        .zip(original_vep.iter())
+        .zip(lifted_alternative.iter())
+        .map(|((raw, vep), lifted)| {
+            let lifted = new_alternative
+                .iter()
+                .position(|x| x == lifted && x != &new_reference)
+                .map(|i| (new_alternative[i].clone(), new_vep[i].clone()));
+            ((raw.as_ref(), vep.as_slice()), lifted)
+        })
+        .collect();
+
+    for (tag, index) in allele_index.iter() {
+        update_info(record, tag, |values| {
+            let new_values: Vec<Vec<u8>> = values
+                .iter()
+                .filter_map(|value| {
+                    let mut fields: Vec<&[u8]> = value.split(|x| *x == b'|').collect();
+                    let allele = *fields.get(*index)?;
+                    for ((raw, vep), lifted) in allele_map.iter() {
+                        if allele == *raw || allele == *vep {
+                            let (lifted_raw, lifted_vep) = lifted.as_ref()?;
+                            fields[*index] = if allele == *raw {
+                                lifted_raw.as_slice()
+                            } else {
+                                lifted_vep.as_slice()
+                            };
+                            return Some(fields.join(&b"|"[..]));
+                        }
+                    }
+                    Some(value.to_vec())
+                })
+                .collect();
+            if new_values.is_empty() {
+                None
+            } else {
+                Some(new_values)
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_header() -> Result<(), LiftOverError> {
+        let mut header_items = vec![
+            VCFHeaderItem::parse(b"##INFO=<ID=CSQ,Number=.,Type=String,Description=\"Consequence annotations from Ensembl VEP. Format: Allele|Consequence|SYMBOL\">", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=ANN,Number=.,Type=String,Description=\"Functional annotations: 'Allele | Annotation | Gene_Name'\">", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=CLNHGVS,Number=.,Type=String,Description=\"HGVS expression\">", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=GENEINFO,Number=1,Type=String,Description=\"Gene\">", 0)?,
+        ];
+        let allele_index = VCFLiftOverInfoHandling::new()
+            .tag("CSQ", InfoHandling::RewriteAllele)
+            .tag("ANN", InfoHandling::RewriteAllele)
+            .tag("CLNHGVS", InfoHandling::MarkStale)
+            .tag("GENEINFO", InfoHandling::Drop)
+            .rewrite_header(&mut header_items)?;
+        assert_eq!(
+            allele_index,
+            vec![(b"CSQ".to_vec(), 0), (b"ANN".to_vec(), 0)]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(header_items.len(), 3);
+        assert_eq!(
+            header_items[2].detail[&b"Description"[..]],
+            b"(Not updated by liftOver) HGVS expression"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_allele_info() -> Result<(), LiftOverError> {
+        let allele_index: HashMap<_, _> = vec![(b"CSQ".to_vec(), 0), (b"ANN".to_vec(), 0)]
+            .into_iter()
+            .collect();
+        let original = PartialVCFRecord::parse_vcf(
+            1,
+            b"1\t100\t.\tAT\tA,ATT\t.\t.\tCSQ=-|del|X,TT|ins|X;ANN=A|del|X\n",
+        )?;
+
+        // REF changed from AT to ATT, and ATT was swapped with REF
+        let mut record = VCFRecordWrapper::Partial(PartialVCFRecord::parse_vcf(
+            1,
+            b"1\t200\t.\tATT\tA,AT\t.\t.\tCSQ=-|del|X,TT|ins|X;ANN=A|del|X\n",
+        )?);
+        rewrite_allele_info(
+            &mut record,
+            &original,
+            &[b"A".to_vec(), b"ATT".to_vec()],
+            &allele_index,
+        );
+        let mut line = Vec::new();
+        record.write(&mut line)?;
+        assert_eq!(
+            str::from_utf8(&line).unwrap(),
+            "1\t200\t.\tATT\tA,AT\t.\t.\tCSQ=-|del|X;ANN=A|del|X\n"
+        );
+
+        // lifted to reverse strand
+        let original =
+            PartialVCFRecord::parse_vcf(1, b"1\t100\t.\tC\tG,T\t.\t.\tCSQ=G|a,T|b,C|c\n")?;
+        let mut record = VCFRecordWrapper::Partial(PartialVCFRecord::parse_vcf(
+            1,
+            b"1\t200\t.\tG\tC,A\t.\t.\tCSQ=G|a,T|b,C|c\n",
+        )?);
+        rewrite_allele_info(
+            &mut record,
+            &original,
+            &[b"C".to_vec(), b"A".to_vec()],
+            &allele_index,
+        );
+        let mut line = Vec::new();
+        record.write(&mut line)?;
+        assert_eq!(
+            str::from_utf8(&line).unwrap(),
+            "1\t200\t.\tG\tC,A\t.\t.\tCSQ=C|a,A|b,C|c\n"
+        );
+
+        let mut record = VCFRecordWrapper::Partial(PartialVCFRecord::parse_vcf(
+            1,
+            b"1\t200\t.\tG\tC\t.\t.\tAC=1;CSQ=G|a;DP=3\n",
+        )?);
+        VCFLiftOverInfoHandling::new()
+            .tag("CSQ", InfoHandling::Drop)
+            .drop_info(&mut record);
+        let mut line = Vec::new();
+        record.write(&mut line)?;
+        assert_eq!(
+            str::from_utf8(&line).unwrap(),
+            "1\t200\t.\tG\tC\t.\t.\tAC=1;DP=3\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+//! Detect lifted records from different original records at the same position with the same
+//! alleles.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter, SeekFrom};
+use std::str::FromStr;
+
+pub(super) const COLLISION: &str = "COLLISION";
+
+/// Names of collision policies
+pub const COLLISION_POLICIES: [&str; 3] = ["keep-all", "keep-first", "fail"];
+
+/// How to resolve lifted records at the same position with the same alleles.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollisionPolicy {
+    /// Keep all records with `COLLISION` flag
+    KeepAll,
+    /// Keep the first record and move others to failed records
+    KeepFirst,
+    /// Move all records to failed records
+    Fail,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-all" => Ok(CollisionPolicy::KeepAll),
+            "keep-first" => Ok(CollisionPolicy::KeepFirst),
+            "fail" => Ok(CollisionPolicy::Fail),
+            _ => Err(format!("Unknown collision policy: {}", s)),
+        }
+    }
+}
+
+/// Write an original record after a lifted record. The original record is attached to the lifted
+/// record while sorting, and is written into failed records if the lifted record is removed.
+pub(super) fn write_attachment<W: Write>(
+    writer: &mut W,
+    line: u32,
+    original: &[u8],
+) -> io::Result<()> {
+    write!(writer, "\0{}\t", line)?;
+    writer.write_all(original)
+}
+
+/// Line numbers and original records of removed records
+pub(super) type RemovedRecords = Vec<(u32, Vec<u8>)>;
+
+fn parse_attachment(attachment: &[u8]) -> Option<(u32, Vec<u8>)> {
+    let mut columns = attachment.strip_prefix(b"\0")?.splitn(2, |x| *x == b'\t');
+    let line = std::str::from_utf8(columns.next()?).ok()?.parse().ok()?;
+    Some((line, columns.next()?.to_vec()))
+}
+
+/// A writer to detect collisions in sorted lifted records.
+///
+/// Lifted records at the same position are buffered, and records with the same REF and ALT are
+/// resolved with a policy. Lines starting with NUL are original records written by
+/// `write_attachment`, and they are not written into the inner writer.
+///
+/// An original record may be lifted into more than one record (split alleles, multi-mapped
+/// copies or pieces of a reference block). If one of them is removed, the other records of the
+/// same original record are also removed, so that the original record is written only into
+/// failed records. Resolved records are written into a temporary file, and written into the
+/// inner writer without records of removed original records in `finish`.
+#[derive(Debug)]
+pub(super) struct CollisionWriter<W: Write> {
+    writer: W,
+    policy: CollisionPolicy,
+    /// resolved records followed by line numbers of their original records
+    resolved: BufWriter<File>,
+    /// lifted records and attached original records at the current position
+    group: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    partial_line: Vec<u8>,
+    removed: RemovedRecords,
+    removed_lines: HashSet<u32>,
+}
+
+fn columns(line: &[u8], n: usize) -> Vec<&[u8]> {
+    line.splitn(n + 1, |x| *x == b'\t').take(n).collect()
+}
+
+impl<W: Write> CollisionWriter<W> {
+    pub fn new(writer: W, policy: CollisionPolicy) -> io::Result<Self> {
+        Ok(CollisionWriter {
+            writer,
+            policy,
+            resolved: BufWriter::new(tempfile::tempfile()?),
+            group: Vec::new(),
+            partial_line: Vec::new(),
+            removed: Vec::new(),
+            removed_lines: HashSet::new(),
+        })
+    }
+
+    fn push_line(&mut self, line: Vec<u8>) -> io::Result<()> {
+        if line.starts_with(b"\0") {
+            if let Some(last) = self.group.last_mut() {
+                last.1 = Some(line);
+            }
+            return Ok(());
+        }
+        if line.starts_with(b"#") {
+            self.flush_group()?;
+            return self.resolved.write_all(&line);
+        }
+        if let Some(first) = self.group.first() {
+            if columns(&first.0, 2) != columns(&line, 2) {
+                self.flush_group()?;
+            }
+        }
+        self.group.push((line, None));
+        Ok(())
+    }
+
+    fn flush_group(&mut self) -> io::Result<()> {
+        let mut alleles: HashMap<Vec<&[u8]>, usize> = HashMap::new();
+        for (line, _) in self.group.iter() {
+            *alleles.entry(columns(line, 5)[3..].to_vec()).or_default() += 1;
+        }
+        let collided: Vec<_> = self
+            .group
+            .iter()
+            .map(|(line, _)| alleles[&columns(line, 5)[3..]] > 1)
+            .collect();
+
+        let mut kept_alleles: Vec<Vec<u8>> = Vec::new();
+        for ((line, attachment), collided) in self.group.drain(..).zip(collided) {
+            let original = attachment.as_deref().and_then(parse_attachment);
+            if !collided {
+                self.resolved.write_all(&line)?;
+                write_resolved_attachment(&mut self.resolved, original.as_ref(), false)?;
+                continue;
+            }
+            let keep = match self.policy {
+                CollisionPolicy::KeepAll => true,
+                CollisionPolicy::KeepFirst => {
+                    let allele = columns(&line, 5)[3..].join(&b'\t');
+                    if kept_alleles.contains(&allele) {
+                        false
+                    } else {
+                        kept_alleles.push(allele);
+                        true
+                    }
+                }
+                CollisionPolicy::Fail => false,
+            };
+            if keep {
+                if self.policy == CollisionPolicy::KeepAll {
+                    self.resolved.write_all(&add_collision_flag(&line))?;
+                } else {
+                    self.resolved.write_all(&line)?;
+                }
+                write_resolved_attachment(&mut self.resolved, original.as_ref(), true)?;
+            } else if let Some(original) = original {
+                if self.removed_lines.insert(original.0) {
+                    self.removed.push(original);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write all resolved records except records of removed original records, and return an
+    /// inner writer, removed original records with line numbers and the number of kept records
+    /// which collided with other records.
+    pub fn finish(mut self) -> io::Result<(W, RemovedRecords, u64)> {
+        if !self.partial_line.is_empty() {
+            let mut line = std::mem::take(&mut self.partial_line);
+            line.push(b'\n');
+            self.push_line(line)?;
+        }
+        self.flush_group()?;
+
+        let mut resolved = self.resolved.into_inner().map_err(|e| e.into_error())?;
+        resolved.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(resolved);
+        let mut kept = 0;
+        let mut pending: Option<Vec<u8>> = None;
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            if let Some((original_line, collided)) = parse_attachment(&line) {
+                if let Some(record) = pending.take() {
+                    if !self.removed_lines.contains(&original_line) {
+                        self.writer.write_all(&record)?;
+                        if collided.starts_with(b"1") {
+                            kept += 1;
+                        }
+                    }
+                }
+                line.clear();
+            } else if let Some(record) = pending.replace(std::mem::take(&mut line)) {
+                self.writer.write_all(&record)?;
+            }
+        }
+        if let Some(record) = pending {
+            self.writer.write_all(&record)?;
+        }
+        self.writer.flush()?;
+        Ok((self.writer, self.removed, kept))
+    }
+}
+
+/// Write a line number of an original record and whether the record collided with other records
+/// after a resolved record.
+fn write_resolved_attachment<W: Write>(
+    writer: &mut W,
+    original: Option<&(u32, Vec<u8>)>,
+    collided: bool,
+) -> io::Result<()> {
+    if let Some((line, _)) = original {
+        write_attachment(writer, *line, if collided { b"1\n" } else { b"0\n" })?;
+    }
+    Ok(())
+}
+
+/// Add `COLLISION` flag into INFO column of a VCF line.
+fn add_collision_flag(line: &[u8]) -> Vec<u8> {
+    let mut columns: Vec<&[u8]> = line.split(|x| *x == b'\t').collect();
+    let last = columns.len() - 1;
+    let mut info = columns.get(7).map(|x| x.to_vec()).unwrap_or_default();
+    if info.ends_with(b"\n") {
+        info.pop();
+    }
+    if info == b"." || info.is_empty() {
+        info.clear();
+    } else {
+        info.push(b';');
+    }
+    info.extend_from_slice(COLLISION.as_bytes());
+    if last == 7 {
+        info.push(b'\n');
+    }
+    columns[7] = &info;
+    columns.join(&b'\t')
+}
+
+impl<W: Write> Write for CollisionWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(index) = rest.iter().position(|x| *x == b'\n') {
+            let mut line = std::mem::take(&mut self.partial_line);
+            line.extend_from_slice(&rest[..=index]);
+            self.push_line(line)?;
+            rest = &rest[index + 1..];
+        }
+        self.partial_line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // records are written into the inner writer in `finish`
+        self.resolved.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_records(policy: CollisionPolicy) -> io::Result<(String, RemovedRecords, u64)> {
+        let mut writer = CollisionWriter::new(Vec::new(), policy)?;
+        writer.write_all(b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n")?;
+        for (line, lifted, original) in [
+            (
+                1,
+                "chr1\t100\ta\tA\tG\t.\t.\t.\n",
+                "chr1\t10\ta\tA\tG\t.\t.\t.\n",
+            ),
+            (
+                2,
+                "chr1\t100\tb\tA\tC\t.\t.\t.\n",
+                "chr1\t20\tb\tA\tC\t.\t.\t.\n",
+            ),
+            (
+                3,
+                "chr1\t100\tc\tA\tG\t.\t.\tAC=1\n",
+                "chr2\t10\tc\tA\tG\t.\t.\tAC=1\n",
+            ),
+            (
+                4,
+                "chr1\t200\td\tA\tG\t.\t.\t.\n",
+                "chr2\t20\td\tA\tG\t.\t.\t.\n",
+            ),
+        ] {
+            writer.write_all(lifted.as_bytes())?;
+            write_attachment(&mut writer, line, original.as_bytes())?;
+        }
+        let (output, removed, kept) = writer.finish()?;
+        Ok((String::from_utf8(output).unwrap(), removed, kept))
+    }
+
+    #[test]
+    fn test_collision_writer() -> io::Result<()> {
+        let (output, removed, kept) = write_records(CollisionPolicy::KeepAll)?;
+        assert_eq!(
+            output,
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t100\ta\tA\tG\t.\t.\tCOLLISION
+chr1\t100\tb\tA\tC\t.\t.\t.
+chr1\t100\tc\tA\tG\t.\t.\tAC=1;COLLISION
+chr1\t200\td\tA\tG\t.\t.\t.
+"
+        );
+        assert!(removed.is_empty());
+        assert_eq!(kept, 2);
+
+        let (output, removed, kept) = write_records(CollisionPolicy::KeepFirst)?;
+        assert_eq!(
+            output,
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t100\ta\tA\tG\t.\t.\t.
+chr1\t100\tb\tA\tC\t.\t.\t.
+chr1\t200\td\tA\tG\t.\t.\t.
+"
+        );
+        assert_eq!(
+            removed,
+            vec![(3, b"chr2\t10\tc\tA\tG\t.\t.\tAC=1\n".to_vec())]
+        );
+        assert_eq!(kept, 1);
+
+        let (output, removed, kept) = write_records(CollisionPolicy::Fail)?;
+        assert_eq!(
+            output,
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t100\tb\tA\tC\t.\t.\t.
+chr1\t200\td\tA\tG\t.\t.\t.
+"
+        );
+        assert_eq!(removed.len(), 2);
+        assert_eq!(kept, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_collision_flag() {
+        assert_eq!(
+            add_collision_flag(b"1\t1\t.\tA\tG\t.\t.\tAC=1\tGT\t0/1\n"),
+            b"1\t1\t.\tA\tG\t.\t.\tAC=1;COLLISION\tGT\t0/1\n"
+        );
+        assert_eq!(
+            add_collision_flag(b"1\t1\t.\tA\tG\t.\t.\t.\n"),
+            b"1\t1\t.\tA\tG\t.\t.\tCOLLISION\n"
+        );
+    }
+}
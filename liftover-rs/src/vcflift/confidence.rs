@@ -0,0 +1,211 @@
+//! Confidence score of lifted variants.
+
+use super::{add_info_helper, VCFRecordWrapper};
+use crate::chain::Strand;
+use crate::poslift::PositionLiftOver;
+use crate::variantlift::LiftedVariant;
+use crate::Variant;
+use std::ops::Range;
+
+pub(super) const LIFT_CONFIDENCE: &str = "LIFT_CONFIDENCE";
+pub(super) const LOW_CONFIDENCE: &str = "LOW_CONFIDENCE";
+
+/// Evidence of a lifted variant used to compute a confidence score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct ConfidenceEvidence {
+    /// distance from the variant to the nearest gap or end of the chain
+    pub gap_distance: u64,
+    /// size of the smallest aligned block which the variant overlaps
+    pub block_size: u64,
+    pub chain_score: i64,
+    /// number of chains which overlap the original variant
+    pub candidate_chains: usize,
+    pub reference_changed: bool,
+}
+
+impl ConfidenceEvidence {
+    /// Collect evidence of a lifted variant from chains which overlap the original variant.
+    pub fn collect(
+        position_liftover: &PositionLiftOver,
+        original: &Variant,
+        lifted: &LiftedVariant,
+    ) -> Self {
+        let start = original.position;
+        let end = original.position + original.reference.len().max(1) as u64;
+        Self::collect_region(
+            position_liftover,
+            &original.chromosome,
+            start..end,
+            &lifted.chromosome,
+            lifted.position,
+            lifted.strand,
+            lifted.reference_changed,
+        )
+    }
+
+    /// Collect evidence of an original region lifted to `lifted_position`.
+    pub fn collect_region(
+        position_liftover: &PositionLiftOver,
+        chromosome: &str,
+        region: Range<u64>,
+        lifted_chromosome: &str,
+        lifted_position: u64,
+        strand: Strand,
+        reference_changed: bool,
+    ) -> Self {
+        let (start, end) = (region.start, region.end);
+        let groups = position_liftover.search_target(chromosome, region);
+
+        // the chain which the variant was lifted with
+        let group = groups
+            .iter()
+            .filter(|x| {
+                x[0].strand == strand
+                    && position_liftover.new_chromosomes()[x[0].new_chromosome_index].name
+                        == lifted_chromosome
+            })
+            .min_by_key(|x| {
+                let new_start = x.iter().map(|y| y.new_start).min().unwrap();
+                let new_end = x.iter().map(|y| y.new_end).max().unwrap();
+                if lifted_position < new_start {
+                    new_start - lifted_position
+                } else if lifted_position >= new_end {
+                    lifted_position + 1 - new_end
+                } else {
+                    0
+                }
+            });
+
+        let (gap_distance, block_size, chain_score) = if let Some(group) = group {
+            let gap_distance = match group.as_slice() {
+                [block] if !block.is_in_gap => start
+                    .saturating_sub(block.original_start)
+                    .min(block.original_end.saturating_sub(end)),
+                _ => 0,
+            };
+            let block_size = group
+                .iter()
+                .filter(|x| !x.is_in_gap)
+                .map(|x| x.original_len())
+                .min()
+                .unwrap_or(0);
+            (
+                gap_distance,
+                block_size,
+                position_liftover.chain_list()[group[0].chain_index].score,
+            )
+        } else {
+            (0, 0, 0)
+        };
+
+        ConfidenceEvidence {
+            gap_distance,
+            block_size,
+            chain_score,
+            candidate_chains: groups.len().max(1),
+            reference_changed,
+        }
+    }
+
+    /// Confidence score from 0 to 100. The score is a product of following factors.
+    ///
+    /// * `min(1, 0.5 + gap distance / 20)`
+    /// * `min(1, 0.5 + block size / 200)`
+    /// * `min(1, 0.5 + log10(chain score) / 12)`
+    /// * `1 / number of candidate chains`
+    /// * `0.8` if the reference allele was changed
+    pub fn score(&self) -> u32 {
+        let gap = (0.5 + self.gap_distance as f64 / 20.).min(1.);
+        let block = (0.5 + self.block_size as f64 / 200.).min(1.);
+        let chain = (0.5 + (self.chain_score.max(1) as f64).log10() / 12.).min(1.);
+        let candidate = 1. / self.candidate_chains as f64;
+        let reference = if self.reference_changed { 0.8 } else { 1. };
+        (100. * gap * block * chain * candidate * reference).round() as u32
+    }
+}
+
+/// Add `LIFT_CONFIDENCE` into INFO column.
+pub(super) fn add_confidence_info(record: &mut VCFRecordWrapper, score: u32) {
+    add_info_helper(
+        record,
+        LIFT_CONFIDENCE.as_bytes(),
+        &[format!("{}", score).into_bytes()],
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LiftOverError;
+
+    #[test]
+    fn test_confidence_evidence() -> Result<(), LiftOverError> {
+        let position_liftover = PositionLiftOver::load(
+            &b"chain 1000000 chr1 1000 + 0 1000 chr1 995 + 0 995 1\n500\t5\t0\n495\n\n\
+chain 1000 chr1 1000 + 900 1000 chr2 100 + 0 100 2\n100\n\n"[..],
+        )?;
+        let lifted = |position: u64, reference: &[u8], reference_changed: bool| LiftedVariant {
+            chromosome: "chr1".to_string(),
+            position,
+            strand: Strand::Forward,
+            original_reference: reference.to_vec(),
+            reference: reference.to_vec(),
+            alternative: vec![b"G".to_vec()],
+            reference_changed,
+        };
+
+        let evidence = ConfidenceEvidence::collect(
+            &position_liftover,
+            &Variant::new("chr1", 100, b"A", &[b"G"]),
+            &lifted(100, b"A", false),
+        );
+        assert_eq!(
+            evidence,
+            ConfidenceEvidence {
+                gap_distance: 100,
+                block_size: 500,
+                chain_score: 1000000,
+                candidate_chains: 1,
+                reference_changed: false,
+            }
+        );
+        assert_eq!(evidence.score(), 100);
+
+        let evidence = ConfidenceEvidence::collect(
+            &position_liftover,
+            &Variant::new("chr1", 497, b"A", &[b"G"]),
+            &lifted(497, b"A", true),
+        );
+        assert_eq!(evidence.gap_distance, 2);
+        assert_eq!(evidence.score(), 48);
+
+        let evidence = ConfidenceEvidence::collect(
+            &position_liftover,
+            &Variant::new("chr1", 498, b"AACCGGTT", &[b"A"]),
+            &lifted(498, b"AAC", false),
+        );
+        assert_eq!((evidence.gap_distance, evidence.block_size), (0, 495));
+
+        let evidence = ConfidenceEvidence::collect(
+            &position_liftover,
+            &Variant::new("chr1", 950, b"A", &[b"G"]),
+            &lifted(945, b"A", false),
+        );
+        assert_eq!(evidence.candidate_chains, 2);
+        assert_eq!(evidence.score(), 50);
+
+        // the variant starts before the only block of the chain
+        let evidence = ConfidenceEvidence::collect_region(
+            &position_liftover,
+            "chr1",
+            898..902,
+            "chr2",
+            0,
+            Strand::Forward,
+            false,
+        );
+        assert_eq!((evidence.gap_distance, evidence.block_size), (0, 100));
+
+        Ok(())
+    }
+}
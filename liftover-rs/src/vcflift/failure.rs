@@ -0,0 +1,270 @@
+//! Reasons and tested regions of records which failed to liftOver.
+
+use crate::variantlift::error::VariantLiftOverError;
+use crate::vcfparse::{PartialVCFRecord, VCFRecord};
+use crate::LiftOverError;
+use std::io::{self, Write};
+use std::str;
+
+/// A region in the new assembly which was tested while lifting a failed record.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TestedRegion {
+    pub chromosome: String,
+    /// zero based start position
+    pub start: u64,
+    pub end: u64,
+    pub chain_id: Option<String>,
+    /// length of an unacceptable gap in the chain
+    pub gap_size: Option<u64>,
+}
+
+/// Why a record failed to liftOver.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LiftOverFailure {
+    /// failure reasons in the order found
+    pub reasons: Vec<&'static str>,
+    pub tested_regions: Vec<TestedRegion>,
+    /// number of lifted candidates if a record failed because of multi-mapping
+    pub multimap: Option<usize>,
+}
+
+impl LiftOverFailure {
+    pub fn is_empty(&self) -> bool {
+        self.reasons.is_empty()
+    }
+
+    /// Some candidates were lifted, but the record was failed.
+    pub fn is_partial_success(&self) -> bool {
+        self.multimap.is_some()
+    }
+
+    pub(super) fn add_reason(&mut self, reason: &'static str) {
+        if !self.reasons.contains(&reason) {
+            self.reasons.push(reason);
+        }
+    }
+
+    pub(super) fn add_region(
+        &mut self,
+        chromosome: &str,
+        start: u64,
+        end: u64,
+        chain_id: Option<&str>,
+    ) {
+        self.tested_regions.push(TestedRegion {
+            chromosome: chromosome.to_string(),
+            start,
+            end,
+            chain_id: chain_id.map(|x| x.to_string()),
+            gap_size: None,
+        });
+    }
+
+    pub(super) fn add_variant_error(&mut self, error: &VariantLiftOverError) {
+        match error {
+            VariantLiftOverError::UnacceptableLargeDeletion {
+                chromosome,
+                start,
+                end,
+                chain_id,
+                gap_size,
+            }
+            | VariantLiftOverError::UnacceptableLargeInsertion {
+                chromosome,
+                start,
+                end,
+                chain_id,
+                gap_size,
+            } => {
+                self.add_reason(
+                    if let VariantLiftOverError::UnacceptableLargeDeletion { .. } = error {
+                        "UNACCEPTABLE_LARGE_DELETION"
+                    } else {
+                        "UNACCEPTABLE_LARGE_INSERTION"
+                    },
+                );
+                self.tested_regions.push(TestedRegion {
+                    chromosome: chromosome.to_string(),
+                    start: *start,
+                    end: *end,
+                    chain_id: Some(chain_id.to_string()),
+                    gap_size: Some(*gap_size),
+                });
+            }
+            VariantLiftOverError::UnknownSequenceName(_) => {
+                self.add_reason("UNKNOWN_SEQUENCE_NAME");
+            }
+            VariantLiftOverError::ReferenceSequenceIsNotMatch => {
+                self.add_reason("UNEXPECTED_REF");
+            }
+        }
+    }
+
+    /// Add FAILED_REASON, MULTIMAP, TESTED_* and PARTIAL_SUCCESS to INFO of a failed record.
+    pub(super) fn write_info(&self, record: &mut PartialVCFRecord) -> io::Result<()> {
+        let info = record.unparsed_info.to_mut();
+        if info == b"." {
+            info.clear();
+        } else {
+            info.push(b';');
+        }
+        write!(info, "FAILED_REASON={}", self.reasons.join(","))?;
+        if let Some(multimap) = self.multimap {
+            write!(info, ";MULTIMAP={}", multimap)?;
+        }
+        if !self.tested_regions.is_empty() {
+            write!(
+                info,
+                ";TESTED_CHROM={}",
+                self.join_regions(|x| x.chromosome.clone())
+            )?;
+            write!(info, ";TESTED_START={}", self.join_regions(|x| x.start + 1))?;
+            write!(info, ";TESTED_END={}", self.join_regions(|x| x.end))?;
+            write!(
+                info,
+                ";TESTED_CHAIN={}",
+                self.join_regions(|x| option_value(&x.chain_id))
+            )?;
+            if self.tested_regions.iter().any(|x| x.gap_size.is_some()) {
+                write!(
+                    info,
+                    ";GAP_SIZE={}",
+                    self.join_regions(|x| option_value(&x.gap_size))
+                )?;
+            }
+        }
+        if self.is_partial_success() {
+            write!(info, ";PARTIAL_SUCCESS")?;
+        }
+        Ok(())
+    }
+
+    fn join_regions<T: ToString, F: Fn(&TestedRegion) -> T>(&self, f: F) -> String {
+        self.tested_regions
+            .iter()
+            .map(|x| f(x).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+fn option_value<T: ToString>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|x| x.to_string())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+/// Write one line per failed record with reasons and tested regions.
+pub struct FailureReportWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> FailureReportWriter<W> {
+    pub fn new(writer: W) -> Result<Self, LiftOverError> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .quote_style(csv::QuoteStyle::Never)
+            .escape(b'\\')
+            .from_writer(writer);
+        writer.write_record([
+            "Chromosome",
+            "Position",
+            "ID",
+            "Reference",
+            "Alternative",
+            "Reasons",
+            "Multimap",
+            "Tested regions",
+            "Tested chains",
+            "Gap sizes",
+        ])?;
+        Ok(FailureReportWriter { writer })
+    }
+
+    pub fn write(
+        &mut self,
+        record: &PartialVCFRecord,
+        failure: &LiftOverFailure,
+    ) -> Result<(), LiftOverError> {
+        let join = |values: Vec<String>| {
+            if values.is_empty() {
+                ".".to_string()
+            } else {
+                values.join(",")
+            }
+        };
+        let regions = &failure.tested_regions;
+        self.writer.write_record([
+            record.contig(),
+            record.position().to_string().as_bytes(),
+            record.id(),
+            record.reference(),
+            &record.alternative().join(&b","[..]),
+            failure.reasons.join(",").as_bytes(),
+            option_value(&failure.multimap).as_bytes(),
+            join(
+                regions
+                    .iter()
+                    .map(|x| format!("{}:{}-{}", x.chromosome, x.start + 1, x.end))
+                    .collect(),
+            )
+            .as_bytes(),
+            join(regions.iter().map(|x| option_value(&x.chain_id)).collect()).as_bytes(),
+            join(regions.iter().map(|x| option_value(&x.gap_size)).collect()).as_bytes(),
+        ])?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), LiftOverError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_failure_info_and_report() -> Result<(), LiftOverError> {
+        let mut failure = LiftOverFailure::default();
+        failure.add_variant_error(&VariantLiftOverError::UnacceptableLargeDeletion {
+            chromosome: "chr1".to_string(),
+            start: 99,
+            end: 160,
+            chain_id: "3".to_string(),
+            gap_size: 50,
+        });
+        failure.add_region("chr2", 10, 11, None);
+        failure.add_reason("UNACCEPTABLE_LARGE_DELETION");
+        assert_eq!(failure.reasons, vec!["UNACCEPTABLE_LARGE_DELETION"]);
+
+        let mut record = PartialVCFRecord::parse_vcf(1, b"chr1\t100\trs1\tA\tG,T\t.\t.\tAC=1\n")?;
+        failure.write_info(&mut record)?;
+        assert_eq!(
+            str::from_utf8(&record.unparsed_info).unwrap(),
+            "AC=1;FAILED_REASON=UNACCEPTABLE_LARGE_DELETION;TESTED_CHROM=chr1,chr2;\
+TESTED_START=100,11;TESTED_END=160,11;TESTED_CHAIN=3,.;GAP_SIZE=50,."
+        );
+
+        let mut report = FailureReportWriter::new(Vec::new())?;
+        report.write(&record, &failure)?;
+        report.write(
+            &PartialVCFRecord::parse_vcf(2, b"chr3\t5\t.\tC\tT\t.\t.\t.\n")?,
+            &LiftOverFailure {
+                reasons: vec!["MULTIMAP"],
+                tested_regions: Vec::new(),
+                multimap: Some(2),
+            },
+        )?;
+        let report = report.writer.into_inner().unwrap();
+        assert_eq!(
+            str::from_utf8(&report).unwrap(),
+            "Chromosome\tPosition\tID\tReference\tAlternative\tReasons\tMultimap\tTested regions\tTested chains\tGap sizes\n\
+chr1\t100\trs1\tA\tG,T\tUNACCEPTABLE_LARGE_DELETION\t.\tchr1:100-160,chr2:11-11\t3,.\t50,.\n\
+chr3\t5\t.\tC\tT\tMULTIMAP\t2\t.\t.\t.\n"
+        );
+        Ok(())
+    }
+}
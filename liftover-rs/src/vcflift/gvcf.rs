@@ -0,0 +1,410 @@
+//! Lift gVCF reference blocks and records with a `<NON_REF>` allele.
+
+use super::svlift::{info_values, parse_number, set_info, CheckedBase, LiftedBase};
+use super::{
+    merge_to_vcf, multiallelic, LiftOverFailure, VCFHeaderRewriteTarget, VCFLiftOver,
+    VCFLiftOverParameters, VCFRecordWrapper,
+};
+use crate::chain::Strand;
+use crate::defs::GenomeSequence;
+use crate::variantlift::LiftedVariant;
+use crate::vcfparse::{PartialVCFRecord, VCFParseError, VCFRecord};
+use crate::LiftOverError;
+use std::borrow::Cow;
+
+pub(super) fn is_non_ref_allele(allele: &[u8]) -> bool {
+    allele == b"<NON_REF>" || allele == b"<*>"
+}
+
+/// A record is a reference block if all ALT alleles are `<NON_REF>` or `<*>`.
+pub(super) fn is_reference_block<R: VCFRecord>(record: &R) -> bool {
+    record.alternative().iter().all(|x| is_non_ref_allele(x))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BlockPiece {
+    chromosome: String,
+    /// zero based position in the original assembly which corresponds to `new_start`
+    original_position: u64,
+    original_start: u64,
+    original_end: u64,
+    new_start: u64,
+    new_end: u64,
+    strand: Strand,
+    chain_index: usize,
+}
+
+/// Merge a lifted variant whose `<NON_REF>` alleles were removed before liftover.
+/// `<NON_REF>` alleles are inserted back, and moved to the end of ALT alleles if REF and ALT
+/// were swapped.
+pub(super) fn merge_to_gvcf<'a>(
+    variant: &LiftedVariant,
+    record: &PartialVCFRecord<'a>,
+    non_ref_index: &[usize],
+    param: &VCFLiftOverParameters,
+    rewrite_target: &VCFHeaderRewriteTarget,
+) -> Result<VCFRecordWrapper<'a>, VCFParseError> {
+    let mut variant = variant.clone();
+    for i in non_ref_index {
+        variant
+            .alternative
+            .insert(*i, record.alternative()[*i].to_vec());
+    }
+
+    match merge_to_vcf(&variant, record, param, rewrite_target)? {
+        VCFRecordWrapper::Complete(c) => {
+            let alleles = 1..=c.alternative.len();
+            let mut new_to_original = vec![0];
+            new_to_original.extend(
+                alleles
+                    .clone()
+                    .filter(|x| !is_non_ref_allele(&c.alternative[x - 1])),
+            );
+            new_to_original.extend(alleles.filter(|x| is_non_ref_allele(&c.alternative[x - 1])));
+            if new_to_original.windows(2).all(|x| x[0] < x[1]) {
+                return Ok(VCFRecordWrapper::Complete(c));
+            }
+            Ok(VCFRecordWrapper::Complete(multiallelic::reorder_alleles(
+                &c,
+                rewrite_target,
+                &new_to_original,
+            )))
+        }
+        partial => Ok(partial),
+    }
+}
+
+impl<G: GenomeSequence> VCFLiftOver<G> {
+    /// Lift a gVCF reference block. The block is split at chain block boundaries, and each piece
+    /// is written with its own END. Sample fields (e.g. MIN_DP and GQ) are kept as is.
+    ///
+    /// Pieces lifted with different chains are parts of one candidate unless they overlap in the
+    /// original assembly, so a block across adjacent chains is not treated as multi-mapped.
+    pub(super) fn lift_reference_block<'a>(
+        &mut self,
+        record: &'a PartialVCFRecord,
+        original_chromosome: &str,
+        failure: &mut LiftOverFailure,
+    ) -> Result<Vec<Vec<VCFRecordWrapper<'a>>>, LiftOverError> {
+        if !self.check_original_reference(record, original_chromosome, failure)? {
+            return Ok(Vec::new());
+        }
+
+        let original_record = record.clone().complete_parse()?;
+        let start = record.position() - 1;
+        let end = info_values(&original_record, b"END")
+            .and_then(|x| x.first())
+            .and_then(|x| parse_number::<u64>(x))
+            .unwrap_or_else(|| record.position())
+            .max(record.position());
+
+        let position_liftover = self.variant_lift.position_liftover();
+        let mut chains: Vec<Vec<BlockPiece>> = position_liftover
+            .search_target(original_chromosome, start..end)
+            .into_iter()
+            .map(|one_chain| {
+                let mut pieces: Vec<_> = one_chain
+                    .iter()
+                    .filter(|x| !x.is_in_gap && x.original_len() == x.new_len())
+                    .filter_map(|x| {
+                        let piece_start = start.max(x.original_start);
+                        let piece_end = end.min(x.original_end);
+                        if piece_start >= piece_end {
+                            return None;
+                        }
+                        let (new_start, original_position) = match x.strand {
+                            Strand::Forward => {
+                                (x.new_start + (piece_start - x.original_start), piece_start)
+                            }
+                            Strand::Reverse => {
+                                (x.new_start + (x.original_end - piece_end), piece_end - 1)
+                            }
+                        };
+                        Some(BlockPiece {
+                            chromosome: position_liftover.new_chromosomes()[x.new_chromosome_index]
+                                .name
+                                .to_string(),
+                            original_position,
+                            original_start: piece_start,
+                            original_end: piece_end,
+                            new_start,
+                            new_end: new_start + (piece_end - piece_start),
+                            strand: x.strand,
+                            chain_index: x.chain_index,
+                        })
+                    })
+                    .collect();
+                pieces.sort_by_key(|x| x.new_start);
+                pieces
+            })
+            .filter(|x| !x.is_empty())
+            .collect();
+
+        if chains.len() > 1 {
+            let mut original_ranges: Vec<_> = chains
+                .iter()
+                .flatten()
+                .map(|x| (x.original_start, x.original_end))
+                .collect();
+            original_ranges.sort();
+            if original_ranges.windows(2).all(|x| x[0].1 <= x[1].0) {
+                let mut pieces: Vec<_> = chains.into_iter().flatten().collect();
+                pieces.sort_by(|x, y| {
+                    x.chromosome
+                        .cmp(&y.chromosome)
+                        .then(x.new_start.cmp(&y.new_start))
+                });
+                chains = vec![pieces];
+            }
+        }
+
+        let mut succeeded_records = Vec::new();
+        'candidate: for pieces in chains {
+            let mut lifted_records = Vec::new();
+            for piece in pieces {
+                // both ends of a piece are checked
+                let last_original_position = match piece.strand {
+                    Strand::Forward => piece.original_end - 1,
+                    Strand::Reverse => piece.original_start,
+                };
+                let lifted_bases = [
+                    (piece.original_position, piece.new_start),
+                    (last_original_position, piece.new_end - 1),
+                ]
+                .map(|(original_position, position)| {
+                    (
+                        original_position,
+                        LiftedBase {
+                            chromosome: piece.chromosome.clone(),
+                            position,
+                            strand: piece.strand,
+                            chain_index: piece.chain_index,
+                        },
+                    )
+                });
+                let checked_bases: Vec<_> = lifted_bases
+                    .iter()
+                    .map(|(original_position, lifted)| CheckedBase {
+                        original_chromosome,
+                        original_position: *original_position,
+                        lifted,
+                        reference_changed: false,
+                    })
+                    .collect();
+                let check = if let Some(x) = self.check_lifted_bases(&checked_bases, failure) {
+                    x
+                } else {
+                    continue 'candidate;
+                };
+
+                let new_reference = self.variant_lift.new_sequence_mut().get_sequence(
+                    &piece.chromosome,
+                    piece.new_start,
+                    piece.new_start + 1,
+                )?;
+                let mut new_record = original_record.clone();
+                new_record.contig = Cow::Owned(piece.chromosome.as_bytes().to_vec());
+                new_record.position = piece.new_start + 1;
+                new_record.reference = Cow::Owned(new_reference);
+                set_info(
+                    &mut new_record,
+                    b"END",
+                    Some(vec![Cow::Owned(piece.new_end.to_string().into_bytes())]),
+                );
+                new_record.info.push((
+                    Cow::Owned(b"ORIGINAL_CHROM".to_vec()),
+                    vec![Cow::Owned(record.contig().to_vec())],
+                ));
+                new_record.info.push((
+                    Cow::Owned(b"ORIGINAL_POS".to_vec()),
+                    vec![Cow::Owned(
+                        (piece.original_position + 1).to_string().into_bytes(),
+                    )],
+                ));
+                new_record.info.push((
+                    Cow::Owned(b"ORIGINAL_STRAND".to_vec()),
+                    vec![Cow::Owned(piece.strand.to_string().into_bytes())],
+                ));
+                let mut new_record = VCFRecordWrapper::Complete(new_record);
+                self.annotate_lifted_record(
+                    &mut new_record,
+                    check,
+                    piece.new_end - piece.new_start != end - start,
+                );
+                lifted_records.push(new_record);
+            }
+            succeeded_records.push(lifted_records);
+        }
+
+        Ok(succeeded_records)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chain::ChainFile;
+    use crate::variantlift::VariantLiftOver;
+    use bio::io::fasta::IndexedReader;
+    use std::io::Cursor;
+
+    // original chr1:1-10 and 14-20 are lifted to new chr1:1-17 (3 bp deletion), chr1:21-30 to
+    // new chr2:1-10, and chr1:31-40 to reverse strand of new chr1:31-40.
+    const CHAIN: &[u8] = b"chain 100 chr1 60 + 0 20 chr1 40 + 0 17 1
+10\t3\t0
+7
+
+chain 100 chr1 60 + 20 30 chr2 10 + 0 10 2
+10
+
+chain 100 chr1 60 + 30 40 chr1 40 - 0 10 3
+10
+";
+    const ORIGINAL_FASTA: &[u8] =
+        b">chr1\nACGTACGTACGGGTTTTTCCCCAAAAAGGGGGTTACCAGGTCAACAACAACAACAACAAC\n";
+    const ORIGINAL_FAI: &[u8] = b"chr1\t60\t6\t60\t61\n";
+    const NEW_FASTA: &[u8] =
+        b">chr1\nACGTACGTACTTTTTCCGGGGGGGGGGGGGCCTGGTAACC\n>chr2\nCCAAAAAGGG\n";
+    const NEW_FAI: &[u8] = b"chr1\t40\t6\t40\t41\nchr2\t10\t53\t10\t11\n";
+
+    fn lift_blocks_with_param(
+        line: &str,
+        param: VCFLiftOverParameters,
+    ) -> Result<(Vec<Vec<String>>, LiftOverFailure), LiftOverError> {
+        let variant_lift = VariantLiftOver::new(
+            ChainFile::load(CHAIN)?,
+            IndexedReader::new(Cursor::new(ORIGINAL_FASTA), ORIGINAL_FAI)?,
+            IndexedReader::new(Cursor::new(NEW_FASTA), NEW_FAI)?,
+        );
+        let mut vcf_lift = VCFLiftOver::new(variant_lift, param);
+        let line = format!("{}\n", line);
+        let record = PartialVCFRecord::parse_vcf(1, line.as_bytes())?;
+        let mut failure = LiftOverFailure::default();
+        let lifted = vcf_lift.lift_reference_block(&record, "chr1", &mut failure)?;
+        let lifted = lifted
+            .iter()
+            .map(|candidate| {
+                candidate
+                    .iter()
+                    .map(|x| {
+                        let mut bytes = Vec::new();
+                        x.write(&mut bytes)?;
+                        Ok(String::from_utf8(bytes).unwrap().trim_end().to_string())
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, LiftOverError>>()?;
+        Ok((lifted, failure))
+    }
+
+    fn lift_blocks(line: &str) -> Result<Vec<Vec<String>>, LiftOverError> {
+        let (lifted, failure) = lift_blocks_with_param(line, VCFLiftOverParameters::new())?;
+        assert!(failure.is_empty());
+        Ok(lifted)
+    }
+
+    #[test]
+    fn test_lift_reference_block() -> Result<(), LiftOverError> {
+        // split by a gap in a chain
+        assert_eq!(
+            lift_blocks("chr1\t3\t.\tG\t<NON_REF>\t.\t.\tEND=16\tGT:DP\t0/0:10")?,
+            vec![vec![
+                "chr1\t3\t.\tG\t<NON_REF>\t.\t.\tEND=10;ORIGINAL_CHROM=chr1;ORIGINAL_POS=3;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=18\tGT:DP\t0/0:10",
+                "chr1\t11\t.\tT\t<NON_REF>\t.\t.\tEND=13;ORIGINAL_CHROM=chr1;ORIGINAL_POS=14;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=18\tGT:DP\t0/0:10",
+            ]]
+        );
+
+        // reverse strand
+        assert_eq!(
+            lift_blocks("chr1\t32\t.\tG\t<NON_REF>\t.\t.\tEND=36\tGT:DP\t0/0:10")?,
+            vec![vec![
+                "chr1\t35\t.\tG\t<NON_REF>\t.\t.\tEND=39;ORIGINAL_CHROM=chr1;ORIGINAL_POS=36;ORIGINAL_STRAND=-;LIFT_CONFIDENCE=20\tGT:DP\t0/0:10",
+            ]]
+        );
+
+        // across adjacent chains
+        assert_eq!(
+            lift_blocks("chr1\t16\t.\tT\t<NON_REF>\t.\t.\tEND=25\tGT:DP\t0/0:10")?,
+            vec![vec![
+                "chr1\t13\t.\tT\t<NON_REF>\t.\t.\tEND=17;ORIGINAL_CHROM=chr1;ORIGINAL_POS=16;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=18\tGT:DP\t0/0:10",
+                "chr2\t1\t.\tC\t<NON_REF>\t.\t.\tEND=5;ORIGINAL_CHROM=chr1;ORIGINAL_POS=21;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=18\tGT:DP\t0/0:10",
+            ]]
+        );
+
+        // outside of chains
+        assert!(lift_blocks("chr1\t45\t.\tC\t<NON_REF>\t.\t.\tEND=50\tGT:DP\t0/0:10")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_reference_block() -> Result<(), LiftOverError> {
+        let param = VCFLiftOverParameters::new()
+            .min_confidence(Some(10))
+            .flag_representation_change(true);
+        let (lifted, failure) = lift_blocks_with_param(
+            "chr1\t3\t.\tG\t<NON_REF>\t.\t.\tEND=16\tGT:DP\t0/0:10",
+            param,
+        )?;
+        assert!(failure.is_empty());
+        assert_eq!(
+            lifted,
+            vec![vec![
+                "chr1\t3\t.\tG\t<NON_REF>\t.\t.\tEND=10;ORIGINAL_CHROM=chr1;ORIGINAL_POS=3;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=18;REPRESENTATION_CHANGED=ALLELE_LENGTH_CHANGED\tGT:DP\t0/0:10",
+                "chr1\t11\t.\tT\t<NON_REF>\t.\t.\tEND=13;ORIGINAL_CHROM=chr1;ORIGINAL_POS=14;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=18;REPRESENTATION_CHANGED=ALLELE_LENGTH_CHANGED\tGT:DP\t0/0:10",
+            ]]
+        );
+
+        // a candidate fails if one of its pieces has low confidence
+        let (lifted, failure) = lift_blocks_with_param(
+            "chr1\t3\t.\tG\t<NON_REF>\t.\t.\tEND=16\tGT:DP\t0/0:10",
+            param.min_confidence(Some(19)),
+        )?;
+        assert!(lifted.is_empty());
+        assert_eq!(failure.reasons, vec!["LOW_CONFIDENCE"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_to_gvcf() -> Result<(), LiftOverError> {
+        let mut rewrite_target = VCFHeaderRewriteTarget::default();
+        rewrite_target.format_ref.insert(b"AD".to_vec());
+        rewrite_target.format_genotype.insert(b"PL".to_vec());
+        rewrite_target.format_gt = true;
+        let param = VCFLiftOverParameters::new();
+        let record = PartialVCFRecord::parse_vcf(
+            1,
+            b"chr1\t5\t.\tA\tG,<NON_REF>\t.\t.\t.\tGT:AD:PL\t0/1:3,4,0:40,0,30,50,45,99\n",
+        )?;
+        let to_string = |x: VCFRecordWrapper| {
+            let mut bytes = Vec::new();
+            x.write(&mut bytes).unwrap();
+            String::from_utf8(bytes).unwrap()
+        };
+
+        let variant = LiftedVariant {
+            chromosome: "chr2".to_string(),
+            position: 9,
+            strand: Strand::Forward,
+            original_reference: b"A".to_vec(),
+            reference: b"A".to_vec(),
+            alternative: vec![b"G".to_vec()],
+            reference_changed: false,
+        };
+        assert_eq!(
+            to_string(merge_to_gvcf(&variant, &record, &[1], &param, &rewrite_target)?),
+            "chr2\t10\t.\tA\tG,<NON_REF>\t.\t.\tORIGINAL_CHROM=chr1;ORIGINAL_POS=5;ORIGINAL_STRAND=+\tGT:AD:PL\t0/1:3,4,0:40,0,30,50,45,99\n"
+        );
+
+        // REF and ALT are swapped, and <NON_REF> is moved to the end
+        let variant = LiftedVariant {
+            reference: b"G".to_vec(),
+            reference_changed: true,
+            ..variant
+        };
+        assert_eq!(
+            to_string(merge_to_gvcf(&variant, &record, &[1], &param, &rewrite_target)?),
+            "chr2\t10\t.\tG\tA,<NON_REF>\t.\t.\tORIGINAL_CHROM=chr1;ORIGINAL_POS=5;ORIGINAL_STRAND=+;REF_CHANGED;ORIGINAL_REF=A\tGT:AD:PL\t0/1:4,3,0:30,0,40,45,50,99\n"
+        );
+        Ok(())
+    }
+}
@@ -1,20 +1,47 @@
+mod annotation;
+mod collision;
+mod confidence;
+mod failure;
+mod gvcf;
+mod multiallelic;
+mod preset;
+mod provenance;
+mod repair;
+mod representation;
 mod rewrite_record;
+mod sort;
+mod summary;
+mod svlift;
+mod verify;
 
 use crate::defs::GenomeSequence;
-use crate::variantlift::error::VariantLiftOverError;
+use crate::normalize::NormalizeDirection;
 use crate::variantlift::{LiftedVariant, VariantLiftOver};
 use crate::vcfparse::{
     CompleteVCFRecord, PartialVCFRecord, VCFHeader, VCFHeaderItem, VCFParseError, VCFReader,
     VCFRecord, VCFWriter,
 };
-use crate::{chromosome_priority, LiftOverError, Variant};
+use crate::{LiftOverError, Variant};
+pub use annotation::{InfoHandling, VCFLiftOverInfoHandling};
+use collision::CollisionWriter;
+pub use collision::{CollisionPolicy, COLLISION_POLICIES};
+pub use failure::{FailureReportWriter, LiftOverFailure, TestedRegion};
 use log::{info, warn};
+pub(crate) use multiallelic::split_record;
 use once_cell::sync::Lazy;
+pub use preset::PRESETS;
+pub use provenance::VCFLiftOverProvenance;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+pub(crate) use sort::SortingWriter;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::io::{self, prelude::*};
 use std::str;
+pub use summary::{
+    variant_class, LiftSummary, MultimapSummary, RecordCount, RepresentationSummary,
+};
+pub(crate) use svlift::{is_breakend_allele, is_symbolic_allele};
 
 pub static LIFT_SUCCESS_VCF_HEADER: Lazy<Vec<VCFHeaderItem>> = Lazy::new(|| {
     vec![
@@ -27,13 +54,38 @@ pub static LIFT_SUCCESS_VCF_HEADER: Lazy<Vec<VCFHeaderItem>> = Lazy::new(|| {
     ]
 });
 
+pub static REVERSE_LIFT_MISMATCH_VCF_HEADER: Lazy<VCFHeaderItem> = Lazy::new(|| {
+    VCFHeaderItem::parse(b"##INFO=<ID=REVERSE_LIFT_MISMATCH,Number=0,Type=Flag,Description=\"Lifted variant does not return to the original position and alleles with the reverse chain\">", 0).unwrap()
+});
+
+pub static REPAIRED_REF_VCF_HEADER: Lazy<VCFHeaderItem> = Lazy::new(|| {
+    VCFHeaderItem::parse(b"##INFO=<ID=REPAIRED_REF,Number=1,Type=String,Description=\"REF allele in the input VCF which did not match the original assembly\">", 0).unwrap()
+});
+
+pub static REPRESENTATION_CHANGE_VCF_HEADER: Lazy<Vec<VCFHeaderItem>> = Lazy::new(|| {
+    vec![
+        VCFHeaderItem::parse(b"##INFO=<ID=REPRESENTATION_CHANGED,Number=.,Type=String,Description=\"Representation of a lifted variant differs from a direct coordinate shift (POSITION_SHIFTED, REVERSE_STRAND_SHIFTED or ALLELE_LENGTH_CHANGED)\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=POSITION_SHIFT,Number=1,Type=Integer,Description=\"Difference between a lifted position and a directly shifted position\">", 0).unwrap(),
+    ]
+});
+
+pub static LIFT_CONFIDENCE_VCF_HEADER: Lazy<VCFHeaderItem> = Lazy::new(|| {
+    VCFHeaderItem::parse(b"##INFO=<ID=LIFT_CONFIDENCE,Number=1,Type=Integer,Description=\"Confidence score of liftOver from 0 to 100\">", 0).unwrap()
+});
+
+pub static COLLISION_VCF_HEADER: Lazy<VCFHeaderItem> = Lazy::new(|| {
+    VCFHeaderItem::parse(b"##INFO=<ID=COLLISION,Number=0,Type=Flag,Description=\"Other original records were lifted to the same position with the same alleles\">", 0).unwrap()
+});
+
 pub static LIFT_FAILED_VCF_HEADER: Lazy<Vec<VCFHeaderItem>> = Lazy::new(|| {
     vec![
         VCFHeaderItem::parse(b"##INFO=<ID=MULTIMAP,Number=1,Type=Integer,Description=\"# of multi-mapped regions\">", 0).unwrap(),
-        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_CHROM,Number=1,Type=String,Description=\"Tested chromosome\">", 0).unwrap(),
-        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_START,Number=1,Type=String,Description=\"Tested start\">", 0).unwrap(),
-        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_END,Number=1,Type=String,Description=\"Tested end\">", 0).unwrap(),
-        VCFHeaderItem::parse(b"##INFO=<ID=FAILED_REASON,Number=1,Type=String,Description=\"Reason of liftOver failure\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_CHROM,Number=.,Type=String,Description=\"Tested chromosome in the new assembly\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_START,Number=.,Type=Integer,Description=\"Tested start in the new assembly\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_END,Number=.,Type=Integer,Description=\"Tested end in the new assembly\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=TESTED_CHAIN,Number=.,Type=String,Description=\"Chain ID of tested region\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=GAP_SIZE,Number=.,Type=Integer,Description=\"Length of unacceptable gap in tested region\">", 0).unwrap(),
+        VCFHeaderItem::parse(b"##INFO=<ID=FAILED_REASON,Number=.,Type=String,Description=\"Reason of liftOver failure\">", 0).unwrap(),
         VCFHeaderItem::parse(b"##INFO=<ID=PARTIAL_SUCCESS,Number=0,Type=Flag,Description=\"Variants in other tried region are succeeded to lift over\">", 0).unwrap(),
     ]
 });
@@ -56,6 +108,105 @@ pub struct VCFHeaderRewriteTarget {
     pub format_alt: HashSet<Vec<u8>>,
     pub format_genotype: HashSet<Vec<u8>>,
     pub format_gt: bool,
+    /// INFO tags of a previous liftOver and their new names
+    pub previous_lift_info: HashMap<Vec<u8>, Vec<u8>>,
+    /// INFO tags whose `Allele` sub-field is rewritten, and an index of the sub-field
+    pub allele_annotation: HashMap<Vec<u8>, usize>,
+}
+
+impl VCFHeaderRewriteTarget {
+    /// Find INFO and FORMAT tags to rewrite when REF and ALT are swapped.
+    pub fn new(header: &VCFHeader, param: &VCFLiftOverParameters) -> Self {
+        let mut rewrite_target = VCFHeaderRewriteTarget::default();
+        let mut allele_count: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let mut allele_number: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+
+        for one_item in &header.header_items {
+            if !param.do_not_rewrite_info && one_item.key == b"INFO" {
+                if let Some(id) = one_item.detail.get(&b"ID"[..]) {
+                    if let Some(number) = one_item.detail.get(&b"Number"[..]) {
+                        if !param.do_not_rewrite_allele_frequency
+                            && ALLELE_FREQUENCY_MATCH.is_match(str::from_utf8(id).unwrap())
+                            && number == b"A"
+                        {
+                            rewrite_target.allele_frequency.insert(id.to_vec());
+                        } else if !param.do_not_rewrite_allele_count
+                            && ALLELE_COUNT_MATCH.is_match(str::from_utf8(id).unwrap())
+                            && number == b"A"
+                        {
+                            rewrite_target.allele_count.insert(id.to_vec());
+
+                            let m = ALLELE_COUNT_MATCH
+                                .captures(str::from_utf8(id).unwrap())
+                                .unwrap();
+                            let mut key: Vec<u8> = Vec::new();
+                            key.extend_from_slice(
+                                m.get(1).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
+                            );
+                            key.push(b' ');
+                            key.extend_from_slice(
+                                m.get(2).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
+                            );
+                            allele_count.insert(id.to_vec(), key);
+                        } else if !param.do_not_rewrite_allele_count
+                            && ALLELE_NUMBER_MATCH.is_match(str::from_utf8(id).unwrap())
+                            && number == b"1"
+                        {
+                            rewrite_target.allele_number.insert(id.to_vec());
+                            let m = ALLELE_NUMBER_MATCH
+                                .captures(str::from_utf8(id).unwrap())
+                                .unwrap();
+                            let mut key: Vec<u8> = Vec::new();
+                            key.extend_from_slice(
+                                m.get(1).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
+                            );
+                            key.push(b' ');
+                            key.extend_from_slice(
+                                m.get(2).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
+                            );
+                            allele_number.insert(key, id.to_vec());
+                        } else {
+                            match &number as &[u8] {
+                                b"G" => rewrite_target.info_genotype.insert(id.to_vec()),
+                                b"R" => rewrite_target.info_ref.insert(id.to_vec()),
+                                b"A" => rewrite_target.info_alt.insert(id.to_vec()),
+                                _ => false,
+                            };
+                        }
+                    }
+                }
+            }
+
+            if !param.do_not_rewrite_format && one_item.key == b"FORMAT" {
+                if let Some(id) = one_item.detail.get(&b"ID"[..]) {
+                    if let Some(number) = one_item.detail.get(&b"Number"[..]) {
+                        match &number as &[u8] {
+                            b"G" => rewrite_target.format_genotype.insert(id.to_vec()),
+                            b"R" => rewrite_target.format_ref.insert(id.to_vec()),
+                            b"A" => rewrite_target.format_alt.insert(id.to_vec()),
+                            _ => false,
+                        };
+                    }
+
+                    if id == b"GT" {
+                        rewrite_target.format_gt = true;
+                    }
+                }
+            }
+        }
+
+        //println!("allele count: {:?}", allele_count);
+        //println!("allele number: {:?}", allele_number);
+        for (k, v) in allele_count.iter() {
+            if let Some(x) = allele_number.get(v) {
+                rewrite_target
+                    .allele_count_to_allele_number
+                    .insert(k.to_vec(), x.to_vec());
+            }
+        }
+
+        rewrite_target
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -118,10 +269,11 @@ impl<'a> VCFRecord for VCFRecordWrapper<'a> {
 #[derive(Debug, PartialEq, Clone)]
 pub enum VCFLiftOverResult<'a> {
     Succeeded(Vec<VCFRecordWrapper<'a>>),
-    Failed(Box<PartialVCFRecord<'a>>),
+    Failed(Box<PartialVCFRecord<'a>>, LiftOverFailure),
 }
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct VCFLiftOverParameters {
     pub allow_multimap: bool,
     pub acceptable_deletion: u64,
@@ -135,6 +287,20 @@ pub struct VCFLiftOverParameters {
     pub do_not_left_align_chain_file: bool,
     pub do_not_use_dot_when_alt_equal_to_ref: bool,
     pub do_not_prefer_cis_contig_when_multimap: bool,
+    pub merge_multi_allelic: bool,
+    pub split_multi_allelic: bool,
+    pub gvcf: bool,
+    pub sort: bool,
+    pub sort_buffer_size: usize,
+    pub only_used_contigs: bool,
+    pub write_contig_md5: bool,
+    pub fail_reverse_lift_mismatch: bool,
+    pub accept_iupac_reference: bool,
+    pub repair_reference: bool,
+    pub flag_representation_change: bool,
+    pub min_confidence: Option<u32>,
+    pub collision_policy: Option<CollisionPolicy>,
+    pub normalize_direction: NormalizeDirection,
 }
 
 impl VCFLiftOverParameters {
@@ -152,6 +318,20 @@ impl VCFLiftOverParameters {
             do_not_left_align_chain_file: false,
             do_not_use_dot_when_alt_equal_to_ref: false,
             do_not_prefer_cis_contig_when_multimap: false,
+            merge_multi_allelic: false,
+            split_multi_allelic: false,
+            gvcf: false,
+            sort: false,
+            sort_buffer_size: 512 * 1024 * 1024,
+            only_used_contigs: false,
+            write_contig_md5: false,
+            fail_reverse_lift_mismatch: false,
+            accept_iupac_reference: false,
+            repair_reference: false,
+            flag_representation_change: false,
+            min_confidence: None,
+            collision_policy: None,
+            normalize_direction: NormalizeDirection::Left,
         }
     }
 
@@ -220,6 +400,95 @@ impl VCFLiftOverParameters {
         self.do_not_prefer_cis_contig_when_multimap = do_not_prefer_cis_contig_when_multimap;
         self
     }
+
+    /// Merge consecutive records at the same position into one multi-allelic record before liftover.
+    pub fn merge_multi_allelic(mut self, merge_multi_allelic: bool) -> Self {
+        self.merge_multi_allelic = merge_multi_allelic;
+        self
+    }
+
+    /// Split merged multi-allelic records into bi-allelic records after liftover.
+    pub fn split_multi_allelic(mut self, split_multi_allelic: bool) -> Self {
+        self.split_multi_allelic = split_multi_allelic;
+        self
+    }
+
+    /// Lift gVCF reference blocks and keep `<NON_REF>` alleles.
+    pub fn gvcf(mut self, gvcf: bool) -> Self {
+        self.gvcf = gvcf;
+        self
+    }
+
+    /// Sort succeeded records by contig order in the lifted header and position.
+    pub fn sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Maximum size of records in memory while sorting. Sorted chunks are written to temporary files if the size is exceeded.
+    pub fn sort_buffer_size(mut self, sort_buffer_size: usize) -> Self {
+        self.sort_buffer_size = sort_buffer_size;
+        self
+    }
+
+    pub fn only_used_contigs(mut self, only_used_contigs: bool) -> Self {
+        self.only_used_contigs = only_used_contigs;
+        self
+    }
+
+    /// Compute MD5 of new assembly contigs for `##contig` lines. The whole assembly is read.
+    pub fn write_contig_md5(mut self, write_contig_md5: bool) -> Self {
+        self.write_contig_md5 = write_contig_md5;
+        self
+    }
+
+    /// Fail records which do not return to the original position and alleles with a reverse chain
+    /// instead of adding `REVERSE_LIFT_MISMATCH` flag.
+    pub fn fail_reverse_lift_mismatch(mut self, fail_reverse_lift_mismatch: bool) -> Self {
+        self.fail_reverse_lift_mismatch = fail_reverse_lift_mismatch;
+        self
+    }
+
+    /// Replace IUPAC codes in REF with bases of the original assembly before liftover.
+    pub fn accept_iupac_reference(mut self, accept_iupac_reference: bool) -> Self {
+        self.accept_iupac_reference = accept_iupac_reference;
+        self
+    }
+
+    /// Replace REF which does not match the original assembly with bases of the original assembly
+    /// before liftover. REF and ALT are swapped if bases of the original assembly are equal to ALT.
+    pub fn repair_reference(mut self, repair_reference: bool) -> Self {
+        self.repair_reference = repair_reference;
+        self
+    }
+
+    /// Add `REPRESENTATION_CHANGED` and `POSITION_SHIFT` into INFO column if a lifted variant is
+    /// re-normalized in the new assembly.
+    pub fn flag_representation_change(mut self, flag_representation_change: bool) -> Self {
+        self.flag_representation_change = flag_representation_change;
+        self
+    }
+
+    /// Fail records whose confidence score in `LIFT_CONFIDENCE` INFO is lower than
+    /// `min_confidence`.
+    pub fn min_confidence(mut self, min_confidence: Option<u32>) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Resolve lifted records from different original records at the same position with the same
+    /// alleles. Collisions are detected while sorting, so this option requires `sort`.
+    pub fn collision_policy(mut self, collision_policy: Option<CollisionPolicy>) -> Self {
+        self.collision_policy = collision_policy;
+        self
+    }
+
+    /// Shift lifted indels to the left-most (VCF) or the right-most (HGVS) position in the new
+    /// assembly. Lifted variants are verified and scored before they are shifted.
+    pub fn normalize_direction(mut self, normalize_direction: NormalizeDirection) -> Self {
+        self.normalize_direction = normalize_direction;
+        self
+    }
 }
 
 impl Default for VCFLiftOverParameters {
@@ -232,25 +501,12 @@ impl Default for VCFLiftOverParameters {
 pub struct VCFLiftOver<G: GenomeSequence> {
     variant_lift: VariantLiftOver<G>,
     param: VCFLiftOverParameters,
-}
-
-fn lift_header_show_warnings(
-    rewrite_target: &mut VCFHeaderRewriteTarget,
-    param: &VCFLiftOverParameters,
-) {
-    if !rewrite_target.info_genotype.is_empty()
-        && !param.do_not_swap_ref_alt
-        && !param.do_not_rewrite_info
-    {
-        warn!("Rewriting INFO with Number=G is not implemented.");
-    }
-
-    if !rewrite_target.format_genotype.is_empty()
-        && !param.do_not_swap_ref_alt
-        && !param.do_not_rewrite_format
-    {
-        warn!("Rewriting FORMAT with Number=G is not implemented.");
-    }
+    provenance: VCFLiftOverProvenance,
+    info_handling: VCFLiftOverInfoHandling,
+    /// liftOver from the new assembly to the original assembly to verify lifted variants
+    reverse_variant_lift: Option<VariantLiftOver<G>>,
+    /// multi-mapped candidates of the last lifted record were reduced to the same name contig
+    last_resolved_by_same_contig: bool,
 }
 
 impl<G: GenomeSequence> VCFLiftOver<G> {
@@ -258,21 +514,43 @@ impl<G: GenomeSequence> VCFLiftOver<G> {
         VCFLiftOver {
             variant_lift,
             param,
+            provenance: VCFLiftOverProvenance::default(),
+            info_handling: VCFLiftOverInfoHandling::default(),
+            reverse_variant_lift: None,
+            last_resolved_by_same_contig: false,
         }
     }
 
+    /// Set information written into a header of lifted VCF
+    pub fn provenance(mut self, provenance: VCFLiftOverProvenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Set INFO tags with positional annotations to drop, mark stale or rewrite
+    pub fn info_handling(mut self, info_handling: VCFLiftOverInfoHandling) -> Self {
+        self.info_handling = info_handling;
+        self
+    }
+
+    /// Lift succeeded variants back with `reverse_variant_lift`, and mark or fail variants which
+    /// do not return to the original position and alleles.
+    pub fn verify_with_reverse_lift(mut self, reverse_variant_lift: VariantLiftOver<G>) -> Self {
+        self.reverse_variant_lift = Some(reverse_variant_lift);
+        self
+    }
+
     pub fn lift_header(
-        &self,
+        &mut self,
         header: &VCFHeader,
     ) -> Result<(VCFHeader, VCFHeaderRewriteTarget), LiftOverError> {
         let mut new_header_items = Vec::new();
-        let mut rewrite_target = VCFHeaderRewriteTarget::default();
-        let mut allele_count: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
-        let mut allele_number: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
 
         for one_item in &header.header_items {
             if one_item.key == b"contig" {
                 self.check_contig_length_helper(&one_item)?;
+            } else if one_item.key == b"reference" && self.provenance.new_assembly.is_some() {
+                // replaced with the new assembly
             } else {
                 new_header_items.push(one_item.clone())
             }
@@ -285,112 +563,39 @@ impl<G: GenomeSequence> VCFLiftOver<G> {
                     warn!("Input VCF looks like COSMIC. You should add --noswap option to avoid swapping reference and alternative allele");
                 }
             }
-
-            if !self.param.do_not_rewrite_info && one_item.key == b"INFO" {
-                if let Some(id) = one_item.detail.get(&b"ID"[..]) {
-                    if let Some(number) = one_item.detail.get(&b"Number"[..]) {
-                        if !self.param.do_not_rewrite_allele_frequency
-                            && ALLELE_FREQUENCY_MATCH.is_match(str::from_utf8(id).unwrap())
-                            && number == b"A"
-                        {
-                            rewrite_target.allele_frequency.insert(id.to_vec());
-                        } else if !self.param.do_not_rewrite_allele_count
-                            && ALLELE_COUNT_MATCH.is_match(str::from_utf8(id).unwrap())
-                            && number == b"A"
-                        {
-                            rewrite_target.allele_count.insert(id.to_vec());
-
-                            let m = ALLELE_COUNT_MATCH
-                                .captures(str::from_utf8(id).unwrap())
-                                .unwrap();
-                            let mut key: Vec<u8> = Vec::new();
-                            key.extend_from_slice(
-                                m.get(1).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
-                            );
-                            key.push(b' ');
-                            key.extend_from_slice(
-                                m.get(2).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
-                            );
-                            allele_count.insert(id.to_vec(), key);
-                        } else if !self.param.do_not_rewrite_allele_count
-                            && ALLELE_NUMBER_MATCH.is_match(str::from_utf8(id).unwrap())
-                            && number == b"1"
-                        {
-                            rewrite_target.allele_number.insert(id.to_vec());
-                            let m = ALLELE_NUMBER_MATCH
-                                .captures(str::from_utf8(id).unwrap())
-                                .unwrap();
-                            let mut key: Vec<u8> = Vec::new();
-                            key.extend_from_slice(
-                                m.get(1).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
-                            );
-                            key.push(b' ');
-                            key.extend_from_slice(
-                                m.get(2).map(|x| x.as_str().as_bytes()).unwrap_or(b""),
-                            );
-                            allele_number.insert(key, id.to_vec());
-                        } else {
-                            match &number as &[u8] {
-                                b"G" => rewrite_target.info_genotype.insert(id.to_vec()),
-                                b"R" => rewrite_target.info_ref.insert(id.to_vec()),
-                                b"A" => rewrite_target.info_alt.insert(id.to_vec()),
-                                _ => false,
-                            };
-                        }
-                    }
-                }
-            }
-
-            if !self.param.do_not_rewrite_format && one_item.key == b"FORMAT" {
-                if let Some(id) = one_item.detail.get(&b"ID"[..]) {
-                    if let Some(number) = one_item.detail.get(&b"Number"[..]) {
-                        match &number as &[u8] {
-                            b"G" => rewrite_target.format_genotype.insert(id.to_vec()),
-                            b"R" => rewrite_target.format_ref.insert(id.to_vec()),
-                            b"A" => rewrite_target.format_alt.insert(id.to_vec()),
-                            _ => false,
-                        };
-                    }
-
-                    if id == b"GT" {
-                        rewrite_target.format_gt = true;
-                    }
-                }
-            }
         }
 
-        //println!("allele count: {:?}", allele_count);
-        //println!("allele number: {:?}", allele_number);
-        for (k, v) in allele_count.iter() {
-            if let Some(x) = allele_number.get(v) {
-                rewrite_target
-                    .allele_count_to_allele_number
-                    .insert(k.to_vec(), x.to_vec());
-            }
+        let mut rewrite_target = VCFHeaderRewriteTarget::new(header, &self.param);
+        rewrite_target.previous_lift_info = provenance::previous_lift_info(header);
+        if !rewrite_target.previous_lift_info.is_empty() {
+            info!("Input VCF was already lifted. INFO tags of the previous liftOver are renamed.");
+            provenance::rename_previous_lift_header(
+                &mut new_header_items,
+                &rewrite_target.previous_lift_info,
+            )?;
         }
 
-        lift_header_show_warnings(&mut rewrite_target, &self.param);
-
-        let mut chromosomes = self
-            .variant_lift
-            .position_liftover()
-            .new_chromosomes()
-            .to_vec();
-        chromosomes.sort_by_key(|x| (chromosome_priority(&x.name), x.length));
-        for one_chromosome in chromosomes {
-            new_header_items.push(VCFHeaderItem::parse(
-                &format!(
-                    "##contig=<ID={},length={}>",
-                    one_chromosome.name, one_chromosome.length
-                )
-                .as_bytes(),
-                0,
-            )?);
-        }
+        rewrite_target.allele_annotation =
+            self.info_handling.rewrite_header(&mut new_header_items)?;
+        new_header_items.extend(self.provenance.header_items()?);
+        new_header_items.extend(self.contig_header_items(header)?);
 
         for one_item in LIFT_SUCCESS_VCF_HEADER.iter() {
             new_header_items.push(one_item.clone());
         }
+        if self.param.accept_iupac_reference || self.param.repair_reference {
+            new_header_items.push(REPAIRED_REF_VCF_HEADER.clone());
+        }
+        if self.reverse_variant_lift.is_some() && !self.param.fail_reverse_lift_mismatch {
+            new_header_items.push(REVERSE_LIFT_MISMATCH_VCF_HEADER.clone());
+        }
+        new_header_items.push(LIFT_CONFIDENCE_VCF_HEADER.clone());
+        if self.param.sort && self.param.collision_policy == Some(CollisionPolicy::KeepAll) {
+            new_header_items.push(COLLISION_VCF_HEADER.clone());
+        }
+        if self.param.flag_representation_change {
+            new_header_items.extend(REPRESENTATION_CHANGE_VCF_HEADER.iter().cloned());
+        }
         Ok((
             VCFHeader {
                 header_items: new_header_items,
@@ -406,24 +611,48 @@ impl<G: GenomeSequence> VCFLiftOver<G> {
         rewrite_target: &VCFHeaderRewriteTarget,
     ) -> Result<VCFLiftOverResult<'a>, LiftOverError> {
         let mut original_variant: Variant = record.into();
+        original_variant.chromosome = self.original_chromosome_name(record.contig());
+
+        if self.param.gvcf && gvcf::is_reference_block(record) {
+            let mut failure = LiftOverFailure::default();
+            let succeeded_records =
+                self.lift_reference_block(record, &original_variant.chromosome, &mut failure)?;
+            return self.finish_lift_record(
+                record,
+                &original_variant.chromosome,
+                succeeded_records,
+                failure,
+            );
+        }
 
-        // add chr prefix if it is required
-        if self
-            .variant_lift
-            .position_liftover()
-            .original_chromosome_by_name(str::from_utf8(record.contig()).unwrap())
-            .is_none()
-            && !original_variant.chromosome.starts_with("chr")
-        {
-            let chr_added_name = format!("chr{}", original_variant.chromosome);
-            if self
-                .variant_lift
-                .position_liftover()
-                .original_chromosome_by_name(&chr_added_name)
-                .is_some()
-            {
-                original_variant.chromosome = chr_added_name;
-            }
+        if svlift::is_structural_variant(record) {
+            let mut failure = LiftOverFailure::default();
+            let succeeded_records =
+                self.lift_structural_variant(record, &original_variant.chromosome, &mut failure)?;
+            return self.finish_lift_record(
+                record,
+                &original_variant.chromosome,
+                succeeded_records,
+                failure,
+            );
+        }
+
+        // <NON_REF> alleles are removed before liftover, and inserted back after liftover.
+        let non_ref_index: Vec<_> = if self.param.gvcf {
+            record
+                .alternative()
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| gvcf::is_non_ref_allele(x))
+                .map(|(i, _)| i)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if !non_ref_index.is_empty() {
+            original_variant
+                .alternative
+                .retain(|x| !gvcf::is_non_ref_allele(x));
         }
 
         let lifted_variant = self.variant_lift.lift_variant(
@@ -434,198 +663,625 @@ impl<G: GenomeSequence> VCFLiftOver<G> {
 
         let mut succeeded_records = Vec::new();
 
-        let mut failed_reasons = HashSet::new();
+        let mut failure = LiftOverFailure::default();
         for one in lifted_variant.iter() {
             match one {
                 Ok(ok) => {
-                    let new_record = merge_to_vcf(&ok, record, &self.param, rewrite_target)?;
-                    succeeded_records.push(new_record);
-                }
-                Err(e) => match e {
-                    VariantLiftOverError::UnacceptableLargeDeletion { .. } => {
-                        failed_reasons.insert("UNACCEPTABLE_LARGE_DELETION");
+                    let verified =
+                        if let Some(reverse_variant_lift) = self.reverse_variant_lift.as_mut() {
+                            verify::returns_to_original(
+                                reverse_variant_lift,
+                                &original_variant,
+                                ok,
+                                self.param.acceptable_deletion,
+                                self.param.acceptable_insertion,
+                            )?
+                        } else {
+                            true
+                        };
+                    if !verified && self.param.fail_reverse_lift_mismatch {
+                        failure.add_reason(verify::REVERSE_LIFT_MISMATCH);
+                        failure.add_region(
+                            &ok.chromosome,
+                            ok.position,
+                            ok.position + ok.reference.len() as u64,
+                            None,
+                        );
+                        continue;
                     }
-                    VariantLiftOverError::UnacceptableLargeInsertion { .. } => {
-                        failed_reasons.insert("UNACCEPTABLE_LARGE_INSERTION");
+
+                    let confidence = confidence::ConfidenceEvidence::collect(
+                        self.variant_lift.position_liftover(),
+                        &original_variant,
+                        ok,
+                    )
+                    .score();
+                    if self
+                        .param
+                        .min_confidence
+                        .is_some_and(|min_confidence| confidence < min_confidence)
+                    {
+                        failure.add_reason(confidence::LOW_CONFIDENCE);
+                        failure.add_region(
+                            &ok.chromosome,
+                            ok.position,
+                            ok.position + ok.reference.len() as u64,
+                            None,
+                        );
+                        continue;
                     }
-                    VariantLiftOverError::UnknownSequenceName(_chrom) => {
-                        failed_reasons.insert("UNKNOWN_SEQUENCE_NAME");
+
+                    let shifted;
+                    let ok = if self.param.normalize_direction == NormalizeDirection::Left {
+                        ok
+                    } else {
+                        shifted = ok.normalize_with_direction(
+                            self.variant_lift.new_sequence_mut(),
+                            self.param.normalize_direction,
+                        )?;
+                        &shifted
+                    };
+
+                    let mut new_record = if non_ref_index.is_empty() {
+                        let mut new_record =
+                            merge_to_vcf(&ok, record, &self.param, rewrite_target)?;
+                        annotation::rewrite_allele_info(
+                            &mut new_record,
+                            record,
+                            &ok.alternative,
+                            &rewrite_target.allele_annotation,
+                        );
+                        new_record
+                    } else {
+                        gvcf::merge_to_gvcf(
+                            &ok,
+                            record,
+                            &non_ref_index,
+                            &self.param,
+                            rewrite_target,
+                        )?
+                    };
+                    if !verified {
+                        verify::add_mismatch_info(&mut new_record);
                     }
-                    VariantLiftOverError::ReferenceSequenceIsNotMatch => {
-                        failed_reasons.insert("UNEXPECTED_REF");
+                    confidence::add_confidence_info(&mut new_record, confidence);
+                    if self.param.flag_representation_change {
+                        let (changes, shift) = representation::representation_changes(
+                            self.variant_lift.position_liftover(),
+                            &original_variant,
+                            ok,
+                        );
+                        representation::add_representation_info(&mut new_record, &changes, shift);
                     }
-                },
+                    succeeded_records.push(vec![new_record]);
+                }
+                Err(e) => failure.add_variant_error(e),
+            }
+        }
+
+        self.finish_lift_record(
+            record,
+            &original_variant.chromosome,
+            succeeded_records,
+            failure,
+        )
+    }
+
+    /// Add `chr` prefix to a contig name if the chain file requires it.
+    fn original_chromosome_name(&self, contig: &[u8]) -> String {
+        let name = str::from_utf8(contig).unwrap();
+        if self
+            .variant_lift
+            .position_liftover()
+            .original_chromosome_by_name(name)
+            .is_none()
+            && !name.starts_with("chr")
+        {
+            let chr_added_name = format!("chr{}", name);
+            if self
+                .variant_lift
+                .position_liftover()
+                .original_chromosome_by_name(&chr_added_name)
+                .is_some()
+            {
+                return chr_added_name;
             }
         }
+        name.to_string()
+    }
 
+    /// Create a lift over result from lifted candidates. A candidate may consist of multiple records.
+    fn finish_lift_record<'a>(
+        &mut self,
+        record: &'a PartialVCFRecord,
+        original_chromosome: &str,
+        mut succeeded_records: Vec<Vec<VCFRecordWrapper<'a>>>,
+        mut failure: LiftOverFailure,
+    ) -> Result<VCFLiftOverResult<'a>, LiftOverError> {
         // create failed record if succeeded record list is empty and some failed reasons are found.
-        if succeeded_records.is_empty() && !failed_reasons.is_empty() {
+        if succeeded_records.is_empty() && !failure.is_empty() {
             let mut new_record = record.clone();
-            if new_record.unparsed_info == &b"."[..] {
-                new_record.unparsed_info = Cow::Owned(b"FAILED_REASON=".to_vec());
-            } else {
-                write!(new_record.unparsed_info.to_mut(), ";FAILED_REASON=",)?;
-            }
-            for (i, one_reason) in failed_reasons.iter().enumerate() {
-                if i != 0 {
-                    write!(new_record.unparsed_info.to_mut(), ",")?;
-                }
-                write!(new_record.unparsed_info.to_mut(), "{}", one_reason)?;
-            }
-            return Ok(VCFLiftOverResult::Failed(Box::new(new_record)));
+            failure.write_info(&mut new_record)?;
+            return Ok(VCFLiftOverResult::Failed(Box::new(new_record), failure));
         }
 
         // use cis mapped variants if it was found.
+        self.last_resolved_by_same_contig = false;
         if !self.param.do_not_prefer_cis_contig_when_multimap
             && succeeded_records
                 .iter()
-                .any(|x| x.contig() != original_variant.chromosome.as_bytes())
+                .any(|x| x[0].contig() != original_chromosome.as_bytes())
             && succeeded_records
                 .iter()
-                .any(|x| x.contig() == original_variant.chromosome.as_bytes())
+                .any(|x| x[0].contig() == original_chromosome.as_bytes())
         {
-            succeeded_records.retain(|x| x.contig() == original_variant.chromosome.as_bytes());
+            succeeded_records.retain(|x| x[0].contig() == original_chromosome.as_bytes());
+            self.last_resolved_by_same_contig = succeeded_records.len() == 1;
         }
 
         // multi-map check
         if succeeded_records.len() > 1 {
             if !self.param.allow_multimap {
-                let mut new_record = record.clone();
-                if new_record.unparsed_info == &b"."[..] {
-                    new_record.unparsed_info = Cow::Borrowed(b"");
-                } else {
-                    new_record.unparsed_info.to_mut().push(b';');
+                let mut failure = LiftOverFailure {
+                    multimap: Some(succeeded_records.len()),
+                    ..Default::default()
+                };
+                failure.add_reason("MULTIMAP");
+                for one in succeeded_records.iter().flatten() {
+                    failure.add_region(
+                        str::from_utf8(one.contig()).unwrap(),
+                        one.position() - 1,
+                        one.position() - 1 + one.reference().len() as u64,
+                        None,
+                    );
                 }
-                write!(
-                    new_record.unparsed_info.to_mut(),
-                    "FAILED_REASON=MULTIMAP;MULTIMAP={}",
-                    succeeded_records.len()
-                )?;
-                return Ok(VCFLiftOverResult::Failed(Box::new(new_record)));
+                let mut new_record = record.clone();
+                failure.write_info(&mut new_record)?;
+                return Ok(VCFLiftOverResult::Failed(Box::new(new_record), failure));
             } else {
                 let success_count = succeeded_records.len();
                 succeeded_records
                     .iter_mut()
+                    .flatten()
                     .for_each(|mut x| add_multimap_info_helper(&mut x, success_count));
             }
         }
 
         // no chain was found.
         if succeeded_records.is_empty() {
+            failure.add_reason("NO_CHAIN");
             let mut new_record = record.clone();
-            write!(new_record.unparsed_info.to_mut(), "FAILED_REASON=NO_CHAIN")?;
-            return Ok(VCFLiftOverResult::Failed(Box::new(new_record)));
+            failure.write_info(&mut new_record)?;
+            return Ok(VCFLiftOverResult::Failed(Box::new(new_record), failure));
+        }
+
+        if !self.info_handling.is_empty() {
+            succeeded_records
+                .iter_mut()
+                .flatten()
+                .for_each(|x| self.info_handling.drop_info(x));
         }
 
-        Ok(VCFLiftOverResult::Succeeded(succeeded_records))
+        Ok(VCFLiftOverResult::Succeeded(
+            succeeded_records.into_iter().flatten().collect(),
+        ))
     }
 
-    pub fn lift_vcf<R: Read, W1: Write, W2: Write, W3: Write>(
+    pub fn lift_vcf<R: Read, W1: Write, W2: Write, W3: Write, W4: Write>(
         &mut self,
         reader: R,
         success_writer: W1,
         failed_writer: W2,
-        mut summary_writer: Option<W3>,
-    ) -> Result<(), LiftOverError> {
-        let mut vcf_reader = VCFReader::new(reader)?;
+        summary_writer: Option<W3>,
+        failure_report_writer: Option<W4>,
+    ) -> Result<LiftSummary, LiftOverError> {
+        self.lift_vcf_reader(
+            VCFReader::new(reader)?,
+            success_writer,
+            failed_writer,
+            summary_writer,
+            failure_report_writer,
+        )
+    }
+
+    /// Same as [`VCFLiftOver::lift_vcf`], but takes a reader whose header is already parsed.
+    /// Use this to choose parameters from the header without reading the input twice.
+    pub fn lift_vcf_reader<R: Read, W1: Write, W2: Write, W3: Write, W4: Write>(
+        &mut self,
+        mut vcf_reader: VCFReader<io::BufReader<R>>,
+        success_writer: W1,
+        failed_writer: W2,
+        summary_writer: Option<W3>,
+        failure_report_writer: Option<W4>,
+    ) -> Result<LiftSummary, LiftOverError> {
         let lifted_header = self.lift_header(&vcf_reader.header)?;
-        let mut success_vcf_writer =
-            VCFWriter::new(io::BufWriter::new(success_writer), lifted_header.0)?;
 
         let mut failed_header = vcf_reader.header.clone();
+        provenance::rename_previous_lift_header(
+            &mut failed_header.header_items,
+            &lifted_header.1.previous_lift_info,
+        )?;
         for one_item in LIFT_FAILED_VCF_HEADER.iter() {
             failed_header.header_items.push(one_item.clone());
         }
+        if self.param.accept_iupac_reference || self.param.repair_reference {
+            failed_header
+                .header_items
+                .push(REPAIRED_REF_VCF_HEADER.clone());
+        }
 
         let mut failed_vcf_writer = VCFWriter::new(failed_writer, failed_header)?;
+        let mut failure_report = failure_report_writer
+            .map(FailureReportWriter::new)
+            .transpose()?;
+
+        let counter = if self.param.sort {
+            if let Some(policy) = self.param.collision_policy {
+                let (collision_writer, mut counter) = self.lift_vcf_sorted(
+                    &mut vcf_reader,
+                    lifted_header,
+                    CollisionWriter::new(io::BufWriter::new(success_writer), policy)?,
+                    &mut failed_vcf_writer,
+                    &mut failure_report,
+                )?;
+                let (mut writer, removed, kept) = collision_writer.finish()?;
+                writer.flush()?;
+                counter.summary.collision.mapped += kept;
+
+                // other lifted records of removed original records are also removed
+                for (line, original) in removed {
+                    let mut record = PartialVCFRecord::parse_vcf(line, &original)?;
+                    let mut failure = LiftOverFailure::default();
+                    failure.add_reason(collision::COLLISION);
+                    let flags = counter.mapped_flags.remove(&line).unwrap_or_default();
+                    counter.summary.move_to_unmapped(&record, &flags, &failure);
+                    if let Some(failure_report) = failure_report.as_mut() {
+                        failure_report.write(&record, &failure)?;
+                    }
+                    failure.write_info(&mut record)?;
+                    failed_vcf_writer.write_record(&record)?;
+                }
+                counter
+            } else {
+                let (mut writer, counter) = self.lift_vcf_sorted(
+                    &mut vcf_reader,
+                    lifted_header,
+                    io::BufWriter::new(success_writer),
+                    &mut failed_vcf_writer,
+                    &mut failure_report,
+                )?;
+                writer.flush()?;
+                counter
+            }
+        } else {
+            let mut success_vcf_writer =
+                VCFWriter::new(io::BufWriter::new(success_writer), lifted_header.0)?;
+            let counter = self.lift_vcf_records(
+                &mut vcf_reader,
+                &lifted_header.1,
+                &mut success_vcf_writer,
+                &mut failed_vcf_writer,
+                &mut failure_report,
+            )?;
+            success_vcf_writer.into_inner().flush()?;
+            counter
+        };
+        failed_vcf_writer.into_inner().flush()?;
+        if let Some(failure_report) = failure_report.as_mut() {
+            failure_report.flush()?;
+        }
+
+        let summary = counter.summary;
+        eprintln!("    Total record: {}", summary.total.total());
+        eprintln!("   Mapped record: {}", summary.total.mapped);
+        eprintln!(" Unmapped record: {}", summary.total.unmapped);
+
+        if let Some(summary_writer) = summary_writer {
+            summary.write_tsv(summary_writer)?;
+        }
 
-        let mut multi_allelic_warned = (self.param.do_not_rewrite_allele_frequency
-            || lifted_header.1.allele_frequency.is_empty())
-            && (self.param.do_not_rewrite_allele_count || lifted_header.1.allele_count.is_empty());
+        Ok(summary)
+    }
+
+    /// Lift records and write succeeded records into `success_writer` in sorted order.
+    fn lift_vcf_sorted<R: Read, W1: Write, W2: Write, W3: Write>(
+        &mut self,
+        vcf_reader: &mut VCFReader<io::BufReader<R>>,
+        lifted_header: (VCFHeader, VCFHeaderRewriteTarget),
+        success_writer: W1,
+        failed_vcf_writer: &mut VCFWriter<W2>,
+        failure_report: &mut Option<FailureReportWriter<W3>>,
+    ) -> Result<(W1, LiftCounter), LiftOverError> {
+        let contigs: Vec<_> = lifted_header
+            .0
+            .header_items
+            .iter()
+            .filter(|x| x.key == b"contig")
+            .filter_map(|x| x.detail.get(&b"ID"[..]).cloned())
+            .collect();
+        let mut success_vcf_writer = VCFWriter::new(
+            SortingWriter::new(success_writer, contigs, self.param.sort_buffer_size),
+            lifted_header.0,
+        )?;
+        let counter = self.lift_vcf_records(
+            vcf_reader,
+            &lifted_header.1,
+            &mut success_vcf_writer,
+            failed_vcf_writer,
+            failure_report,
+        )?;
+        let writer = success_vcf_writer
+            .into_inner()
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .finish()?;
+        Ok((writer, counter))
+    }
+
+    fn lift_vcf_records<R: Read, W1: Write, W2: Write, W3: Write>(
+        &mut self,
+        vcf_reader: &mut VCFReader<io::BufReader<R>>,
+        rewrite_target: &VCFHeaderRewriteTarget,
+        success_vcf_writer: &mut VCFWriter<W1>,
+        failed_vcf_writer: &mut VCFWriter<W2>,
+        failure_report: &mut Option<FailureReportWriter<W3>>,
+    ) -> Result<LiftCounter, LiftOverError> {
+        let mut multi_allelic_warned = self.param.merge_multi_allelic
+            || ((self.param.do_not_rewrite_allele_frequency
+                || rewrite_target.allele_frequency.is_empty())
+                && (self.param.do_not_rewrite_allele_count
+                    || rewrite_target.allele_count.is_empty()));
         let mut last_position = 0;
 
-        let mut warn_chrom = HashSet::new();
-        let mut succeeded_records = 0;
-        let mut failed_records = 0;
+        // Number of values of INFO/FORMAT are required to merge records even if rewriting is disabled.
+        let merge_target =
+            VCFHeaderRewriteTarget::new(&vcf_reader.header, &VCFLiftOverParameters::new());
+        let mut multi_allelic_group: Vec<(u32, Vec<u8>)> = Vec::new();
+        let mut multi_allelic_position: (Vec<u8>, u64) = (Vec::new(), 0);
+
+        let mut counter = LiftCounter::default();
+        let mut renamed_line = Vec::new();
+        let mut repaired_line = Vec::new();
         while let Some(original_record) = vcf_reader.next_record()? {
+            let original_record = if rewrite_target.previous_lift_info.is_empty() {
+                original_record
+            } else {
+                provenance::rename_previous_lift_info(
+                    &original_record,
+                    &rewrite_target.previous_lift_info,
+                    &mut renamed_line,
+                )?;
+                PartialVCFRecord::parse_vcf(original_record.line, &renamed_line)?
+            };
+            let original_record = if self.repair_reference_helper(
+                &original_record,
+                rewrite_target,
+                &mut repaired_line,
+            )? {
+                PartialVCFRecord::parse_vcf(original_record.line, &repaired_line)?
+            } else {
+                original_record
+            };
+            if self.param.merge_multi_allelic {
+                if multi_allelic_position.0 != original_record.contig.as_ref()
+                    || multi_allelic_position.1 != original_record.position
+                {
+                    self.lift_multi_allelic_group(
+                        &multi_allelic_group,
+                        rewrite_target,
+                        &merge_target,
+                        success_vcf_writer,
+                        failed_vcf_writer,
+                        failure_report,
+                        &mut counter,
+                    )?;
+                    multi_allelic_group.clear();
+                    multi_allelic_position =
+                        (original_record.contig.to_vec(), original_record.position);
+                }
+                multi_allelic_group.push((original_record.line, original_record.raw.to_vec()));
+                continue;
+            }
+
             if !multi_allelic_warned && last_position == original_record.position {
                 warn!("Multi allelic sites should be merged into one line to rewrite allele frequency/count correctly.");
-                warn!("Please merge multi allelic sites with `bcftools norm -m +any` command");
+                warn!("Please merge multi allelic sites with `bcftools norm -m +any` command or use --merge-multi-allelic option");
                 multi_allelic_warned = true;
             }
             last_position = original_record.position;
 
-            let lifted_record = self.lift_record(&original_record, &lifted_header.1);
+            self.lift_and_write_record(
+                &original_record,
+                rewrite_target,
+                None,
+                success_vcf_writer,
+                failed_vcf_writer,
+                failure_report,
+                &mut counter,
+            )?;
+        }
+        self.lift_multi_allelic_group(
+            &multi_allelic_group,
+            rewrite_target,
+            &merge_target,
+            success_vcf_writer,
+            failed_vcf_writer,
+            failure_report,
+            &mut counter,
+        )?;
 
-            match lifted_record {
-                Ok(VCFLiftOverResult::Succeeded(succeeded)) => {
-                    for one_success in succeeded {
-                        success_vcf_writer.write_record(&one_success)?;
-                    }
-                    succeeded_records += 1;
+        Ok(counter)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn lift_multi_allelic_group<W1: Write, W2: Write, W3: Write>(
+        &mut self,
+        group: &[(u32, Vec<u8>)],
+        rewrite_target: &VCFHeaderRewriteTarget,
+        merge_target: &VCFHeaderRewriteTarget,
+        success_vcf_writer: &mut VCFWriter<W1>,
+        failed_vcf_writer: &mut VCFWriter<W2>,
+        failure_report: &mut Option<FailureReportWriter<W3>>,
+        counter: &mut LiftCounter,
+    ) -> Result<(), LiftOverError> {
+        if group.is_empty() {
+            return Ok(());
+        }
+        if group.len() == 1 {
+            let record = PartialVCFRecord::parse_vcf(group[0].0, &group[0].1)?;
+            return self.lift_and_write_record(
+                &record,
+                rewrite_target,
+                None,
+                success_vcf_writer,
+                failed_vcf_writer,
+                failure_report,
+                counter,
+            );
+        }
+
+        let records = group
+            .iter()
+            .map(|(line, raw)| PartialVCFRecord::parse_vcf(*line, raw)?.complete_parse())
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(merged) =
+            multiallelic::merge_records(&records, merge_target, self.param.split_multi_allelic)
+        {
+            let mut merged_raw = Vec::new();
+            merged.write(&mut merged_raw)?;
+            let merged = PartialVCFRecord::parse_vcf(merged.line, &merged_raw)?;
+            let split_target = if self.param.split_multi_allelic {
+                Some(merge_target)
+            } else {
+                None
+            };
+            self.lift_and_write_record(
+                &merged,
+                rewrite_target,
+                split_target,
+                success_vcf_writer,
+                failed_vcf_writer,
+                failure_report,
+                counter,
+            )
+        } else {
+            for (line, raw) in group {
+                let record = PartialVCFRecord::parse_vcf(*line, raw)?;
+                self.lift_and_write_record(
+                    &record,
+                    rewrite_target,
+                    None,
+                    success_vcf_writer,
+                    failed_vcf_writer,
+                    failure_report,
+                    counter,
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Lift a record and write it. Records are split into bi-allelic records after liftover
+    /// if `split_target` is given.
+    #[allow(clippy::too_many_arguments)]
+    fn lift_and_write_record<W1: Write, W2: Write, W3: Write>(
+        &mut self,
+        original_record: &PartialVCFRecord,
+        rewrite_target: &VCFHeaderRewriteTarget,
+        split_target: Option<&VCFHeaderRewriteTarget>,
+        success_vcf_writer: &mut VCFWriter<W1>,
+        failed_vcf_writer: &mut VCFWriter<W2>,
+        failure_report: &mut Option<FailureReportWriter<W3>>,
+        counter: &mut LiftCounter,
+    ) -> Result<(), LiftOverError> {
+        let lifted_record = self.lift_record(original_record, rewrite_target);
+
+        match lifted_record {
+            Ok(VCFLiftOverResult::Succeeded(succeeded)) => {
+                let attachment = if self.param.sort && self.param.collision_policy.is_some() {
+                    let mut attachment = Vec::new();
+                    original_record.write(&mut attachment)?;
+                    Some((original_record.line, attachment))
+                } else {
+                    None
+                };
+                for one_success in succeeded.iter() {
+                    write_record_helper(
+                        success_vcf_writer,
+                        one_success,
+                        split_target,
+                        attachment.as_ref(),
+                    )?;
                 }
-                Ok(VCFLiftOverResult::Failed(failed)) => {
-                    failed_vcf_writer.write_record(failed.as_ref())?;
-                    failed_records += 1;
+                let flags =
+                    summary::MappedFlags::new(&succeeded, self.last_resolved_by_same_contig);
+                // counts are kept to move the record to unmapped if it is removed by collision
+                if attachment.is_some() && !flags.is_empty() {
+                    counter.mapped_flags.insert(original_record.line, flags);
                 }
-                Err(e) => match e {
-                    LiftOverError::UnknownSequenceError(chrom, _) => {
-                        if !warn_chrom.contains(&chrom) {
-                            warn!("{} is not found in chain or FASTA", chrom);
-                            warn_chrom.insert(chrom);
-                        }
-                    }
-                    _ => return Err(e.into()),
-                },
+                counter.summary.add_mapped(original_record, &flags);
             }
-
-            if (succeeded_records + failed_records) % 1_000_000 == 0 {
-                info!(
-                    "Processed {} entries at {}:{}",
-                    succeeded_records + failed_records,
-                    str::from_utf8(&original_record.contig).unwrap(),
-                    original_record.position
-                );
+            Ok(VCFLiftOverResult::Failed(failed, failure)) => {
+                write_record_helper(failed_vcf_writer, failed.as_ref(), split_target, None)?;
+                if let Some(failure_report) = failure_report.as_mut() {
+                    failure_report.write(original_record, &failure)?;
+                }
+                counter.summary.add_unmapped(original_record, &failure);
             }
+            Err(e) => match e {
+                LiftOverError::UnknownSequenceError(chrom, _) => {
+                    if !counter.warn_chrom.contains(&chrom) {
+                        warn!("{} is not found in chain or FASTA", chrom);
+                        counter.warn_chrom.insert(chrom);
+                    }
+                }
+                _ => return Err(e),
+            },
         }
 
-        eprintln!("    Total record: {}", succeeded_records + failed_records);
-        eprintln!("   Mapped record: {}", succeeded_records);
-        eprintln!(" Unmapped record: {}", failed_records);
-
-        if let Some(summary_writer) = summary_writer.as_mut() {
-            let mut csv_writer = csv::WriterBuilder::new()
-                .delimiter(b'\t')
-                .quote_style(csv::QuoteStyle::Never)
-                .escape(b'\\')
-                .from_writer(summary_writer);
-            csv_writer.write_record(&["Title", "Count", "Percent"])?;
-            csv_writer.write_record(&[
-                "Total record".to_string(),
-                (succeeded_records + failed_records).to_string(),
-                "100.00%".to_string(),
-            ])?;
-            csv_writer.write_record(&[
-                "Mapped record".to_string(),
-                succeeded_records.to_string(),
-                format!(
-                    "{:.2}%",
-                    (succeeded_records as f64) / (succeeded_records + failed_records) as f64 * 100.
-                ),
-            ])?;
-            csv_writer.write_record(&[
-                "Unmapped record".to_string(),
-                failed_records.to_string(),
-                format!(
-                    "{:.2}%",
-                    failed_records as f64 / (succeeded_records + failed_records) as f64 * 100.
-                ),
-            ])?;
+        if counter.summary.total.total().is_multiple_of(1_000_000) {
+            info!(
+                "Processed {} entries at {}:{}",
+                counter.summary.total.total(),
+                str::from_utf8(&original_record.contig).unwrap(),
+                original_record.position
+            );
         }
 
         Ok(())
     }
 
+    /// Write a record whose REF is repaired with the original assembly into `buffer` if REF
+    /// repair is enabled. Returns false if the record is not repaired.
+    fn repair_reference_helper(
+        &mut self,
+        record: &PartialVCFRecord,
+        rewrite_target: &VCFHeaderRewriteTarget,
+        buffer: &mut Vec<u8>,
+    ) -> Result<bool, LiftOverError> {
+        if !self.param.accept_iupac_reference && !self.param.repair_reference {
+            return Ok(false);
+        }
+        let chromosome = self.original_chromosome_name(record.contig());
+        let start = record.position() - 1;
+        let sequence = match self.variant_lift.original_sequence_mut().get_sequence(
+            &chromosome,
+            start,
+            start + record.reference().len() as u64,
+        ) {
+            Ok(sequence) => sequence,
+            // unknown chromosomes are reported by liftover
+            Err(_) => return Ok(false),
+        };
+        Ok(repair::repair_reference(
+            record,
+            &sequence,
+            &self.param,
+            rewrite_target,
+            buffer,
+        )?)
+    }
+
     fn check_contig_length_helper(&self, one_item: &VCFHeaderItem) -> Result<(), LiftOverError> {
         if let Some(length) = one_item.detail.get(&b"length"[..]) {
             if let Some(id) = one_item.detail.get(&b"ID"[..]) {
@@ -648,6 +1304,43 @@ impl<G: GenomeSequence> VCFLiftOver<G> {
     }
 }
 
+#[derive(Debug, Default)]
+struct LiftCounter {
+    summary: LiftSummary,
+    warn_chrom: HashSet<String>,
+    /// counts of mapped records which can be removed by collision
+    mapped_flags: HashMap<u32, summary::MappedFlags>,
+}
+
+/// Write a record, and split it if `split_target` is given. An original record in `attachment` is
+/// written after each record to detect collisions.
+fn write_record_helper<W: Write, R: VCFRecord>(
+    writer: &mut VCFWriter<W>,
+    record: &R,
+    split_target: Option<&VCFHeaderRewriteTarget>,
+    attachment: Option<&(u32, Vec<u8>)>,
+) -> Result<(), LiftOverError> {
+    let write_attachment = |writer: &mut VCFWriter<W>| -> io::Result<()> {
+        if let Some((line, original)) = attachment {
+            collision::write_attachment(writer.get_mut(), *line, original)?;
+        }
+        Ok(())
+    };
+    if let Some(split_target) = split_target {
+        let mut raw = Vec::new();
+        record.write(&mut raw)?;
+        let record = PartialVCFRecord::parse_vcf(0, &raw)?.complete_parse()?;
+        for one in multiallelic::split_record(&record, split_target) {
+            writer.write_record(&one)?;
+            write_attachment(writer)?;
+        }
+    } else {
+        writer.write_record(record)?;
+        write_attachment(writer)?;
+    }
+    Ok(())
+}
+
 fn add_multimap_info_helper(record: &mut VCFRecordWrapper, count: usize) {
     match record {
         VCFRecordWrapper::Partial(ref mut p) => {
@@ -667,6 +1360,30 @@ fn add_multimap_info_helper(record: &mut VCFRecordWrapper, count: usize) {
     }
 }
 
+fn add_info_helper(record: &mut VCFRecordWrapper, key: &[u8], values: &[Vec<u8>]) {
+    match record {
+        VCFRecordWrapper::Partial(ref mut p) => {
+            if p.unparsed_info == &b"."[..] {
+                p.unparsed_info = Cow::Borrowed(b"");
+            } else {
+                p.unparsed_info.to_mut().push(b';');
+            }
+            let info = p.unparsed_info.to_mut();
+            info.extend_from_slice(key);
+            if !values.is_empty() {
+                info.push(b'=');
+                info.extend_from_slice(&values.join(&b","[..]));
+            }
+        }
+        VCFRecordWrapper::Complete(ref mut c) => {
+            c.info.push((
+                Cow::Owned(key.to_vec()),
+                values.iter().map(|x| Cow::Owned(x.clone())).collect(),
+            ));
+        }
+    }
+}
+
 fn merge_to_vcf<'a>(
     variant: &LiftedVariant,
     record: &PartialVCFRecord<'a>,
@@ -781,6 +1498,28 @@ fn swap_ref_alt<'a>(
         vec![Cow::Owned(lifted_variant.original_reference.to_vec())],
     ));
 
+    replace_reference(
+        original_record,
+        &mut record,
+        &lifted_variant.original_reference,
+        param,
+        rewrite_target,
+    )?;
+
+    // TODO: add original chrom/pos/ref record
+
+    Ok(record)
+}
+
+/// Append `original_reference` to ALT alleles of `record` whose REF is already replaced, and
+/// remove ALT alleles equal to the new REF. INFO, FORMAT and GT are rewritten for new alleles.
+fn replace_reference<'a>(
+    original_record: &PartialVCFRecord<'a>,
+    record: &mut CompleteVCFRecord<'a>,
+    original_reference: &[u8],
+    param: &VCFLiftOverParameters,
+    rewrite_target: &VCFHeaderRewriteTarget,
+) -> Result<(), VCFParseError> {
     let to_delete_index: HashSet<_> = record
         .alternative()
         .iter()
@@ -794,38 +1533,39 @@ fn swap_ref_alt<'a>(
         .enumerate()
         .filter(|(i, _)| !to_delete_index.contains(i))
         .map(|(_, x)| x.clone())
-        .chain(vec![Cow::Owned(lifted_variant.original_reference.to_vec())].into_iter())
+        .chain(vec![Cow::Owned(original_reference.to_vec())].into_iter())
         .collect();
 
     if !param.do_not_rewrite_info {
-        rewrite_record::rewrite_info(&mut record, rewrite_target, &to_delete_index);
+        rewrite_record::rewrite_info(record, rewrite_target, &to_delete_index);
+    }
+    if param.split_multi_allelic {
+        // ID, QUAL and FILTER of merged records are required to split records
+        rewrite_record::rewrite_info(
+            record,
+            &multiallelic::MERGED_COLUMNS_TARGET,
+            &to_delete_index,
+        );
     }
 
     if !param.do_not_rewrite_allele_frequency {
         rewrite_record::rewrite_allele_frequency(
             original_record,
-            &mut record,
+            record,
             rewrite_target,
             &to_delete_index,
         )?;
     }
 
     if !param.do_not_rewrite_format {
-        rewrite_record::rewrite_format(&mut record, rewrite_target, &to_delete_index);
+        rewrite_record::rewrite_format(record, rewrite_target, &to_delete_index);
     }
 
     if !param.do_not_rewrite_gt {
-        rewrite_record::rewrite_gt(
-            original_record,
-            &mut record,
-            rewrite_target,
-            &to_delete_index,
-        )?;
+        rewrite_record::rewrite_gt(original_record, record, rewrite_target, &to_delete_index)?;
     }
 
-    // TODO: add original chrom/pos/ref record
-
-    Ok(record)
+    Ok(())
 }
 
 #[cfg(test)]
@@ -0,0 +1,912 @@
+//! Merge records at the same position into one multi-allelic record, and split them back.
+
+use super::rewrite_record::{
+    format_gt, parse_gt, rewrite_genotype_values, AlleleMapping, GTSeparator,
+};
+use super::VCFHeaderRewriteTarget;
+use crate::vcfparse::CompleteVCFRecord;
+use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::str;
+
+type Values<'a> = Vec<Cow<'a, [u8]>>;
+
+/// INFO tags to keep ID, QUAL and FILTER of each merged record until the record is split.
+const MERGED_COLUMNS: [&[u8]; 3] = [b"MERGED_ID", b"MERGED_QUAL", b"MERGED_FILTER"];
+
+/// Rewrite target of `MERGED_COLUMNS` tags, which have a value for each ALT allele.
+pub(super) static MERGED_COLUMNS_TARGET: Lazy<VCFHeaderRewriteTarget> = Lazy::new(|| {
+    let mut rewrite_target = VCFHeaderRewriteTarget::default();
+    rewrite_target
+        .info_alt
+        .extend(MERGED_COLUMNS.iter().map(|x| x.to_vec()));
+    rewrite_target
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldNumber {
+    Alt,
+    Ref,
+    Genotype,
+    Other,
+}
+
+fn info_number(rewrite_target: &VCFHeaderRewriteTarget, key: &[u8]) -> FieldNumber {
+    if rewrite_target.info_alt.contains(key)
+        || rewrite_target.allele_frequency.contains(key)
+        || rewrite_target.allele_count.contains(key)
+    {
+        FieldNumber::Alt
+    } else if rewrite_target.info_ref.contains(key) {
+        FieldNumber::Ref
+    } else if rewrite_target.info_genotype.contains(key) {
+        FieldNumber::Genotype
+    } else {
+        FieldNumber::Other
+    }
+}
+
+fn format_number(rewrite_target: &VCFHeaderRewriteTarget, key: &[u8]) -> FieldNumber {
+    if rewrite_target.format_alt.contains(key) {
+        FieldNumber::Alt
+    } else if rewrite_target.format_ref.contains(key) {
+        FieldNumber::Ref
+    } else if rewrite_target.format_genotype.contains(key) {
+        FieldNumber::Genotype
+    } else {
+        FieldNumber::Other
+    }
+}
+
+fn missing<'a>() -> Cow<'a, [u8]> {
+    Cow::Borrowed(b".")
+}
+
+fn is_missing(values: &[Cow<[u8]>]) -> bool {
+    values.iter().all(|x| x.as_ref() == b".")
+}
+
+fn is_symbolic(allele: &[u8]) -> bool {
+    allele.starts_with(b"<")
+        || allele == b"*"
+        || allele == b"."
+        || allele.contains(&b'[')
+        || allele.contains(&b']')
+}
+
+fn format_value<'r, 'a>(
+    record: &'r CompleteVCFRecord<'a>,
+    sample: usize,
+    key: &[u8],
+) -> Option<&'r Values<'a>> {
+    let index = record.format.iter().position(|x| x.as_ref() == key)?;
+    record.call.get(sample)?.get(index)
+}
+
+/// Merge records at the same position into one multi-allelic record.
+///
+/// If `keep_columns` is true, ID, QUAL and FILTER of the record of each ALT allele are kept in
+/// INFO to restore them in `split_record`.
+///
+/// Records with shorter REF are extended to the longest REF. `None` is returned if
+/// records cannot be merged (e.g. REF alleles are not consistent, a symbolic allele
+/// should be extended, or records have different values for the same allele or genotype
+/// in a Number=A/R/G field).
+pub fn merge_records<'a>(
+    records: &[CompleteVCFRecord<'a>],
+    rewrite_target: &VCFHeaderRewriteTarget,
+    keep_columns: bool,
+) -> Option<CompleteVCFRecord<'a>> {
+    let first = records.first()?;
+    let reference: &[u8] = records
+        .iter()
+        .map(|x| x.reference.as_ref())
+        .max_by_key(|x| x.len())?;
+
+    // merged allele index for each allele of each record (REF is 0)
+    let mut alternative: Vec<Vec<u8>> = Vec::new();
+    let mut allele_map: Vec<Vec<usize>> = Vec::new();
+    for one in records {
+        if one.contig != first.contig
+            || one.position != first.position
+            || !reference.starts_with(&one.reference)
+        {
+            return None;
+        }
+        let suffix = &reference[one.reference.len()..];
+        let mut one_map = vec![0];
+        for allele in one.alternative.iter() {
+            if allele.as_ref() == b"." {
+                return None;
+            }
+            let extended = if suffix.is_empty() {
+                allele.to_vec()
+            } else if is_symbolic(allele) {
+                return None;
+            } else {
+                [allele.as_ref(), suffix].concat()
+            };
+            let index = if let Some(index) = alternative.iter().position(|x| x == &extended) {
+                index
+            } else {
+                alternative.push(extended);
+                alternative.len() - 1
+            };
+            one_map.push(index + 1);
+        }
+        allele_map.push(one_map);
+    }
+    let allele_count = alternative.len() + 1;
+    let mappings: Vec<_> = allele_map
+        .iter()
+        .map(|one_map| AlleleMapping {
+            original_allele_count: one_map.len(),
+            new_to_original: (0..allele_count)
+                .map(|x| one_map.iter().position(|y| *y == x))
+                .collect(),
+        })
+        .collect();
+
+    // ID
+    let mut ids: Vec<&[u8]> = Vec::new();
+    for one in records {
+        for id in one.id.split(|x| *x == b';') {
+            if !id.is_empty() && id != b"." && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    let id = if ids.is_empty() {
+        b".".to_vec()
+    } else {
+        ids.join(&b';')
+    };
+
+    // QUAL
+    let qual = records
+        .iter()
+        .filter_map(|x| {
+            str::from_utf8(&x.qual)
+                .ok()?
+                .parse::<f64>()
+                .ok()
+                .map(|y| (y, &x.qual))
+        })
+        .max_by(|x, y| x.0.partial_cmp(&y.0).unwrap_or(Ordering::Equal))
+        .map(|x| x.1.clone())
+        .unwrap_or_else(missing);
+
+    // FILTER
+    let mut filters: Vec<&[u8]> = Vec::new();
+    let mut passed = false;
+    for one in records {
+        for filter in one.filter.split(|x| *x == b';') {
+            match filter {
+                b"" | b"." => (),
+                b"PASS" => passed = true,
+                _ => {
+                    if !filters.contains(&filter) {
+                        filters.push(filter);
+                    }
+                }
+            }
+        }
+    }
+    let filter = if !filters.is_empty() {
+        filters.join(&b';')
+    } else if passed {
+        b"PASS".to_vec()
+    } else {
+        b".".to_vec()
+    };
+
+    // INFO
+    let mut info_keys: Vec<&Cow<[u8]>> = Vec::new();
+    for one in records {
+        for (k, _) in one.info.iter() {
+            if !info_keys.contains(&k) {
+                info_keys.push(k);
+            }
+        }
+    }
+    let mut info = Vec::new();
+    for key in info_keys {
+        let values: Vec<_> = records
+            .iter()
+            .map(|x| x.info.iter().find(|(k, _)| k == key).map(|(_, v)| v))
+            .collect();
+        if let Some(v) = merge_values(
+            info_number(rewrite_target, key),
+            &values,
+            &allele_map,
+            &mappings,
+            None,
+        )? {
+            info.push((key.clone(), v));
+        }
+    }
+    if keep_columns {
+        // the first record of each ALT allele
+        let sources: Vec<_> = (1..allele_count)
+            .filter_map(|allele| {
+                records
+                    .iter()
+                    .zip(allele_map.iter())
+                    .find(|(_, one_map)| one_map[1..].contains(&allele))
+                    .map(|(x, _)| x)
+            })
+            .collect();
+        for (i, key) in MERGED_COLUMNS.iter().enumerate() {
+            let values = sources
+                .iter()
+                .map(|x| {
+                    let value = match i {
+                        0 => &x.id,
+                        1 => &x.qual,
+                        _ => &x.filter,
+                    };
+                    Cow::Owned(encode_column(value))
+                })
+                .collect();
+            info.push((Cow::Borrowed(*key), values));
+        }
+    }
+
+    // FORMAT
+    let mut format: Vec<Cow<[u8]>> = Vec::new();
+    for one in records {
+        for k in one.format.iter() {
+            if !format.contains(k) {
+                format.push(k.clone());
+            }
+        }
+    }
+    if let Some(gt_index) = format.iter().position(|x| x.as_ref() == b"GT") {
+        let gt = format.remove(gt_index);
+        format.insert(0, gt);
+    }
+    let sample_count = records.iter().map(|x| x.call.len()).max().unwrap_or(0);
+    let call = (0..sample_count)
+        .map(|sample| {
+            let mut ploidy = None;
+            format
+                .iter()
+                .map(|key| {
+                    let values: Vec<_> = records
+                        .iter()
+                        .map(|x| format_value(x, sample, key))
+                        .collect();
+                    if key.as_ref() == b"GT" {
+                        let (gt, gt_ploidy) = merge_gt(&values, &allele_map);
+                        ploidy = gt_ploidy;
+                        Some(gt)
+                    } else {
+                        Some(
+                            merge_values(
+                                format_number(rewrite_target, key),
+                                &values,
+                                &allele_map,
+                                &mappings,
+                                ploidy,
+                            )?
+                            .unwrap_or_else(|| vec![missing()]),
+                        )
+                    }
+                })
+                .collect::<Option<_>>()
+        })
+        .collect::<Option<_>>()?;
+
+    Some(CompleteVCFRecord {
+        line: first.line,
+        contig: first.contig.clone(),
+        position: first.position,
+        id: Cow::Owned(id),
+        reference: Cow::Owned(reference.to_vec()),
+        alternative: alternative.into_iter().map(Cow::Owned).collect(),
+        qual,
+        filter: Cow::Owned(filter),
+        info,
+        format,
+        call,
+    })
+}
+
+/// Fill a missing value, or check that a filled value is the same.
+fn fill_value<'a>(merged: &mut Cow<'a, [u8]>, value: Cow<'a, [u8]>) -> Option<()> {
+    if merged.as_ref() == b"." {
+        *merged = value;
+    } else if value.as_ref() != b"." && *merged != value {
+        return None;
+    }
+    Some(())
+}
+
+/// Merge values of a field. The outer `None` means that values conflict, and the inner `None`
+/// means that no record has the field.
+fn merge_values<'a>(
+    number: FieldNumber,
+    values: &[Option<&Values<'a>>],
+    allele_map: &[Vec<usize>],
+    mappings: &[AlleleMapping],
+    ploidy: Option<usize>,
+) -> Option<Option<Values<'a>>> {
+    let allele_count = if let Some(mapping) = mappings.first() {
+        mapping.new_to_original.len()
+    } else {
+        return Some(None);
+    };
+    let present = values
+        .iter()
+        .zip(allele_map.iter().zip(mappings.iter()))
+        .filter_map(|(v, m)| v.map(|v| (v, m)));
+
+    match number {
+        FieldNumber::Alt | FieldNumber::Ref => {
+            let offset = if number == FieldNumber::Alt { 1 } else { 0 };
+            let mut merged = vec![missing(); allele_count - offset];
+            let mut found = false;
+            for (v, (one_map, _)) in present {
+                found = true;
+                if v.len() + offset != one_map.len() {
+                    continue;
+                }
+                for (x, i) in v.iter().zip(one_map[offset..].iter()) {
+                    fill_value(&mut merged[i - offset], x.clone())?;
+                }
+            }
+            Some(if found { Some(merged) } else { None })
+        }
+        FieldNumber::Genotype => {
+            let mut merged: Option<Values> = None;
+            let mut found = false;
+            for (v, (_, mapping)) in present {
+                found = true;
+                if is_missing(v) {
+                    continue;
+                }
+                if let Some(rewritten) = rewrite_genotype_values(v, ploidy, mapping) {
+                    if let Some(merged) = merged.as_mut() {
+                        if merged.len() == rewritten.len() {
+                            for (x, y) in merged.iter_mut().zip(rewritten) {
+                                fill_value(x, y)?;
+                            }
+                        }
+                    } else {
+                        merged = Some(rewritten);
+                    }
+                }
+            }
+            Some(merged.or_else(|| if found { Some(vec![missing()]) } else { None }))
+        }
+        FieldNumber::Other => {
+            let present: Vec<_> = present.map(|(v, _)| v).collect();
+            Some(
+                present
+                    .iter()
+                    .find(|x| !x.is_empty() && !is_missing(x))
+                    .or_else(|| present.first())
+                    .map(|x| x.to_vec()),
+            )
+        }
+    }
+}
+
+fn merge_gt<'a>(
+    values: &[Option<&Values<'a>>],
+    allele_map: &[Vec<usize>],
+) -> (Values<'a>, Option<usize>) {
+    let parsed: Vec<Vec<_>> = values
+        .iter()
+        .zip(allele_map.iter())
+        .filter_map(|(v, one_map)| {
+            let gt = parse_gt(v.as_ref()?.first()?).ok()?;
+            Some(
+                gt.into_iter()
+                    .map(|(a, s)| (a.map(|x| one_map.get(x).copied().unwrap_or(0)), s))
+                    .collect(),
+            )
+        })
+        .collect();
+    let ploidy = if let Some(ploidy) = parsed.iter().map(|x| x.len()).max() {
+        ploidy
+    } else {
+        return (vec![missing()], None);
+    };
+    let phased = ploidy > 1
+        && parsed
+            .iter()
+            .any(|x| x.first().map(|y| y.1 == GTSeparator::Phased) == Some(true));
+
+    let alleles: Vec<Option<usize>> = if phased {
+        (0..ploidy)
+            .map(|i| {
+                let called: Vec<_> = parsed.iter().filter_map(|x| x.get(i)?.0).collect();
+                called
+                    .iter()
+                    .find(|x| **x != 0)
+                    .or_else(|| called.first())
+                    .copied()
+            })
+            .collect()
+    } else {
+        let called: Vec<_> = parsed.iter().flatten().filter_map(|x| x.0).collect();
+        if called.is_empty() {
+            vec![None; ploidy]
+        } else {
+            let mut non_ref: Vec<_> = called.into_iter().filter(|x| *x != 0).collect();
+            non_ref.truncate(ploidy);
+            let mut alleles = vec![Some(0); ploidy - non_ref.len()];
+            alleles.extend(non_ref.into_iter().map(Some));
+            alleles.sort();
+            alleles
+        }
+    };
+
+    let separator = if phased {
+        GTSeparator::Phased
+    } else {
+        GTSeparator::Unphased
+    };
+    let gt: Vec<_> = alleles
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            (
+                *x,
+                if i + 1 == ploidy {
+                    GTSeparator::None
+                } else {
+                    separator
+                },
+            )
+        })
+        .collect();
+    (vec![Cow::Owned(format_gt(&gt).into_bytes())], Some(ploidy))
+}
+
+/// Escape a column value to keep it in an INFO value. A missing value is escaped to distinguish
+/// it from a missing INFO value.
+fn encode_column(value: &[u8]) -> Vec<u8> {
+    if value == b"." {
+        return b"%2E".to_vec();
+    }
+    let mut encoded = Vec::new();
+    for x in value {
+        match x {
+            b'%' => encoded.extend_from_slice(b"%25"),
+            b';' => encoded.extend_from_slice(b"%3B"),
+            b',' => encoded.extend_from_slice(b"%2C"),
+            b'=' => encoded.extend_from_slice(b"%3D"),
+            _ => encoded.push(*x),
+        }
+    }
+    encoded
+}
+
+fn decode_column(value: &[u8]) -> Option<Vec<u8>> {
+    if value == b"." {
+        return None;
+    }
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < value.len() {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .filter(|_| value[i] == b'%')
+            .and_then(|x| u8::from_str_radix(str::from_utf8(x).ok()?, 16).ok());
+        if let Some(x) = escaped {
+            decoded.push(x);
+            i += 3;
+        } else {
+            decoded.push(value[i]);
+            i += 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Restore ID, QUAL and FILTER of the merged record of `allele` (1-based ALT allele index), and
+/// remove `MERGED_COLUMNS` tags. ID is missing if no merged record has the allele (e.g. REF and
+/// ALT were swapped), and QUAL and FILTER are not changed in this case.
+fn restore_merged_columns(record: &mut CompleteVCFRecord, allele: usize, alternative_count: usize) {
+    let info_count = record.info.len();
+    for (i, key) in MERGED_COLUMNS.iter().enumerate() {
+        let index = if let Some(index) = record.info.iter().position(|(k, _)| k.as_ref() == *key) {
+            index
+        } else {
+            continue;
+        };
+        let (_, values) = record.info.remove(index);
+        if values.len() != alternative_count {
+            continue;
+        }
+        let value = decode_column(&values[allele - 1]);
+        match (i, value) {
+            (0, value) => record.id = Cow::Owned(value.unwrap_or_else(|| b".".to_vec())),
+            (1, Some(value)) => record.qual = Cow::Owned(value),
+            (2, Some(value)) => record.filter = Cow::Owned(value),
+            _ => (),
+        }
+    }
+    if record.info.is_empty() && info_count > 0 {
+        record.info.push((missing(), Vec::new()));
+    }
+}
+
+/// Split a multi-allelic record into bi-allelic records. ID, QUAL and FILTER of each record
+/// merged by `merge_records` are restored.
+pub fn split_record<'a>(
+    record: &CompleteVCFRecord<'a>,
+    rewrite_target: &VCFHeaderRewriteTarget,
+) -> Vec<CompleteVCFRecord<'a>> {
+    let alternative_count = record.alternative.len();
+    if alternative_count <= 1 {
+        let mut record = record.clone();
+        restore_merged_columns(&mut record, 1, alternative_count);
+        return vec![record];
+    }
+
+    (1..=alternative_count)
+        .map(|allele| {
+            let mapping = AlleleMapping {
+                original_allele_count: alternative_count + 1,
+                new_to_original: vec![Some(0), Some(allele)],
+            };
+            let split_values =
+                |number: FieldNumber, v: &Values<'a>, ploidy: Option<usize>| match number {
+                    FieldNumber::Alt if v.len() == alternative_count => vec![v[allele - 1].clone()],
+                    FieldNumber::Ref if v.len() == alternative_count + 1 => {
+                        vec![v[0].clone(), v[allele].clone()]
+                    }
+                    FieldNumber::Genotype if !is_missing(v) => {
+                        rewrite_genotype_values(v, ploidy, &mapping).unwrap_or_else(|| v.clone())
+                    }
+                    _ => v.clone(),
+                };
+
+            let mut reference: &[u8] = &record.reference;
+            let mut alternative: &[u8] = &record.alternative[allele - 1];
+            if !is_symbolic(alternative) {
+                while reference.len() > 1
+                    && alternative.len() > 1
+                    && reference.last() == alternative.last()
+                {
+                    reference = &reference[..reference.len() - 1];
+                    alternative = &alternative[..alternative.len() - 1];
+                }
+            }
+
+            let info = record
+                .info
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        split_values(info_number(rewrite_target, k), v, None),
+                    )
+                })
+                .collect();
+
+            let gt_index = record.format.iter().position(|x| x.as_ref() == b"GT");
+            let call = record
+                .call
+                .iter()
+                .map(|one_sample| {
+                    let gt = gt_index
+                        .and_then(|i| one_sample.get(i))
+                        .and_then(|x| x.first())
+                        .and_then(|x| parse_gt(x).ok());
+                    let ploidy = gt.as_ref().map(|x| x.len());
+                    record
+                        .format
+                        .iter()
+                        .zip(one_sample.iter())
+                        .map(|(k, v)| {
+                            if k.as_ref() == b"GT" {
+                                if let Some(gt) = gt.as_ref() {
+                                    return vec![Cow::Owned(split_gt(gt, allele).into_bytes())];
+                                }
+                            }
+                            split_values(format_number(rewrite_target, k), v, ploidy)
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let mut split = CompleteVCFRecord {
+                line: record.line,
+                contig: record.contig.clone(),
+                position: record.position,
+                id: record.id.clone(),
+                reference: Cow::Owned(reference.to_vec()),
+                alternative: vec![Cow::Owned(alternative.to_vec())],
+                qual: record.qual.clone(),
+                filter: record.filter.clone(),
+                info,
+                format: record.format.clone(),
+                call,
+            };
+            restore_merged_columns(&mut split, allele, alternative_count);
+            split
+        })
+        .collect()
+}
+
+/// Reorder ALT alleles of a record. `new_to_original` is an original allele index for each new
+/// allele index, and REF (index 0) should be kept at first.
+pub fn reorder_alleles<'a>(
+    record: &CompleteVCFRecord<'a>,
+    rewrite_target: &VCFHeaderRewriteTarget,
+    new_to_original: &[usize],
+) -> CompleteVCFRecord<'a> {
+    let allele_count = record.alternative.len() + 1;
+    let mapping = AlleleMapping {
+        original_allele_count: allele_count,
+        new_to_original: new_to_original.iter().map(|x| Some(*x)).collect(),
+    };
+    let mut original_to_new = vec![0; allele_count];
+    for (i, x) in new_to_original.iter().enumerate() {
+        original_to_new[*x] = i;
+    }
+
+    let reorder_values = |number: FieldNumber, v: &Values<'a>, ploidy: Option<usize>| match number {
+        FieldNumber::Alt if v.len() + 1 == allele_count => new_to_original[1..]
+            .iter()
+            .map(|x| v[x - 1].clone())
+            .collect(),
+        FieldNumber::Ref if v.len() == allele_count => {
+            new_to_original.iter().map(|x| v[*x].clone()).collect()
+        }
+        FieldNumber::Genotype if !is_missing(v) => {
+            rewrite_genotype_values(v, ploidy, &mapping).unwrap_or_else(|| v.clone())
+        }
+        _ => v.clone(),
+    };
+
+    let mut new_record = record.clone();
+    new_record.alternative = new_to_original[1..]
+        .iter()
+        .map(|x| record.alternative[x - 1].clone())
+        .collect();
+    new_record.info = record
+        .info
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.clone(),
+                reorder_values(info_number(rewrite_target, k), v, None),
+            )
+        })
+        .collect();
+
+    let gt_index = record.format.iter().position(|x| x.as_ref() == b"GT");
+    new_record.call = record
+        .call
+        .iter()
+        .map(|one_sample| {
+            let gt = gt_index
+                .and_then(|i| one_sample.get(i))
+                .and_then(|x| x.first())
+                .and_then(|x| parse_gt(x).ok());
+            let ploidy = gt.as_ref().map(|x| x.len());
+            record
+                .format
+                .iter()
+                .zip(one_sample.iter())
+                .map(|(k, v)| {
+                    if k.as_ref() == b"GT" {
+                        if let Some(gt) = gt.as_ref() {
+                            let gt: Vec<_> = gt
+                                .iter()
+                                .map(|(x, s)| (x.and_then(|y| original_to_new.get(y).copied()), *s))
+                                .collect();
+                            return vec![Cow::Owned(format_gt(&gt).into_bytes())];
+                        }
+                    }
+                    reorder_values(format_number(rewrite_target, k), v, ploidy)
+                })
+                .collect()
+        })
+        .collect();
+
+    new_record
+}
+
+fn split_gt(gt: &[(Option<usize>, GTSeparator)], allele: usize) -> String {
+    let mut gt: Vec<_> = gt
+        .iter()
+        .map(|(x, s)| (x.map(|y| if y == allele { 1 } else { 0 }), *s))
+        .collect();
+    if !gt.is_empty() && gt[0].1 == GTSeparator::Unphased {
+        gt.sort_by_key(|x| x.0);
+        let l = gt.len();
+        for (i, x) in gt.iter_mut().enumerate() {
+            x.1 = if i + 1 == l {
+                GTSeparator::None
+            } else {
+                GTSeparator::Unphased
+            };
+        }
+    }
+    format_gt(&gt)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vcfparse::{PartialVCFRecord, VCFRecord};
+
+    fn rewrite_target() -> VCFHeaderRewriteTarget {
+        let mut rewrite_target = VCFHeaderRewriteTarget::default();
+        rewrite_target.allele_frequency.insert(b"AF".to_vec());
+        rewrite_target.allele_count.insert(b"AC".to_vec());
+        rewrite_target.allele_number.insert(b"AN".to_vec());
+        rewrite_target.info_ref.insert(b"RR".to_vec());
+        rewrite_target.format_ref.insert(b"AD".to_vec());
+        rewrite_target.format_genotype.insert(b"PL".to_vec());
+        rewrite_target.format_gt = true;
+        rewrite_target
+    }
+
+    fn to_string<R: VCFRecord>(record: &R) -> String {
+        let mut bytes = Vec::new();
+        record.write(&mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_merge_and_split() {
+        let lines: Vec<&[u8]> = vec![
+            b"chr1\t100\trs1\tAT\tA\t30\tPASS\tAC=1;AN=4;AF=0.25;RR=1,2\tGT:AD:PL\t0/1:5,3:10,0,20\t0/0:8,0:0,10,50\n",
+            b"chr1\t100\trs2\tA\tG\t50\tq10\tAC=2;AN=4;AF=0.5;RR=1,3;DB\tGT:AD:PL\t0/1:5,2:10,0,40\t0/1:8,9:0,5,60\n",
+        ];
+        let records: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                PartialVCFRecord::parse_vcf(i as u32, x)
+                    .unwrap()
+                    .complete_parse()
+                    .unwrap()
+            })
+            .collect();
+        let rewrite_target = rewrite_target();
+
+        let merged = merge_records(&records, &rewrite_target, true).unwrap();
+        assert_eq!(
+            to_string(&merged),
+            "chr1\t100\trs1;rs2\tAT\tA,GT\t50\tq10\tAC=1,2;AN=4;AF=0.25,0.5;RR=1,2,3;DB;MERGED_ID=rs1,rs2;MERGED_QUAL=30,50;MERGED_FILTER=PASS,q10\tGT:AD:PL\t1/2:5,3,2:10,0,20,0,.,40\t0/2:8,0,9:0,10,50,5,.,60\n"
+        );
+
+        let split = split_record(&merged, &rewrite_target);
+        assert_eq!(split.len(), 2);
+        assert_eq!(
+            to_string(&split[0]),
+            "chr1\t100\trs1\tAT\tA\t30\tPASS\tAC=1;AN=4;AF=0.25;RR=1,2;DB\tGT:AD:PL\t0/1:5,3:10,0,20\t0/0:8,0:0,10,50\n"
+        );
+        assert_eq!(
+            to_string(&split[1]),
+            "chr1\t100\trs2\tA\tG\t50\tq10\tAC=2;AN=4;AF=0.5;RR=1,3;DB\tGT:AD:PL\t0/1:5,2:10,0,40\t0/1:8,9:0,5,60\n"
+        );
+    }
+
+    #[test]
+    fn test_split_merged_columns() {
+        let lines: Vec<&[u8]> = vec![
+            b"chr1\t100\trs1;rs3\tA\tG\t.\tPASS\t.\n",
+            b"chr1\t100\t.\tA\tC\t20\tq10;s50\t.\n",
+        ];
+        let records: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                PartialVCFRecord::parse_vcf(i as u32, x)
+                    .unwrap()
+                    .complete_parse()
+                    .unwrap()
+            })
+            .collect();
+        let rewrite_target = rewrite_target();
+        let merged = merge_records(&records, &rewrite_target, true).unwrap();
+        assert_eq!(
+            to_string(&merged),
+            "chr1\t100\trs1;rs3\tA\tG,C\t20\tq10;s50\tMERGED_ID=rs1%3Brs3,%2E;MERGED_QUAL=%2E,20;MERGED_FILTER=PASS,q10%3Bs50\n"
+        );
+        let split: Vec<_> = split_record(&merged, &rewrite_target)
+            .iter()
+            .map(to_string)
+            .collect();
+        assert_eq!(
+            split,
+            vec![
+                "chr1\t100\trs1;rs3\tA\tG\t.\tPASS\t.\n",
+                "chr1\t100\t.\tA\tC\t20\tq10;s50\t.\n",
+            ]
+        );
+
+        // REF and ALT were swapped, and the new ALT allele has no merged record
+        let swapped = PartialVCFRecord::parse_vcf(
+            0,
+            b"chr1\t100\trs1;rs3\tG\tC,A\t20\tq10\tMERGED_ID=%2E,.;MERGED_QUAL=20,.;MERGED_FILTER=q10%3Bs50,.\n",
+        )
+        .unwrap()
+        .complete_parse()
+        .unwrap();
+        let split: Vec<_> = split_record(&swapped, &rewrite_target)
+            .iter()
+            .map(to_string)
+            .collect();
+        assert_eq!(
+            split,
+            vec![
+                "chr1\t100\t.\tG\tC\t20\tq10;s50\t.\n",
+                "chr1\t100\t.\tG\tA\t20\tq10\t.\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reorder_alleles() {
+        let record = PartialVCFRecord::parse_vcf(
+            0,
+            b"chr1\t100\t.\tA\t<NON_REF>,G\t.\t.\tAC=1,2;AN=4;AF=0.25,0.5;RR=1,2,3\tGT:AD:PL\t0/2:5,0,2:10,20,30,0,40,50\t1|0:3,4,0:0,1,2,3,4,5\n",
+        )
+        .unwrap()
+        .complete_parse()
+        .unwrap();
+        let reordered = reorder_alleles(&record, &rewrite_target(), &[0, 2, 1]);
+        assert_eq!(
+            to_string(&reordered),
+            "chr1\t100\t.\tA\tG,<NON_REF>\t.\t.\tAC=2,1;AN=4;AF=0.5,0.25;RR=1,3,2\tGT:AD:PL\t0/1:5,2,0:10,0,50,20,40,30\t2|0:3,0,4:0,3,5,1,4,2\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicting_values() {
+        // PL of 0/0 and reference AD are different between records
+        let lines: Vec<&[u8]> = vec![
+            b"chr1\t100\trs1\tAT\tA\t30\tPASS\tAC=1;AN=4\tGT:AD:PL\t0/1:5,3:10,0,20\n",
+            b"chr1\t100\trs2\tA\tG\t50\tq10\tAC=2;AN=4\tGT:AD:PL\t0/1:5,2:30,0,40\n",
+            b"chr1\t100\trs3\tA\tC\t50\tq10\tAC=2;AN=4\tGT:AD:PL\t0/1:6,2:10,0,40\n",
+        ];
+        let records: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                PartialVCFRecord::parse_vcf(i as u32, x)
+                    .unwrap()
+                    .complete_parse()
+                    .unwrap()
+            })
+            .collect();
+        let rewrite_target = rewrite_target();
+        assert_eq!(merge_records(&records[0..2], &rewrite_target, false), None);
+        assert_eq!(merge_records(&records[1..3], &rewrite_target, false), None);
+
+        let mut rewrite_target = rewrite_target;
+        rewrite_target.format_genotype.clear();
+        rewrite_target.format_ref.clear();
+        assert!(merge_records(&records[0..2], &rewrite_target, false).is_some());
+    }
+
+    #[test]
+    fn test_merge_inconsistent_reference() {
+        let lines: Vec<&[u8]> = vec![
+            b"chr1\t100\t.\tAT\tA\t.\t.\t.\n",
+            b"chr1\t100\t.\tC\tG\t.\t.\t.\n",
+        ];
+        let records: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                PartialVCFRecord::parse_vcf(i as u32, x)
+                    .unwrap()
+                    .complete_parse()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(merge_records(&records, &rewrite_target(), false), None);
+    }
+}
@@ -0,0 +1,91 @@
+//! Named presets of `VCFLiftOverParameters` for well-known VCF sources.
+
+use super::VCFLiftOverParameters;
+use crate::vcfparse::VCFHeader;
+
+/// Names of presets
+pub const PRESETS: [&str; 4] = ["clinvar", "cosmic", "gnomad", "genotypes"];
+
+impl VCFLiftOverParameters {
+    /// Parameters of a named preset.
+    ///
+    /// * `clinvar` and `cosmic` do not swap REF and ALT, because these files annotate a
+    ///   specific ALT allele.
+    /// * `gnomad` merges bi-allelic records before liftover and splits them after liftover to
+    ///   rewrite allele frequency and count correctly.
+    /// * `genotypes` merges bi-allelic records before liftover to rewrite GT of all alleles.
+    pub fn preset(name: &str) -> Option<Self> {
+        let param = VCFLiftOverParameters::new();
+        match name {
+            "clinvar" | "cosmic" => Some(param.do_not_swap_ref_alt(true)),
+            "gnomad" => Some(param.merge_multi_allelic(true).split_multi_allelic(true)),
+            "genotypes" => Some(param.merge_multi_allelic(true)),
+            _ => None,
+        }
+    }
+
+    /// Find a preset from `##source` header line.
+    pub fn preset_from_header(header: &VCFHeader) -> Option<&'static str> {
+        header
+            .header_items
+            .iter()
+            .filter(|x| x.key == b"source")
+            .find_map(|x| {
+                let source = x.value.to_ascii_lowercase();
+                if source == b"clinvar" {
+                    Some("clinvar")
+                } else if source.starts_with(b"cosmic") {
+                    Some("cosmic")
+                } else if source.windows(6).any(|x| x == b"gnomad") {
+                    Some("gnomad")
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vcfparse::VCFReader;
+
+    #[test]
+    fn test_preset() {
+        for one in PRESETS {
+            assert!(VCFLiftOverParameters::preset(one).is_some());
+        }
+        assert_eq!(VCFLiftOverParameters::preset("unknown"), None);
+        assert!(
+            VCFLiftOverParameters::preset("clinvar")
+                .unwrap()
+                .do_not_swap_ref_alt
+        );
+    }
+
+    #[test]
+    fn test_preset_from_header() -> Result<(), crate::LiftOverError> {
+        for (source, expected) in [
+            ("ClinVar", Some("clinvar")),
+            ("COSMICv92", Some("cosmic")),
+            ("gnomAD_v3.1", Some("gnomad")),
+            ("dbSNP", None),
+        ] {
+            let header = VCFReader::new(
+                format!(
+                    "##fileformat=VCFv4.2\n##source={}\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n",
+                    source
+                )
+                .as_bytes(),
+            )?
+            .header;
+            assert_eq!(
+                VCFLiftOverParameters::preset_from_header(&header),
+                expected,
+                "{}",
+                source
+            );
+        }
+        Ok(())
+    }
+}
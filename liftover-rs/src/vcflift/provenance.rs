@@ -0,0 +1,424 @@
+//! `##contig` lines and provenance header lines of lifted VCF.
+
+use super::VCFLiftOver;
+use crate::defs::{chromosome_priority, GenomeSequence};
+use crate::vcfparse::{PartialVCFRecord, VCFHeader, VCFHeaderItem, VCFRecord};
+use crate::LiftOverError;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::str;
+
+/// Window size to read a contig sequence while computing MD5
+const MD5_WINDOW_SIZE: u64 = 1024 * 1024;
+
+/// INFO tags added to lifted records
+const LIFT_INFO_TAGS: [&[u8]; 12] = [
+    b"MULTIMAP",
+    b"REF_CHANGED",
+    b"ORIGINAL_REF",
+    b"ORIGINAL_CHROM",
+    b"ORIGINAL_POS",
+    b"ORIGINAL_STRAND",
+    b"REVERSE_LIFT_MISMATCH",
+    b"REPAIRED_REF",
+    b"REPRESENTATION_CHANGED",
+    b"POSITION_SHIFT",
+    b"LIFT_CONFIDENCE",
+    b"COLLISION",
+];
+
+/// Information about a liftOver run, which is written into a header of lifted VCF.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VCFLiftOverProvenance {
+    /// Name and version of a program (e.g. `transanno 0.4.5`)
+    pub program: Option<String>,
+    /// Path to a chain file
+    pub chain: Option<String>,
+    pub command_line: Option<String>,
+    /// Path to an original assembly FASTA
+    pub original_assembly: Option<String>,
+    /// Path to a new assembly FASTA. Its file name is used as `assembly` attribute of `##contig`
+    pub new_assembly: Option<String>,
+}
+
+impl VCFLiftOverProvenance {
+    pub fn new() -> Self {
+        VCFLiftOverProvenance::default()
+    }
+
+    pub fn program(mut self, program: &str) -> Self {
+        self.program = Some(program.to_string());
+        self
+    }
+
+    pub fn chain(mut self, chain: &str) -> Self {
+        self.chain = Some(chain.to_string());
+        self
+    }
+
+    pub fn command_line(mut self, command_line: &str) -> Self {
+        self.command_line = Some(command_line.to_string());
+        self
+    }
+
+    pub fn original_assembly(mut self, original_assembly: &str) -> Self {
+        self.original_assembly = Some(original_assembly.to_string());
+        self
+    }
+
+    pub fn new_assembly(mut self, new_assembly: &str) -> Self {
+        self.new_assembly = Some(new_assembly.to_string());
+        self
+    }
+
+    /// Header lines to record this liftOver
+    pub(super) fn header_items(&self) -> Result<Vec<VCFHeaderItem>, LiftOverError> {
+        let mut items = Vec::new();
+        for (key, value) in [
+            ("reference", &self.new_assembly),
+            ("liftoverProgram", &self.program),
+            ("liftoverChain", &self.chain),
+            ("liftoverCommand", &self.command_line),
+            ("liftoverSourceAssembly", &self.original_assembly),
+        ] {
+            if let Some(value) = value {
+                items.push(VCFHeaderItem::parse(
+                    format!("##{}={}", key, value).as_bytes(),
+                    0,
+                )?);
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// Assembly name from a FASTA or 2bit path. e.g. `GRCh38` for `/path/to/GRCh38.fa.gz`
+fn assembly_name(path: &str) -> Option<String> {
+    let mut name = Path::new(path).file_name()?.to_str()?;
+    for suffix in [".gz", ".bgz"] {
+        name = name.strip_suffix(suffix).unwrap_or(name);
+    }
+    for suffix in [".fa", ".fasta", ".fna", ".2bit"] {
+        name = name.strip_suffix(suffix).unwrap_or(name);
+    }
+    Some(name.to_string())
+}
+
+/// MD5 of upper case sequence without line breaks, as `M5` in SAM.
+fn contig_md5<G: GenomeSequence>(
+    sequence: &mut G,
+    name: &str,
+    length: u64,
+) -> Result<String, LiftOverError> {
+    let mut context = md5::Context::new();
+    let mut data = Vec::new();
+    let mut start = 0;
+    while start < length {
+        let end = length.min(start + MD5_WINDOW_SIZE);
+        data.clear();
+        sequence.sequence(name, start, end, &mut data)?;
+        data.make_ascii_uppercase();
+        context.consume(&data);
+        start = end;
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+fn suffixed_name(tag: &[u8], suffix: &str) -> Vec<u8> {
+    let mut name = tag.to_vec();
+    name.push(b'_');
+    name.extend_from_slice(suffix.as_bytes());
+    name
+}
+
+/// New names of INFO tags added by a previous liftOver, to keep them in a lifted VCF again.
+///
+/// Tags are renamed with the original assembly of the previous liftOver found in the last
+/// `##liftoverSourceAssembly` line (e.g. `ORIGINAL_POS_hg18`), or with a number if the assembly
+/// is unknown.
+pub(super) fn previous_lift_info(header: &VCFHeader) -> HashMap<Vec<u8>, Vec<u8>> {
+    let info_ids: HashSet<&[u8]> = header
+        .header_items
+        .iter()
+        .filter(|x| x.key == b"INFO")
+        .filter_map(|x| x.detail.get(&b"ID"[..]))
+        .map(|x| &x[..])
+        .collect();
+    let tags: Vec<_> = LIFT_INFO_TAGS
+        .iter()
+        .filter(|x| info_ids.contains(*x))
+        .collect();
+    if tags.is_empty() {
+        return HashMap::new();
+    }
+
+    let assembly = header
+        .header_items
+        .iter()
+        .rev()
+        .find(|x| x.key == b"liftoverSourceAssembly")
+        .and_then(|x| str::from_utf8(&x.value).ok())
+        .and_then(assembly_name)
+        .map(|x| {
+            x.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        });
+    let suffix = (1..)
+        .map(|i| match (&assembly, i) {
+            (Some(assembly), 1) => assembly.clone(),
+            (Some(assembly), i) => format!("{}_{}", assembly, i),
+            (None, i) => i.to_string(),
+        })
+        .find(|suffix| {
+            tags.iter()
+                .all(|x| !info_ids.contains(&suffixed_name(x, suffix)[..]))
+        })
+        .unwrap();
+
+    tags.iter()
+        .map(|x| (x.to_vec(), suffixed_name(x, &suffix)))
+        .collect()
+}
+
+/// Rename `##INFO` lines of a previous liftOver.
+pub(super) fn rename_previous_lift_header(
+    header_items: &mut [VCFHeaderItem],
+    renames: &HashMap<Vec<u8>, Vec<u8>>,
+) -> Result<(), LiftOverError> {
+    for item in header_items.iter_mut().filter(|x| x.key == b"INFO") {
+        let (old_id, new_id) = match item
+            .detail
+            .get(&b"ID"[..])
+            .and_then(|x| renames.get(x).map(|y| (x, y)))
+        {
+            Some((old_id, new_id)) => ([&b"ID="[..], old_id].concat(), new_id),
+            None => continue,
+        };
+        let mut line = b"##INFO=".to_vec();
+        match item.value.windows(old_id.len()).position(|x| x == old_id) {
+            Some(index) => {
+                line.extend_from_slice(&item.value[..index + 3]);
+                line.extend_from_slice(new_id);
+                line.extend_from_slice(&item.value[index + old_id.len()..]);
+            }
+            None => continue,
+        }
+        *item = VCFHeaderItem::parse(&line, 0)?;
+    }
+    Ok(())
+}
+
+/// Write a record whose INFO tags of a previous liftOver are renamed.
+pub(super) fn rename_previous_lift_info(
+    record: &PartialVCFRecord,
+    renames: &HashMap<Vec<u8>, Vec<u8>>,
+    buffer: &mut Vec<u8>,
+) -> io::Result<()> {
+    let mut info = Vec::new();
+    for (i, one) in record.unparsed_info.split(|x| *x == b';').enumerate() {
+        if i != 0 {
+            info.push(b';');
+        }
+        let key_length = one.iter().position(|x| *x == b'=').unwrap_or(one.len());
+        match renames.get(&one[..key_length]) {
+            Some(new_key) => {
+                info.extend_from_slice(new_key);
+                info.extend_from_slice(&one[key_length..]);
+            }
+            None => info.extend_from_slice(one),
+        }
+    }
+    let mut renamed = record.clone();
+    renamed.unparsed_info = Cow::Owned(info);
+    buffer.clear();
+    renamed.write(buffer)
+}
+
+impl<G: GenomeSequence> VCFLiftOver<G> {
+    /// Create `##contig` lines in the order of the new assembly FASTA. Contigs found only in the
+    /// chain file are placed at the end.
+    pub(super) fn contig_header_items(
+        &mut self,
+        header: &VCFHeader,
+    ) -> Result<Vec<VCFHeaderItem>, LiftOverError> {
+        let param = self.param;
+        let used_contigs = if param.only_used_contigs {
+            Some(self.used_contigs(header))
+        } else {
+            None
+        };
+
+        let mut contigs = self.variant_lift.new_sequence_mut().get_contig_list();
+        let fasta_contigs: HashSet<_> = contigs.iter().map(|x| x.0.clone()).collect();
+        let mut chain_only_contigs: Vec<_> = self
+            .variant_lift
+            .position_liftover()
+            .new_chromosomes()
+            .iter()
+            .filter(|x| !fasta_contigs.contains(&x.name))
+            .map(|x| (x.name.clone(), x.length))
+            .collect();
+        chain_only_contigs.sort_by_key(|x| (chromosome_priority(&x.0), x.1));
+        contigs.extend(chain_only_contigs);
+        if let Some(used_contigs) = used_contigs {
+            contigs.retain(|x| used_contigs.contains(&x.0));
+        }
+
+        let assembly = self
+            .provenance
+            .new_assembly
+            .as_deref()
+            .and_then(assembly_name);
+        let mut items = Vec::new();
+        for (name, length) in contigs {
+            let mut line = format!("##contig=<ID={},length={}", name, length);
+            if let Some(assembly) = assembly.as_ref() {
+                line.push_str(&format!(",assembly={}", assembly));
+            }
+            if param.write_contig_md5 && fasta_contigs.contains(&name) {
+                let md5 = contig_md5(self.variant_lift.new_sequence_mut(), &name, length)?;
+                line.push_str(&format!(",md5={}", md5));
+            }
+            line.push('>');
+            items.push(VCFHeaderItem::parse(line.as_bytes(), 0)?);
+        }
+        Ok(items)
+    }
+
+    /// New contigs which variants can be lifted to. If the input header has `##contig` lines,
+    /// only chains from the listed contigs are considered.
+    fn used_contigs(&self, header: &VCFHeader) -> HashSet<String> {
+        let original_contigs: HashSet<_> = header
+            .header_items
+            .iter()
+            .filter(|x| x.key == b"contig")
+            .filter_map(|x| x.detail.get(&b"ID"[..]))
+            .map(|x| self.original_chromosome_name(x))
+            .collect();
+
+        self.variant_lift
+            .position_liftover()
+            .chain_list()
+            .iter()
+            .filter(|x| {
+                original_contigs.is_empty()
+                    || original_contigs.contains(&x.original_chromosome.name)
+            })
+            .map(|x| x.new_chromosome.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_assembly_name() {
+        assert_eq!(
+            assembly_name("../genomes/GRCh38/GRCh38.chr22.genome.fa"),
+            Some("GRCh38.chr22.genome".to_string())
+        );
+        assert_eq!(assembly_name("hg19.fasta.gz"), Some("hg19".to_string()));
+        assert_eq!(assembly_name("/data/hg38.2bit"), Some("hg38".to_string()));
+        assert_eq!(
+            assembly_name("T2T-CHM13v2.0.fna"),
+            Some("T2T-CHM13v2.0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_provenance_header_items() -> Result<(), LiftOverError> {
+        let items = VCFLiftOverProvenance::new()
+            .program("transanno 0.4.5")
+            .chain("hg19ToHg38.over.chain.gz")
+            .new_assembly("hg38.fa")
+            .header_items()?;
+        let lines: Vec<_> = items
+            .iter()
+            .map(|x| {
+                format!(
+                    "{}={}",
+                    str::from_utf8(&x.key).unwrap(),
+                    str::from_utf8(&x.value).unwrap()
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "reference=hg38.fa",
+                "liftoverProgram=transanno 0.4.5",
+                "liftoverChain=hg19ToHg38.over.chain.gz",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_previous_lift() -> Result<(), LiftOverError> {
+        let mut header_items = vec![
+            VCFHeaderItem::parse(b"##liftoverSourceAssembly=/data/hg17.fa", 0)?,
+            VCFHeaderItem::parse(b"##liftoverSourceAssembly=/data/hg18.fa.gz", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=ORIGINAL_POS_hg17,Number=1,Type=Integer,Description=\"Original position\">", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=ORIGINAL_CHROM,Number=1,Type=String,Description=\"Original chromosome\">", 0)?,
+            VCFHeaderItem::parse(b"##INFO=<ID=ORIGINAL_POS,Number=1,Type=Integer,Description=\"Original position\">", 0)?,
+        ];
+        let header = VCFHeader {
+            header_items: header_items.clone(),
+            samples: Vec::new(),
+        };
+        let renames = previous_lift_info(&header);
+        assert_eq!(renames.len(), 2);
+        assert_eq!(renames[&b"ORIGINAL_POS"[..]], b"ORIGINAL_POS_hg18".to_vec());
+
+        rename_previous_lift_header(&mut header_items, &renames)?;
+        assert_eq!(
+            header_items[5].value,
+            b"<ID=ORIGINAL_POS_hg18,Number=1,Type=Integer,Description=\"Original position\">"
+                .to_vec()
+        );
+        assert_eq!(
+            header_items[4].detail[&b"ID"[..]],
+            b"ORIGINAL_CHROM_hg18".to_vec()
+        );
+        assert_eq!(
+            header_items[3].detail[&b"ID"[..]],
+            b"ORIGINAL_POS_hg17".to_vec()
+        );
+
+        let record = PartialVCFRecord::parse_vcf(
+            1,
+            b"chr1\t100\t.\tA\tG\t.\t.\tDP=3;ORIGINAL_CHROM=chr2;ORIGINAL_POS=200;ORIGINAL_POS_hg17=300\n",
+        )?;
+        let mut buffer = Vec::new();
+        rename_previous_lift_info(&record, &renames, &mut buffer)?;
+        assert_eq!(
+            str::from_utf8(&buffer).unwrap(),
+            "chr1\t100\t.\tA\tG\t.\t.\tDP=3;ORIGINAL_CHROM_hg18=chr2;ORIGINAL_POS_hg18=200;ORIGINAL_POS_hg17=300\n"
+        );
+
+        // assembly is unknown
+        header_items.push(VCFHeaderItem::parse(
+            b"##INFO=<ID=ORIGINAL_POS,Number=1,Type=Integer,Description=\"Original position\">",
+            0,
+        )?);
+        header_items.retain(|x| x.key != b"liftoverSourceAssembly");
+        let renames = previous_lift_info(&VCFHeader {
+            header_items,
+            samples: Vec::new(),
+        });
+        assert_eq!(renames[&b"ORIGINAL_POS"[..]], b"ORIGINAL_POS_1".to_vec());
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! Repair REF alleles which do not match the original assembly.
+
+use super::{replace_reference, VCFHeaderRewriteTarget, VCFLiftOverParameters};
+use crate::vcfparse::{PartialVCFRecord, VCFParseError, VCFRecord};
+use std::borrow::Cow;
+
+pub(super) const REPAIRED_REF: &str = "REPAIRED_REF";
+
+/// Bases represented by an IUPAC nucleotide code.
+fn iupac_bases(code: u8) -> &'static [u8] {
+    match code.to_ascii_uppercase() {
+        b'A' => b"A",
+        b'C' => b"C",
+        b'G' => b"G",
+        b'T' => b"T",
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => b"",
+    }
+}
+
+/// Returns true if all bases of `sequence` are represented by IUPAC codes in `reference`.
+pub(super) fn is_iupac_compatible(reference: &[u8], sequence: &[u8]) -> bool {
+    reference.len() == sequence.len()
+        && reference
+            .iter()
+            .zip(sequence.iter())
+            .all(|(r, s)| iupac_bases(*r).contains(s))
+}
+
+/// Replace REF of `record` with `sequence` of the original assembly, and write the repaired record
+/// into `buffer`. REF and ALT are swapped if `sequence` is equal to one of ALT alleles. Returns
+/// false if the record is not repaired. A record is not repaired if it requires swapping and its
+/// REF contains bases other than A, C, G and T, because the REF becomes an ALT allele.
+pub(super) fn repair_reference(
+    record: &PartialVCFRecord,
+    sequence: &[u8],
+    param: &VCFLiftOverParameters,
+    rewrite_target: &VCFHeaderRewriteTarget,
+    buffer: &mut Vec<u8>,
+) -> Result<bool, VCFParseError> {
+    let reference = record.reference();
+    if sequence.len() != reference.len()
+        || sequence == reference
+        || !sequence.iter().all(|x| b"ACGT".contains(x))
+    {
+        return Ok(false);
+    }
+
+    let swap = record.alternative().iter().any(|x| x.as_ref() == sequence);
+    if !param.repair_reference && (swap || !is_iupac_compatible(reference, sequence)) {
+        return Ok(false);
+    }
+    if swap && !reference.iter().all(|x| b"ACGT".contains(x)) {
+        return Ok(false);
+    }
+
+    buffer.clear();
+    if swap {
+        let mut repaired = record.clone().complete_parse()?;
+        repaired.reference = Cow::Owned(sequence.to_vec());
+        replace_reference(record, &mut repaired, reference, param, rewrite_target)?;
+        repaired.info.push((
+            Cow::Owned(REPAIRED_REF.as_bytes().to_vec()),
+            vec![Cow::Owned(reference.to_vec())],
+        ));
+        repaired.write(buffer)?;
+    } else {
+        let mut repaired = record.clone();
+        repaired.reference = Cow::Owned(sequence.to_vec());
+        let info = repaired.unparsed_info.to_mut();
+        if info as &[u8] == &b"."[..] {
+            info.clear();
+        } else {
+            info.push(b';');
+        }
+        info.extend_from_slice(REPAIRED_REF.as_bytes());
+        info.push(b'=');
+        info.extend_from_slice(reference);
+        repaired.write(buffer)?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vcfparse::VCFReader;
+    use std::str;
+
+    #[test]
+    fn test_is_iupac_compatible() {
+        assert!(is_iupac_compatible(b"ARN", b"AGT"));
+        assert!(is_iupac_compatible(b"r", b"G"));
+        assert!(!is_iupac_compatible(b"Y", b"A"));
+        assert!(!is_iupac_compatible(b"AC", b"A"));
+    }
+
+    #[test]
+    fn test_repair_reference() -> Result<(), VCFParseError> {
+        let header = VCFReader::new(
+            &b"##fileformat=VCFv4.2
+##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1
+"[..],
+        )?
+        .header;
+        let param = VCFLiftOverParameters::new().repair_reference(true);
+        let rewrite_target = VCFHeaderRewriteTarget::new(&header, &param);
+        let mut buffer = Vec::new();
+
+        for (line, sequence, expected) in [
+            (
+                "1\t10\t.\tR\tC\t.\t.\tAF=0.2\tGT\t0/1\n",
+                &b"G"[..],
+                Some("1\t10\t.\tG\tC\t.\t.\tAF=0.2;REPAIRED_REF=R\tGT\t0/1\n"),
+            ),
+            (
+                "1\t10\t.\tA\tC\t.\t.\t.\tGT\t0/1\n",
+                &b"T"[..],
+                Some("1\t10\t.\tT\tC\t.\t.\tREPAIRED_REF=A\tGT\t0/1\n"),
+            ),
+            (
+                "1\t10\t.\tA\tG\t.\t.\tAF=0.2\tGT\t0/0\n",
+                &b"G"[..],
+                Some("1\t10\t.\tG\tA\t.\t.\tAF=0.8;REPAIRED_REF=A\tGT\t1/1\n"),
+            ),
+            ("1\t10\t.\tA\tG\t.\t.\t.\tGT\t0/1\n", &b"A"[..], None),
+            ("1\t10\t.\tA\tG\t.\t.\t.\tGT\t0/1\n", &b"N"[..], None),
+            ("1\t10\t.\tR\tG\t.\t.\t.\tGT\t0/1\n", &b"G"[..], None),
+        ] {
+            let record = PartialVCFRecord::parse_vcf(1, line.as_bytes())?;
+            let repaired =
+                repair_reference(&record, sequence, &param, &rewrite_target, &mut buffer)?;
+            assert_eq!(
+                repaired.then(|| str::from_utf8(&buffer).unwrap()),
+                expected,
+                "{}",
+                line
+            );
+        }
+
+        let param = VCFLiftOverParameters::new().accept_iupac_reference(true);
+        for (line, sequence) in [
+            ("1\t10\t.\tA\tC\t.\t.\t.\tGT\t0/1\n", b"T"),
+            ("1\t10\t.\tN\tC\t.\t.\t.\tGT\t0/1\n", b"C"),
+        ] {
+            let record = PartialVCFRecord::parse_vcf(1, line.as_bytes())?;
+            assert!(
+                !repair_reference(&record, sequence, &param, &rewrite_target, &mut buffer)?,
+                "{}",
+                line
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! Detect changes of variant representation caused by normalization in the new assembly.
+
+use super::{add_info_helper, VCFRecordWrapper};
+use crate::chain::Strand;
+use crate::poslift::PositionLiftOver;
+use crate::variantlift::LiftedVariant;
+use crate::Variant;
+
+pub(super) const REPRESENTATION_CHANGED: &str = "REPRESENTATION_CHANGED";
+pub(super) const POSITION_SHIFT: &str = "POSITION_SHIFT";
+
+pub(super) const POSITION_SHIFTED: &str = "POSITION_SHIFTED";
+pub(super) const REVERSE_STRAND_SHIFTED: &str = "REVERSE_STRAND_SHIFTED";
+pub(super) const ALLELE_LENGTH_CHANGED: &str = "ALLELE_LENGTH_CHANGED";
+
+/// Compare a lifted variant with a direct coordinate shift of the original variant. Returns
+/// kinds of changes and a difference of positions.
+pub(super) fn representation_changes(
+    position_liftover: &PositionLiftOver,
+    original: &Variant,
+    lifted: &LiftedVariant,
+) -> (Vec<&'static str>, Option<i64>) {
+    let mut changes = Vec::new();
+
+    // The first base of the original variant is the last base on the reverse strand, and a padding
+    // base of an indel is placed before the variant in the new assembly.
+    let (original_position, padding) = match lifted.strand {
+        Strand::Forward => (original.position, 0),
+        Strand::Reverse => (
+            original.position + original.reference.len().max(1) as u64 - 1,
+            has_padding_base(original) as i64,
+        ),
+    };
+    let shift = position_liftover
+        .lift_position(&original.chromosome, original_position)
+        .iter()
+        .filter(|x| x.chromosome.name == lifted.chromosome && x.strand == lifted.strand)
+        .map(|x| lifted.position as i64 - x.position as i64 + padding)
+        .min_by_key(|x| x.abs())
+        .filter(|x| *x != 0);
+    if shift.is_some() {
+        changes.push(match lifted.strand {
+            Strand::Forward => POSITION_SHIFTED,
+            Strand::Reverse => REVERSE_STRAND_SHIFTED,
+        });
+    }
+
+    if lifted.reference.len() != original.reference.len()
+        || lifted
+            .alternative
+            .iter()
+            .zip(original.alternative.iter())
+            .any(|(x, y)| x.len() != y.len())
+    {
+        changes.push(ALLELE_LENGTH_CHANGED);
+    }
+
+    (changes, shift)
+}
+
+/// Returns true if all alleles of an indel start with the same base.
+fn has_padding_base(variant: &Variant) -> bool {
+    let first = variant.reference.first();
+    first.is_some()
+        && variant
+            .alternative
+            .iter()
+            .all(|x| x.first() == first && x.len() != variant.reference.len())
+}
+
+/// Add `REPRESENTATION_CHANGED` and `POSITION_SHIFT` into INFO column.
+pub(super) fn add_representation_info(
+    record: &mut VCFRecordWrapper,
+    changes: &[&str],
+    shift: Option<i64>,
+) {
+    if changes.is_empty() {
+        return;
+    }
+    add_info_helper(
+        record,
+        REPRESENTATION_CHANGED.as_bytes(),
+        &changes
+            .iter()
+            .map(|x| x.as_bytes().to_vec())
+            .collect::<Vec<_>>(),
+    );
+    if let Some(shift) = shift {
+        add_info_helper(
+            record,
+            POSITION_SHIFT.as_bytes(),
+            &[format!("{}", shift).into_bytes()],
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LiftOverError;
+
+    fn lifted(
+        position: u64,
+        strand: Strand,
+        reference: &[u8],
+        alternative: &[u8],
+    ) -> LiftedVariant {
+        LiftedVariant {
+            chromosome: "chr1".to_string(),
+            position,
+            strand,
+            original_reference: reference.to_vec(),
+            reference: reference.to_vec(),
+            alternative: vec![alternative.to_vec()],
+            reference_changed: false,
+        }
+    }
+
+    #[test]
+    fn test_representation_changes() -> Result<(), LiftOverError> {
+        let forward = PositionLiftOver::load(
+            &b"chain 1000 chr1 1000 + 0 1000 chr1 995 + 0 995 1\n500\t5\t0\n495\n\n"[..],
+        )?;
+        let snv = Variant::new("chr1", 100, b"A", &[b"G"]);
+        assert_eq!(
+            representation_changes(&forward, &snv, &lifted(100, Strand::Forward, b"A", b"G")),
+            (vec![], None)
+        );
+        assert_eq!(
+            representation_changes(&forward, &snv, &lifted(98, Strand::Forward, b"A", b"G")),
+            (vec![POSITION_SHIFTED], Some(-2))
+        );
+        let deletion = Variant::new("chr1", 700, b"ATT", &[b"A"]);
+        assert_eq!(
+            representation_changes(
+                &forward,
+                &deletion,
+                &lifted(695, Strand::Forward, b"AT", b"A")
+            ),
+            (vec![ALLELE_LENGTH_CHANGED], None)
+        );
+
+        let reverse = PositionLiftOver::load(
+            &b"chain 1000 chr1 1000 + 0 1000 chr1 1000 - 0 1000 2\n1000\n\n"[..],
+        )?;
+        let deletion = Variant::new("chr1", 100, b"AT", &[b"A"]);
+        assert_eq!(
+            representation_changes(
+                &reverse,
+                &deletion,
+                &lifted(897, Strand::Reverse, b"CA", b"C")
+            ),
+            (vec![], None)
+        );
+        assert_eq!(
+            representation_changes(
+                &reverse,
+                &deletion,
+                &lifted(895, Strand::Reverse, b"CA", b"C")
+            ),
+            (vec![REVERSE_STRAND_SHIFTED], Some(-2))
+        );
+        Ok(())
+    }
+}
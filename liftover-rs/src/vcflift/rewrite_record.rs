@@ -18,6 +18,7 @@ pub fn rewrite_info(
     rewrite_target: &VCFHeaderRewriteTarget,
     to_delete_index: &HashSet<usize>,
 ) {
+    let allele_mapping = AlleleMapping::new(record.alternative().len(), to_delete_index);
     for (k, v) in record.info.iter_mut() {
         if rewrite_target.info_ref.contains(k.as_ref()) {
             // Reorder Number=R INFO
@@ -50,7 +51,15 @@ pub fn rewrite_info(
                 .collect();
             v.push(Cow::Owned(b".".to_vec()));
         } else if rewrite_target.info_genotype.contains(k.as_ref()) {
-            // TODO: implement here
+            // Reorder Number=G INFO
+            if let Some(rewritten) = rewrite_genotype_values(v, None, &allele_mapping) {
+                *v = rewritten;
+            } else {
+                warn!(
+                    "Cannot rewrite INFO {}: number of values does not match number of genotypes",
+                    str::from_utf8(k).unwrap_or("")
+                );
+            }
         }
     }
 }
@@ -148,7 +157,14 @@ pub fn rewrite_format(
     rewrite_target: &VCFHeaderRewriteTarget,
     to_delete_index: &HashSet<usize>,
 ) {
+    let allele_mapping = AlleleMapping::new(record.alternative().len(), to_delete_index);
+    let gt_index = record.format.iter().position(|x| x.as_ref() == b"GT");
     for one_sample in record.call.iter_mut() {
+        let ploidy = gt_index
+            .and_then(|i| one_sample.get(i))
+            .and_then(|x| x.first())
+            .and_then(|x| parse_gt(x).ok())
+            .map(|x| x.len());
         for (k, v) in record.format.iter().zip(one_sample.iter_mut()) {
             if rewrite_target.format_ref.contains(k.as_ref()) {
                 // Reorder Number=R INFO
@@ -180,8 +196,16 @@ pub fn rewrite_format(
                     .map(|(_, x)| x.clone())
                     .collect();
                 v.push(Cow::Owned(b".".to_vec()));
-            } else {
-                // TODO: implement here
+            } else if rewrite_target.format_genotype.contains(k.as_ref()) {
+                // Reorder Number=G FORMAT
+                if let Some(rewritten) = rewrite_genotype_values(v, ploidy, &allele_mapping) {
+                    *v = rewritten;
+                } else {
+                    warn!(
+                        "Cannot rewrite FORMAT {}: number of values does not match number of genotypes",
+                        str::from_utf8(k).unwrap_or("")
+                    );
+                }
             }
         }
     }
@@ -234,14 +258,115 @@ pub fn rewrite_gt<'a>(
     Ok(())
 }
 
+/// Correspondence between alleles of a rewritten record and alleles of the original record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct AlleleMapping {
+    pub(super) original_allele_count: usize,
+    /// original allele index for each new allele index (REF is 0)
+    pub(super) new_to_original: Vec<Option<usize>>,
+}
+
+impl AlleleMapping {
+    /// `new_alternative_count` is the number of ALT alleles after swapping.
+    fn new(new_alternative_count: usize, to_delete_index: &HashSet<usize>) -> Self {
+        let original_alternative_count = new_alternative_count + to_delete_index.len() - 1;
+        let mut new_to_original = vec![to_delete_index.iter().min().map(|x| x + 1)];
+        new_to_original.extend(
+            (0..original_alternative_count)
+                .filter(|x| !to_delete_index.contains(x))
+                .map(|x| Some(x + 1)),
+        );
+        new_to_original.push(Some(0));
+        AlleleMapping {
+            original_allele_count: original_alternative_count + 1,
+            new_to_original,
+        }
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+/// Number of genotypes for the number of alleles and ploidy
+fn genotype_count(allele_count: usize, ploidy: usize) -> usize {
+    binomial(allele_count + ploidy - 1, ploidy)
+}
+
+/// Index of a genotype in Number=G fields. `alleles` should be sorted.
+fn genotype_index(alleles: &[usize]) -> usize {
+    alleles
+        .iter()
+        .enumerate()
+        .map(|(i, x)| binomial(x + i, i + 1))
+        .sum()
+}
+
+pub(super) fn infer_ploidy(allele_count: usize, value_count: usize) -> Option<usize> {
+    if allele_count < 2 {
+        return None;
+    }
+    (1..)
+        .map(|x| (x, genotype_count(allele_count, x)))
+        .take_while(|(_, count)| *count <= value_count)
+        .find(|(_, count)| *count == value_count)
+        .map(|(x, _)| x)
+}
+
+/// Reorder values of Number=G field. `ploidy` is used if the number of values matches,
+/// otherwise ploidy is inferred from the number of values.
+pub(super) fn rewrite_genotype_values<'a>(
+    values: &[Cow<'a, [u8]>],
+    ploidy: Option<usize>,
+    allele_mapping: &AlleleMapping,
+) -> Option<Vec<Cow<'a, [u8]>>> {
+    if values.len() == 1 && values[0].as_ref() == b"." {
+        return Some(values.to_vec());
+    }
+    let ploidy = ploidy
+        .filter(|x| {
+            *x > 0 && genotype_count(allele_mapping.original_allele_count, *x) == values.len()
+        })
+        .or_else(|| infer_ploidy(allele_mapping.original_allele_count, values.len()))?;
+
+    let new_allele_count = allele_mapping.new_to_original.len();
+    let mut rewritten = vec![Cow::Owned(b".".to_vec()); genotype_count(new_allele_count, ploidy)];
+    let mut genotype = vec![0; ploidy];
+    loop {
+        let original_genotype: Option<Vec<_>> = genotype
+            .iter()
+            .map(|x| allele_mapping.new_to_original[*x])
+            .collect();
+        if let Some(mut original_genotype) = original_genotype {
+            original_genotype.sort_unstable();
+            rewritten[genotype_index(&genotype)] =
+                values[genotype_index(&original_genotype)].clone();
+        }
+
+        // next non-decreasing genotype
+        let Some(i) = genotype.iter().rposition(|x| x + 1 < new_allele_count) else {
+            break;
+        };
+        let next = genotype[i] + 1;
+        for x in genotype[i..].iter_mut() {
+            *x = next;
+        }
+    }
+
+    Some(rewritten)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum GTSeparator {
+pub(super) enum GTSeparator {
     Phased,
     Unphased,
     None,
 }
 
-fn parse_gt(input: &[u8]) -> Result<Vec<(Option<usize>, GTSeparator)>, VCFParseError> {
+pub(super) fn parse_gt(input: &[u8]) -> Result<Vec<(Option<usize>, GTSeparator)>, VCFParseError> {
     let (input, values) = many1(pair(
         alt((digit1, tag(b"."))),
         opt(alt((tag(b"|"), tag(b"/")))),
@@ -272,7 +397,7 @@ fn parse_gt(input: &[u8]) -> Result<Vec<(Option<usize>, GTSeparator)>, VCFParseE
         })?)
 }
 
-fn format_gt(gt: &[(Option<usize>, GTSeparator)]) -> String {
+pub(super) fn format_gt(gt: &[(Option<usize>, GTSeparator)]) -> String {
     let mut result = String::new();
     for one in gt {
         if let Some(x) = one.0 {
@@ -315,4 +440,83 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_genotype_index() {
+        assert_eq!(genotype_index(&[0, 0]), 0);
+        assert_eq!(genotype_index(&[0, 1]), 1);
+        assert_eq!(genotype_index(&[1, 1]), 2);
+        assert_eq!(genotype_index(&[0, 2]), 3);
+        assert_eq!(genotype_index(&[1, 2]), 4);
+        assert_eq!(genotype_index(&[2, 2]), 5);
+        assert_eq!(genotype_index(&[2]), 2);
+        assert_eq!(genotype_index(&[0, 0, 1]), 1);
+        assert_eq!(genotype_index(&[1, 1, 1]), 3);
+        assert_eq!(genotype_count(3, 2), 6);
+        assert_eq!(genotype_count(2, 3), 4);
+        assert_eq!(infer_ploidy(2, 3), Some(2));
+        assert_eq!(infer_ploidy(3, 3), Some(1));
+        assert_eq!(infer_ploidy(2, 4), Some(3));
+        assert_eq!(infer_ploidy(3, 4), None);
+    }
+
+    fn to_values(values: &[&str]) -> Vec<Cow<'static, [u8]>> {
+        values
+            .iter()
+            .map(|x| Cow::Owned(x.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rewrite_genotype_values() {
+        // REF=A ALT=G -> REF=G ALT=A
+        let mapping = AlleleMapping::new(1, &[0].iter().cloned().collect());
+        assert_eq!(mapping.new_to_original, vec![Some(1), Some(0)]);
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["0", "10", "100"]), Some(2), &mapping),
+            Some(to_values(&["100", "10", "0"]))
+        );
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["0", "10"]), Some(2), &mapping),
+            Some(to_values(&["10", "0"]))
+        );
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["0", "1", "2", "3"]), None, &mapping),
+            Some(to_values(&["3", "2", "1", "0"]))
+        );
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["."]), Some(2), &mapping),
+            Some(to_values(&["."]))
+        );
+
+        // REF=A ALT=C,G -> REF=G ALT=C,A
+        let mapping = AlleleMapping::new(2, &[1].iter().cloned().collect());
+        assert_eq!(mapping.new_to_original, vec![Some(2), Some(1), Some(0)]);
+        // original order: AA AC CC AG CG GG
+        // new order: GG GC CC GA CA AA
+        assert_eq!(
+            rewrite_genotype_values(
+                &to_values(&["AA", "AC", "CC", "AG", "CG", "GG"]),
+                Some(2),
+                &mapping
+            ),
+            Some(to_values(&["GG", "CG", "CC", "AG", "AC", "AA"]))
+        );
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["A", "C", "G"]), Some(2), &mapping),
+            Some(to_values(&["G", "C", "A"]))
+        );
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["0", "1", "2", "3", "4"]), Some(2), &mapping),
+            None
+        );
+
+        // REF=A ALT=C -> REF=G ALT=C,A
+        let mapping = AlleleMapping::new(2, &HashSet::new());
+        assert_eq!(mapping.new_to_original, vec![None, Some(1), Some(0)]);
+        assert_eq!(
+            rewrite_genotype_values(&to_values(&["AA", "AC", "CC"]), Some(2), &mapping),
+            Some(to_values(&[".", ".", "CC", ".", "AC", "AA"]))
+        );
+    }
 }
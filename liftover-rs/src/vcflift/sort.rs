@@ -0,0 +1,257 @@
+//! Sort lifted VCF records with external merge sort.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter, SeekFrom};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SortKey {
+    contig_index: usize,
+    /// contig name is used to sort contigs which are not found in header
+    contig: Vec<u8>,
+    position: u64,
+}
+
+/// A writer to sort VCF records by contig order in the header and position.
+///
+/// Header lines are written as is, and records are buffered until `finish` is called.
+/// Sorted chunks are written to temporary files if buffered records exceed `buffer_size` bytes.
+/// Records with the same position keep original order. Lines starting with NUL are attached to a
+/// previous record, and are written after the record.
+#[derive(Debug)]
+pub struct SortingWriter<W: Write> {
+    writer: W,
+    contig_order: HashMap<Vec<u8>, usize>,
+    buffer_size: usize,
+    buffer: Vec<(SortKey, Vec<u8>)>,
+    buffered_bytes: usize,
+    chunks: Vec<File>,
+    partial_line: Vec<u8>,
+}
+
+impl<W: Write> SortingWriter<W> {
+    /// `contigs` are contig names in the output order.
+    pub fn new<I: IntoIterator<Item = Vec<u8>>>(
+        writer: W,
+        contigs: I,
+        buffer_size: usize,
+    ) -> SortingWriter<W> {
+        SortingWriter {
+            writer,
+            contig_order: contigs
+                .into_iter()
+                .enumerate()
+                .map(|(i, x)| (x, i))
+                .collect(),
+            buffer_size,
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            chunks: Vec::new(),
+            partial_line: Vec::new(),
+        }
+    }
+
+    fn sort_key(&self, line: &[u8]) -> io::Result<SortKey> {
+        let mut columns = line.splitn(3, |x| *x == b'\t');
+        let contig = columns.next().unwrap_or_default();
+        let position = columns
+            .next()
+            .and_then(|x| std::str::from_utf8(x).ok())
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Cannot sort VCF record: {}",
+                        String::from_utf8_lossy(line).trim_end()
+                    ),
+                )
+            })?;
+        Ok(match self.contig_order.get(contig) {
+            Some(index) => SortKey {
+                contig_index: *index,
+                contig: Vec::new(),
+                position,
+            },
+            None => SortKey {
+                contig_index: usize::MAX,
+                contig: contig.to_vec(),
+                position,
+            },
+        })
+    }
+
+    fn push_line(&mut self, line: Vec<u8>) -> io::Result<()> {
+        if line.starts_with(b"#") && self.buffer.is_empty() && self.chunks.is_empty() {
+            return self.writer.write_all(&line);
+        }
+        if line.starts_with(b"\0") {
+            if let Some(last) = self.buffer.last_mut() {
+                self.buffered_bytes += line.len();
+                last.1.extend_from_slice(&line);
+                return Ok(());
+            }
+        }
+        // spill before a new record to keep attached lines with a record
+        if self.buffered_bytes >= self.buffer_size {
+            self.spill()?;
+        }
+        let key = self.sort_key(&line)?;
+        self.buffered_bytes += line.len() + key.contig.len() + std::mem::size_of::<SortKey>();
+        self.buffer.push((key, line));
+        Ok(())
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort_by(|x, y| x.0.cmp(&y.0));
+        let mut chunk = BufWriter::new(tempfile::tempfile()?);
+        for (_, line) in self.buffer.drain(..) {
+            chunk.write_all(&line)?;
+        }
+        let mut chunk = chunk.into_inner().map_err(|e| e.into_error())?;
+        chunk.seek(SeekFrom::Start(0))?;
+        self.chunks.push(chunk);
+        self.buffered_bytes = 0;
+        Ok(())
+    }
+
+    /// Write all buffered records in sorted order, and return an inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.partial_line.is_empty() {
+            let mut line = std::mem::take(&mut self.partial_line);
+            line.push(b'\n');
+            self.push_line(line)?;
+        }
+
+        if self.chunks.is_empty() {
+            self.buffer.sort_by(|x, y| x.0.cmp(&y.0));
+            for (_, line) in self.buffer.drain(..) {
+                self.writer.write_all(&line)?;
+            }
+        } else {
+            if !self.buffer.is_empty() {
+                self.spill()?;
+            }
+            self.merge_chunks()?;
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn merge_chunks(&mut self) -> io::Result<()> {
+        let mut readers: Vec<_> = self.chunks.drain(..).map(BufReader::new).collect();
+        let mut heap = BinaryHeap::new();
+        for (i, reader) in readers.iter_mut().enumerate() {
+            let mut line = Vec::new();
+            if read_record(reader, &mut line)? {
+                heap.push(Reverse(MergeItem {
+                    key: self.sort_key(&line)?,
+                    chunk: i,
+                    line,
+                }));
+            }
+        }
+
+        while let Some(Reverse(item)) = heap.pop() {
+            self.writer.write_all(&item.line)?;
+            let mut line = item.line;
+            line.clear();
+            if read_record(&mut readers[item.chunk], &mut line)? {
+                heap.push(Reverse(MergeItem {
+                    key: self.sort_key(&line)?,
+                    chunk: item.chunk,
+                    line,
+                }));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a record with attached lines from a sorted chunk.
+fn read_record<R: BufRead>(reader: &mut R, line: &mut Vec<u8>) -> io::Result<bool> {
+    if reader.read_until(b'\n', line)? == 0 {
+        return Ok(false);
+    }
+    while reader.fill_buf()?.first() == Some(&b'\0') {
+        reader.read_until(b'\n', line)?;
+    }
+    Ok(true)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct MergeItem {
+    key: SortKey,
+    chunk: usize,
+    line: Vec<u8>,
+}
+
+impl PartialOrd for MergeItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // earlier chunk contains earlier records
+        (&self.key, self.chunk).cmp(&(&other.key, other.chunk))
+    }
+}
+
+impl<W: Write> Write for SortingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(index) = rest.iter().position(|x| *x == b'\n') {
+            let mut line = std::mem::take(&mut self.partial_line);
+            line.extend_from_slice(&rest[..=index]);
+            self.push_line(line)?;
+            rest = &rest[index + 1..];
+        }
+        self.partial_line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // records are written in `finish`
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sorting_writer() -> io::Result<()> {
+        let input: &[u8] = b"##fileformat=VCFv4.2\n#CHROM\tPOS\n\
+chr2\t100\ta\nchr1\t300\tb\nchrUn\t10\tc\nchr1\t100\td\nchr2\t50\te\nchr1\t100\tf\nchrM\t1\tg\n";
+        let expected: &[u8] = b"##fileformat=VCFv4.2\n#CHROM\tPOS\n\
+chr1\t100\td\nchr1\t100\tf\nchr1\t300\tb\nchr2\t50\te\nchr2\t100\ta\nchrM\t1\tg\nchrUn\t10\tc\n";
+        let contigs = vec![b"chr1".to_vec(), b"chr2".to_vec(), b"chrM".to_vec()];
+
+        // sort in memory
+        let mut writer = SortingWriter::new(Vec::new(), contigs.clone(), 1 << 20);
+        writer.write_all(input)?;
+        assert_eq!(writer.finish()?, expected);
+
+        // sort with temporary files
+        let mut writer = SortingWriter::new(Vec::new(), contigs, 1);
+        for one in input.chunks(7) {
+            writer.write_all(one)?;
+        }
+        assert_eq!(writer.finish()?, expected);
+
+        // attached lines are kept with a record
+        let input: &[u8] = b"chr2\t100\ta\n\0a\nchr1\t300\tb\n\0b1\n\0b2\nchr1\t100\tc\n";
+        let expected: &[u8] = b"chr1\t100\tc\nchr1\t300\tb\n\0b1\n\0b2\nchr2\t100\ta\n\0a\n";
+        let mut writer = SortingWriter::new(Vec::new(), vec![b"chr1".to_vec()], 1);
+        for one in input.chunks(5) {
+            writer.write_all(one)?;
+        }
+        assert_eq!(writer.finish()?, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,439 @@
+//! Summary of VCF liftOver broken down by failure reason, chromosome and variant type.
+
+use super::{collision, representation, svlift, verify, LiftOverFailure, VCFRecordWrapper};
+use crate::vcfparse::{PartialVCFRecord, VCFRecord};
+use crate::LiftOverError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RecordCount {
+    pub mapped: u64,
+    pub unmapped: u64,
+}
+
+impl RecordCount {
+    pub fn total(&self) -> u64 {
+        self.mapped + self.unmapped
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MultimapSummary {
+    /// multi-mapped records which were lifted to the same name contig
+    pub resolved_by_same_contig: u64,
+    /// multi-mapped records which were lifted to all positions
+    pub allowed: u64,
+    /// multi-mapped records which were failed
+    pub failed: u64,
+}
+
+/// Lifted records whose representation differs from a direct coordinate shift
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RepresentationSummary {
+    pub position_shifted: u64,
+    pub reverse_strand_shifted: u64,
+    pub allele_length_changed: u64,
+}
+
+/// Summary of `VCFLiftOver::lift_vcf`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LiftSummary {
+    pub total: RecordCount,
+    pub failure_reasons: BTreeMap<String, u64>,
+    /// counts by original chromosome
+    pub chromosomes: BTreeMap<String, RecordCount>,
+    /// counts by variant class (SNV, MNP, INSERTION, DELETION, SYMBOLIC, MIXED or REFERENCE)
+    pub variant_classes: BTreeMap<String, RecordCount>,
+    /// lifted records whose reference allele was changed
+    pub reference_changed: u64,
+    /// lifted records whose REF and ALT were swapped
+    pub swapped: u64,
+    pub multimap: MultimapSummary,
+    /// records which did not return to the original position and alleles with a reverse chain.
+    /// Mapped records are marked with `REVERSE_LIFT_MISMATCH` flag.
+    pub reverse_lift_mismatch: RecordCount,
+    /// records marked with `REPRESENTATION_CHANGED`
+    pub representation: RepresentationSummary,
+    /// lifted records at the same position with the same alleles as other records. Kept records
+    /// are counted as mapped.
+    pub collision: RecordCount,
+}
+
+/// Classify a record by its REF and ALT alleles. A record with different classes of ALT alleles
+/// is `MIXED`.
+pub fn variant_class<R: VCFRecord>(record: &R) -> &'static str {
+    let reference = record.reference();
+    let mut classes = record
+        .alternative()
+        .iter()
+        .filter(|x| x.as_ref() != b"*" && x.as_ref() != b".")
+        .map(|x| {
+            if svlift::is_symbolic_allele(x) || svlift::is_breakend_allele(x) {
+                "SYMBOLIC"
+            } else if x.len() == reference.len() {
+                if x.len() == 1 {
+                    "SNV"
+                } else {
+                    "MNP"
+                }
+            } else if x.len() > reference.len() {
+                "INSERTION"
+            } else {
+                "DELETION"
+            }
+        });
+    match classes.next() {
+        None => "REFERENCE",
+        Some(first) => {
+            if classes.all(|x| x == first) {
+                first
+            } else {
+                "MIXED"
+            }
+        }
+    }
+}
+
+fn has_info(record: &VCFRecordWrapper, key: &[u8]) -> bool {
+    match record {
+        VCFRecordWrapper::Partial(p) => p
+            .unparsed_info
+            .split(|x| *x == b';')
+            .any(|x| x.split(|y| *y == b'=').next() == Some(key)),
+        VCFRecordWrapper::Complete(c) => c.info.iter().any(|x| x.0.as_ref() == key),
+    }
+}
+
+fn info_values(record: &VCFRecordWrapper, key: &[u8]) -> Vec<Vec<u8>> {
+    match record {
+        VCFRecordWrapper::Partial(p) => p
+            .unparsed_info
+            .split(|x| *x == b';')
+            .filter_map(|x| {
+                let mut key_value = x.splitn(2, |y| *y == b'=');
+                if key_value.next() == Some(key) {
+                    key_value.next()
+                } else {
+                    None
+                }
+            })
+            .flat_map(|x| x.split(|y| *y == b',').map(|y| y.to_vec()))
+            .collect(),
+        VCFRecordWrapper::Complete(c) => c
+            .info
+            .iter()
+            .filter(|x| x.0.as_ref() == key)
+            .flat_map(|x| x.1.iter().map(|y| y.to_vec()))
+            .collect(),
+    }
+}
+
+/// Counts of a mapped record other than totals. They are kept to move the record to unmapped
+/// if the record is removed after liftover.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct MappedFlags {
+    reference_changed: bool,
+    swapped: bool,
+    resolved_by_same_contig: bool,
+    multimap_allowed: bool,
+    reverse_lift_mismatch: bool,
+    position_shifted: bool,
+    reverse_strand_shifted: bool,
+    allele_length_changed: bool,
+}
+
+impl MappedFlags {
+    pub fn new(lifted: &[VCFRecordWrapper], resolved_by_same_contig: bool) -> Self {
+        let changes: Vec<_> = lifted
+            .iter()
+            .flat_map(|x| info_values(x, representation::REPRESENTATION_CHANGED.as_bytes()))
+            .collect();
+        let changed = |kind: &str| changes.iter().any(|x| x == kind.as_bytes());
+        MappedFlags {
+            reference_changed: lifted.iter().any(|x| has_info(x, b"ORIGINAL_REF")),
+            swapped: lifted.iter().any(|x| has_info(x, b"REF_CHANGED")),
+            resolved_by_same_contig,
+            multimap_allowed: lifted.iter().any(|x| has_info(x, b"MULTIMAP")),
+            reverse_lift_mismatch: lifted
+                .iter()
+                .any(|x| has_info(x, verify::REVERSE_LIFT_MISMATCH.as_bytes())),
+            position_shifted: changed(representation::POSITION_SHIFTED),
+            reverse_strand_shifted: changed(representation::REVERSE_STRAND_SHIFTED),
+            allele_length_changed: changed(representation::ALLELE_LENGTH_CHANGED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == MappedFlags::default()
+    }
+}
+
+impl LiftSummary {
+    fn record_count<'a>(
+        counts: &'a mut BTreeMap<String, RecordCount>,
+        key: &str,
+    ) -> &'a mut RecordCount {
+        counts.entry(key.to_string()).or_default()
+    }
+
+    /// Add or subtract counts of a mapped record.
+    fn count_mapped(&mut self, original: &PartialVCFRecord, flags: &MappedFlags, add: bool) {
+        let update = |count: &mut u64, flag: bool| {
+            if flag && add {
+                *count += 1;
+            } else if flag {
+                *count -= 1;
+            }
+        };
+        update(&mut self.total.mapped, true);
+        update(
+            &mut Self::record_count(
+                &mut self.chromosomes,
+                &String::from_utf8_lossy(original.contig()),
+            )
+            .mapped,
+            true,
+        );
+        update(
+            &mut Self::record_count(&mut self.variant_classes, variant_class(original)).mapped,
+            true,
+        );
+        update(&mut self.reference_changed, flags.reference_changed);
+        update(&mut self.swapped, flags.swapped);
+        update(
+            &mut self.multimap.resolved_by_same_contig,
+            flags.resolved_by_same_contig,
+        );
+        update(&mut self.multimap.allowed, flags.multimap_allowed);
+        update(
+            &mut self.reverse_lift_mismatch.mapped,
+            flags.reverse_lift_mismatch,
+        );
+        update(
+            &mut self.representation.position_shifted,
+            flags.position_shifted,
+        );
+        update(
+            &mut self.representation.reverse_strand_shifted,
+            flags.reverse_strand_shifted,
+        );
+        update(
+            &mut self.representation.allele_length_changed,
+            flags.allele_length_changed,
+        );
+    }
+
+    pub(super) fn add_mapped(&mut self, original: &PartialVCFRecord, flags: &MappedFlags) {
+        self.count_mapped(original, flags, true);
+    }
+
+    pub(super) fn add_unmapped(&mut self, original: &PartialVCFRecord, failure: &LiftOverFailure) {
+        self.total.unmapped += 1;
+        Self::record_count(
+            &mut self.chromosomes,
+            &String::from_utf8_lossy(original.contig()),
+        )
+        .unmapped += 1;
+        Self::record_count(&mut self.variant_classes, variant_class(original)).unmapped += 1;
+        for reason in failure.reasons.iter() {
+            *self.failure_reasons.entry(reason.to_string()).or_default() += 1;
+        }
+        if failure.multimap.is_some() {
+            self.multimap.failed += 1;
+        }
+        if failure.reasons.contains(&verify::REVERSE_LIFT_MISMATCH) {
+            self.reverse_lift_mismatch.unmapped += 1;
+        }
+        if failure.reasons.contains(&collision::COLLISION) {
+            self.collision.unmapped += 1;
+        }
+    }
+
+    /// Count a mapped record as unmapped, if the record is removed after liftover.
+    pub(super) fn move_to_unmapped(
+        &mut self,
+        original: &PartialVCFRecord,
+        flags: &MappedFlags,
+        failure: &LiftOverFailure,
+    ) {
+        self.count_mapped(original, flags, false);
+        self.add_unmapped(original, failure);
+    }
+
+    /// Write numbers of total, mapped and unmapped records as TSV, followed by the breakdown
+    /// by failure reason, chromosome and variant class and other counts of the JSON summary.
+    /// Percentages are relative to the number of total records.
+    pub fn write_tsv<W: Write>(&self, writer: W) -> Result<(), LiftOverError> {
+        let mut csv_writer = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .quote_style(csv::QuoteStyle::Never)
+            .escape(b'\\')
+            .from_writer(writer);
+        let total = self.total.total();
+        csv_writer.write_record(["Title", "Count", "Percent"])?;
+        let mut write_row = |title: String, count: u64| {
+            csv_writer.write_record(&[
+                title,
+                count.to_string(),
+                format!("{:.2}%", count as f64 / total as f64 * 100.),
+            ])
+        };
+        write_row("Total record".to_string(), total)?;
+        write_row("Mapped record".to_string(), self.total.mapped)?;
+        write_row("Unmapped record".to_string(), self.total.unmapped)?;
+
+        for (reason, count) in self.failure_reasons.iter() {
+            write_row(format!("Failure reason {}", reason), *count)?;
+        }
+        for (title, counts) in [
+            ("Chromosome", &self.chromosomes),
+            ("Variant class", &self.variant_classes),
+        ] {
+            for (key, count) in counts.iter() {
+                write_row(format!("{} {} mapped", title, key), count.mapped)?;
+                write_row(format!("{} {} unmapped", title, key), count.unmapped)?;
+            }
+        }
+        for (title, count) in [
+            ("Reference changed", self.reference_changed),
+            ("Swapped", self.swapped),
+            (
+                "Multimap resolved by same contig",
+                self.multimap.resolved_by_same_contig,
+            ),
+            ("Multimap allowed", self.multimap.allowed),
+            ("Multimap failed", self.multimap.failed),
+            (
+                "Reverse lift mismatch mapped",
+                self.reverse_lift_mismatch.mapped,
+            ),
+            (
+                "Reverse lift mismatch unmapped",
+                self.reverse_lift_mismatch.unmapped,
+            ),
+            ("Position shifted", self.representation.position_shifted),
+            (
+                "Reverse strand shifted",
+                self.representation.reverse_strand_shifted,
+            ),
+            (
+                "Allele length changed",
+                self.representation.allele_length_changed,
+            ),
+            ("Collision mapped", self.collision.mapped),
+            ("Collision unmapped", self.collision.unmapped),
+        ] {
+            write_row(title.to_string(), count)?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), LiftOverError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_variant_class() -> Result<(), LiftOverError> {
+        for (line, expected) in [
+            ("1\t1\t.\tA\tG\t.\t.\t.\n", "SNV"),
+            ("1\t1\t.\tAC\tGT\t.\t.\t.\n", "MNP"),
+            ("1\t1\t.\tA\tAT,ATT\t.\t.\t.\n", "INSERTION"),
+            ("1\t1\t.\tAT\tA,*\t.\t.\t.\n", "DELETION"),
+            ("1\t1\t.\tA\t<DEL>\t.\t.\t.\n", "SYMBOLIC"),
+            ("1\t1\t.\tA\tG]2:100]\t.\t.\t.\n", "SYMBOLIC"),
+            ("1\t1\t.\tA\tG,AT\t.\t.\t.\n", "MIXED"),
+            ("1\t1\t.\tA\t.\t.\t.\t.\n", "REFERENCE"),
+        ] {
+            let record = PartialVCFRecord::parse_vcf(1, line.as_bytes())?;
+            assert_eq!(variant_class(&record), expected, "{}", line);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_tsv() -> Result<(), LiftOverError> {
+        let mut summary = LiftSummary::default();
+        let record = PartialVCFRecord::parse_vcf(1, b"1\t1\t.\tA\tG\t.\t.\t.\n")?;
+        let lifted = PartialVCFRecord::parse_vcf(1, b"1\t3\t.\tA\tG\t.\t.\tORIGINAL_REF=C;REPRESENTATION_CHANGED=POSITION_SHIFTED;POSITION_SHIFT=2\n")?;
+        summary.add_mapped(
+            &record,
+            &MappedFlags::new(&[VCFRecordWrapper::Partial(lifted)], false),
+        );
+        let mut failure = LiftOverFailure::default();
+        failure.add_reason("MULTIMAP");
+        failure.multimap = Some(2);
+        let record = PartialVCFRecord::parse_vcf(2, b"2\t1\t.\tAT\tA\t.\t.\t.\n")?;
+        summary.add_unmapped(&record, &failure);
+
+        let mut tsv = Vec::new();
+        summary.write_tsv(&mut tsv)?;
+        assert_eq!(
+            str::from_utf8(&tsv).unwrap(),
+            "Title\tCount\tPercent
+Total record\t2\t100.00%
+Mapped record\t1\t50.00%
+Unmapped record\t1\t50.00%
+Failure reason MULTIMAP\t1\t50.00%
+Chromosome 1 mapped\t1\t50.00%
+Chromosome 1 unmapped\t0\t0.00%
+Chromosome 2 mapped\t0\t0.00%
+Chromosome 2 unmapped\t1\t50.00%
+Variant class DELETION mapped\t0\t0.00%
+Variant class DELETION unmapped\t1\t50.00%
+Variant class SNV mapped\t1\t50.00%
+Variant class SNV unmapped\t0\t0.00%
+Reference changed\t1\t50.00%
+Swapped\t0\t0.00%
+Multimap resolved by same contig\t0\t0.00%
+Multimap allowed\t0\t0.00%
+Multimap failed\t1\t50.00%
+Reverse lift mismatch mapped\t0\t0.00%
+Reverse lift mismatch unmapped\t0\t0.00%
+Position shifted\t1\t50.00%
+Reverse strand shifted\t0\t0.00%
+Allele length changed\t0\t0.00%
+Collision mapped\t0\t0.00%
+Collision unmapped\t0\t0.00%
+"
+        );
+
+        let mut json = Vec::new();
+        summary.write_json(&mut json)?;
+        let json: serde_json::Value = serde_json::from_slice(&json)?;
+        assert_eq!(json["failure_reasons"]["MULTIMAP"], 1);
+        assert_eq!(json["variant_classes"]["SNV"]["mapped"], 1);
+        assert_eq!(json["representation"]["position_shifted"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_to_unmapped() -> Result<(), LiftOverError> {
+        let mut summary = LiftSummary::default();
+        let record = PartialVCFRecord::parse_vcf(1, b"1\t1\t.\tA\tG\t.\t.\t.\n")?;
+        let lifted = PartialVCFRecord::parse_vcf(1, b"1\t3\t.\tG\tA\t.\t.\tORIGINAL_REF=A;REF_CHANGED;MULTIMAP=2;REPRESENTATION_CHANGED=ALLELE_LENGTH_CHANGED\n")?;
+        let flags = MappedFlags::new(&[VCFRecordWrapper::Partial(lifted)], true);
+        summary.add_mapped(&record, &flags);
+        assert_eq!(summary.reference_changed, 1);
+
+        let mut failure = LiftOverFailure::default();
+        failure.add_reason(collision::COLLISION);
+        summary.move_to_unmapped(&record, &flags, &failure);
+
+        let mut expected = LiftSummary::default();
+        expected.add_unmapped(&record, &failure);
+        expected.chromosomes.get_mut("1").unwrap().mapped = 0;
+        expected.variant_classes.get_mut("SNV").unwrap().mapped = 0;
+        assert_eq!(summary, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,979 @@
+//! Lift structural variants with symbolic (e.g. `<DEL>`, `<DUP>`) or breakend ALT alleles.
+
+use super::{confidence, representation, verify, LiftOverFailure, VCFLiftOver, VCFRecordWrapper};
+use crate::chain::Strand;
+use crate::defs::{reverse_complement, GenomeSequence};
+use crate::vcfparse::{CompleteVCFRecord, PartialVCFRecord, VCFRecord};
+use crate::LiftOverError;
+use std::borrow::Cow;
+use std::io::prelude::*;
+use std::str;
+
+pub(crate) fn is_symbolic_allele(allele: &[u8]) -> bool {
+    allele.len() > 2 && allele.starts_with(b"<") && allele.ends_with(b">")
+}
+
+pub(crate) fn is_breakend_allele(allele: &[u8]) -> bool {
+    allele.contains(&b'[')
+        || allele.contains(&b']')
+        || (allele.len() > 1 && (allele.starts_with(b".") || allele.ends_with(b".")))
+}
+
+/// A record is lifted as a structural variant if all ALT alleles are symbolic alleles, breakends or `*`.
+pub(super) fn is_structural_variant<R: VCFRecord>(record: &R) -> bool {
+    let is_sv = |x: &[u8]| is_symbolic_allele(x) || is_breakend_allele(x);
+    let alternative = record.alternative();
+    alternative.iter().any(|x| is_sv(x))
+        && alternative.iter().all(|x| is_sv(x) || x.as_ref() == b"*")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct BreakendMate {
+    /// `[` if the joined piece extends to the right of the mate position, `]` if it extends to the left.
+    pub bracket: u8,
+    pub chromosome: Vec<u8>,
+    /// one based position
+    pub position: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Breakend {
+    pub sequence: Vec<u8>,
+    /// `true` if the sequence is written before the mate (`t[p[`, `t]p]` or `t.`)
+    pub sequence_first: bool,
+    /// `None` for a single breakend
+    pub mate: Option<BreakendMate>,
+}
+
+impl Breakend {
+    pub fn parse(allele: &[u8]) -> Option<Breakend> {
+        if let Some(first) = allele.iter().position(|x| *x == b'[' || *x == b']') {
+            let bracket = allele[first];
+            let second = first + 1 + allele[first + 1..].iter().position(|x| *x == bracket)?;
+            let mate = &allele[first + 1..second];
+            let colon = mate.iter().rposition(|x| *x == b':')?;
+            let position = str::from_utf8(&mate[colon + 1..]).ok()?.parse().ok()?;
+            let (sequence, sequence_first) = if first > 0 {
+                if second + 1 != allele.len() {
+                    return None;
+                }
+                (&allele[..first], true)
+            } else {
+                (&allele[second + 1..], false)
+            };
+            if sequence.is_empty() {
+                return None;
+            }
+            Some(Breakend {
+                sequence: sequence.to_vec(),
+                sequence_first,
+                mate: Some(BreakendMate {
+                    bracket,
+                    chromosome: mate[..colon].to_vec(),
+                    position,
+                }),
+            })
+        } else if allele.len() > 1 && allele.ends_with(b".") {
+            Some(Breakend {
+                sequence: allele[..allele.len() - 1].to_vec(),
+                sequence_first: true,
+                mate: None,
+            })
+        } else if allele.len() > 1 && allele.starts_with(b".") {
+            Some(Breakend {
+                sequence: allele[1..].to_vec(),
+                sequence_first: false,
+                mate: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn format(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        if self.sequence_first {
+            result.extend_from_slice(&self.sequence);
+        }
+        match &self.mate {
+            Some(mate) => {
+                result.push(mate.bracket);
+                result.extend_from_slice(&mate.chromosome);
+                write!(result, ":{}", mate.position).unwrap();
+                result.push(mate.bracket);
+            }
+            None => result.push(b'.'),
+        }
+        if !self.sequence_first {
+            result.extend_from_slice(&self.sequence);
+        }
+        result
+    }
+
+    /// Create a breakend at a lifted position. The sequence is reverse complemented and joined
+    /// side is flipped if the position was lifted to reverse strand.
+    fn lift(&self, strand: Strand, reference: &[u8], mate: Option<BreakendMate>) -> Breakend {
+        let (mut sequence, sequence_first) = match strand {
+            Strand::Forward => (self.sequence.clone(), self.sequence_first),
+            Strand::Reverse => (reverse_complement(&self.sequence), !self.sequence_first),
+        };
+        if sequence_first {
+            sequence[0] = reference[0];
+        } else {
+            *sequence.last_mut().unwrap() = *reference.last().unwrap();
+        }
+        Breakend {
+            sequence,
+            sequence_first,
+            mate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct LiftedBase {
+    pub chromosome: String,
+    /// zero based position
+    pub position: u64,
+    pub strand: Strand,
+    pub chain_index: usize,
+}
+
+/// A base of an original record and its lifted position, which is checked after liftover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct CheckedBase<'b> {
+    pub original_chromosome: &'b str,
+    /// zero based position
+    pub original_position: u64,
+    pub lifted: &'b LiftedBase,
+    pub reference_changed: bool,
+}
+
+/// Result of checks of a lifted structural variant or reference block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct LiftCheck {
+    pub verified: bool,
+    pub confidence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LiftedMate {
+    mate: BreakendMate,
+    original_chromosome: String,
+    /// zero based position
+    original_position: u64,
+    lifted: LiftedBase,
+}
+
+pub(super) fn info_values<'b, 'a>(
+    record: &'b CompleteVCFRecord<'a>,
+    key: &[u8],
+) -> Option<&'b [Cow<'a, [u8]>]> {
+    record
+        .info
+        .iter()
+        .find(|x| x.0.as_ref() == key)
+        .map(|x| &x.1[..])
+}
+
+pub(super) fn parse_number<T: str::FromStr>(value: &[u8]) -> Option<T> {
+    str::from_utf8(value).ok()?.parse().ok()
+}
+
+fn info_interval(record: &CompleteVCFRecord, key: &[u8]) -> Option<(i64, i64)> {
+    match info_values(record, key)? {
+        [lower, upper] => Some((parse_number(lower)?, parse_number(upper)?)),
+        _ => None,
+    }
+}
+
+/// Replace values of an INFO tag, or remove the tag if `values` is `None`.
+pub(super) fn set_info(record: &mut CompleteVCFRecord, key: &[u8], values: Option<Vec<Cow<[u8]>>>) {
+    let values: Option<Vec<Cow<'static, [u8]>>> =
+        values.map(|x| x.into_iter().map(|y| Cow::Owned(y.into_owned())).collect());
+    match (record.info.iter().position(|x| x.0.as_ref() == key), values) {
+        (Some(index), Some(values)) => record.info[index].1 = values,
+        (Some(index), None) => {
+            record.info.remove(index);
+        }
+        (None, Some(values)) => record.info.push((Cow::Owned(key.to_vec()), values)),
+        (None, None) => (),
+    }
+}
+
+fn format_interval(interval: (i64, i64)) -> Vec<Cow<'static, [u8]>> {
+    vec![
+        Cow::Owned(interval.0.to_string().into_bytes()),
+        Cow::Owned(interval.1.to_string().into_bytes()),
+    ]
+}
+
+impl<G: GenomeSequence> VCFLiftOver<G> {
+    fn add_tested_base(&self, failure: &mut LiftOverFailure, base: &LiftedBase) {
+        failure.add_region(
+            &base.chromosome,
+            base.position,
+            base.position + 1,
+            Some(&self.variant_lift.position_liftover().chain_list()[base.chain_index].chain_id),
+        );
+    }
+
+    fn lift_base(&self, chromosome: &str, position: u64) -> Vec<LiftedBase> {
+        self.variant_lift
+            .position_liftover()
+            .lift_region(chromosome, position..(position + 1))
+            .into_iter()
+            .filter(|x| x.len() == 1)
+            .map(|x| LiftedBase {
+                chromosome: x.chromosome.name.to_string(),
+                position: x.start,
+                strand: x.strand,
+                chain_index: x.chain_index,
+            })
+            .collect()
+    }
+
+    /// Lift a confidence interval around `anchor`. Offsets are relative to `lifted`, the lifted
+    /// position of `anchor`. The original interval is kept if the window cannot be lifted.
+    fn lift_confidence_interval(
+        &self,
+        chromosome: &str,
+        anchor: u64,
+        interval: (i64, i64),
+        lifted: &LiftedBase,
+    ) -> (i64, i64) {
+        let window_start = (anchor as i64 + interval.0).max(0) as u64;
+        let window_end = ((anchor as i64 + interval.1 + 1).max(0) as u64).max(window_start + 1);
+        self.variant_lift
+            .position_liftover()
+            .lift_region(chromosome, window_start..window_end)
+            .into_iter()
+            .find(|x| x.chain_index == lifted.chain_index && !x.is_empty())
+            .map(|x| {
+                (
+                    x.start as i64 - lifted.position as i64,
+                    x.end as i64 - 1 - lifted.position as i64,
+                )
+            })
+            .unwrap_or(match lifted.strand {
+                Strand::Forward => interval,
+                Strand::Reverse => (-interval.1, -interval.0),
+            })
+    }
+
+    /// Lift a mate position of a breakend. A mate lifted with the same chain as `own` is preferred.
+    ///
+    /// A mate position is lifted in the same way as the mate record itself, so the breakend fails
+    /// if its mate record fails with NO_CHAIN or MULTIMAP.
+    fn lift_breakend_mate(
+        &self,
+        mate: &BreakendMate,
+        own: &LiftedBase,
+    ) -> Result<LiftedMate, &'static str> {
+        if mate.position == 0 {
+            return Err("BND_MATE_UNMAPPED");
+        }
+        let original_chromosome = self.original_chromosome_name(&mate.chromosome);
+        let mut candidates = self.lift_base(&original_chromosome, mate.position - 1);
+        if !self.param.do_not_prefer_cis_contig_when_multimap
+            && candidates
+                .iter()
+                .any(|x| x.chromosome == original_chromosome)
+        {
+            candidates.retain(|x| x.chromosome == original_chromosome);
+        }
+        if candidates.len() > 1 && !self.param.allow_multimap {
+            return Err("BND_MATE_MULTIMAP");
+        }
+        let lifted = candidates
+            .iter()
+            .find(|x| x.chain_index == own.chain_index)
+            .or_else(|| candidates.first())
+            .ok_or("BND_MATE_UNMAPPED")?;
+        Ok(LiftedMate {
+            mate: BreakendMate {
+                bracket: match (lifted.strand, mate.bracket) {
+                    (Strand::Forward, x) => x,
+                    (Strand::Reverse, b'[') => b']',
+                    (Strand::Reverse, _) => b'[',
+                },
+                chromosome: lifted.chromosome.as_bytes().to_vec(),
+                position: lifted.position + 1,
+            },
+            original_position: mate.position - 1,
+            original_chromosome,
+            lifted: lifted.clone(),
+        })
+    }
+
+    /// Check lifted bases of a structural variant or a reference block in the same way as other
+    /// variants. All bases have to be lifted back with the reverse chain, and the lowest
+    /// confidence score of the bases is used. `None` is returned if the candidate fails.
+    pub(super) fn check_lifted_bases(
+        &self,
+        bases: &[CheckedBase],
+        failure: &mut LiftOverFailure,
+    ) -> Option<LiftCheck> {
+        let verified = if let Some(reverse_variant_lift) = self.reverse_variant_lift.as_ref() {
+            bases.iter().all(|x| {
+                verify::position_returns_to_original(
+                    reverse_variant_lift.position_liftover(),
+                    x.original_chromosome,
+                    x.original_position,
+                    &x.lifted.chromosome,
+                    x.lifted.position,
+                )
+            })
+        } else {
+            true
+        };
+        if !verified && self.param.fail_reverse_lift_mismatch {
+            failure.add_reason(verify::REVERSE_LIFT_MISMATCH);
+            bases
+                .iter()
+                .for_each(|x| self.add_tested_base(failure, x.lifted));
+            return None;
+        }
+
+        let confidence = bases
+            .iter()
+            .map(|x| {
+                confidence::ConfidenceEvidence::collect_region(
+                    self.variant_lift.position_liftover(),
+                    x.original_chromosome,
+                    x.original_position..(x.original_position + 1),
+                    &x.lifted.chromosome,
+                    x.lifted.position,
+                    x.lifted.strand,
+                    x.reference_changed,
+                )
+                .score()
+            })
+            .min()
+            .unwrap_or(0);
+        if self
+            .param
+            .min_confidence
+            .is_some_and(|min_confidence| confidence < min_confidence)
+        {
+            failure.add_reason(confidence::LOW_CONFIDENCE);
+            bases
+                .iter()
+                .for_each(|x| self.add_tested_base(failure, x.lifted));
+            return None;
+        }
+
+        Some(LiftCheck {
+            verified,
+            confidence,
+        })
+    }
+
+    /// Add INFO tags of reverse liftOver, confidence and representation change. Length of
+    /// a symbolic allele is the only representation change checked in a structural variant and
+    /// a reference block.
+    pub(super) fn annotate_lifted_record(
+        &self,
+        record: &mut VCFRecordWrapper,
+        check: LiftCheck,
+        length_changed: bool,
+    ) {
+        if !check.verified {
+            verify::add_mismatch_info(record);
+        }
+        confidence::add_confidence_info(record, check.confidence);
+        if self.param.flag_representation_change && length_changed {
+            representation::add_representation_info(
+                record,
+                &[representation::ALLELE_LENGTH_CHANGED],
+                None,
+            );
+        }
+    }
+
+    /// Check a contig name and REF of a record with symbolic ALT alleles. REF `N` is not checked.
+    pub(super) fn check_original_reference(
+        &mut self,
+        record: &PartialVCFRecord,
+        original_chromosome: &str,
+        failure: &mut LiftOverFailure,
+    ) -> Result<bool, LiftOverError> {
+        if self
+            .variant_lift
+            .position_liftover()
+            .original_chromosome_by_name(original_chromosome)
+            .is_none()
+        {
+            failure.add_reason("UNKNOWN_SEQUENCE_NAME");
+            return Ok(false);
+        }
+
+        let start = record.position() - 1;
+        let reference = record.reference();
+        if reference != b"N" {
+            let expected_ref = self.variant_lift.original_sequence_mut().get_sequence(
+                original_chromosome,
+                start,
+                start + reference.len() as u64,
+            )?;
+            if expected_ref != reference {
+                failure.add_reason("UNEXPECTED_REF");
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Lift a structural variant. POS and END are lifted separately and both ends must be
+    /// lifted with the same chain. SVLEN, CIPOS and CIEND are recomputed from lifted positions,
+    /// and mate positions in breakend ALT alleles are lifted.
+    ///
+    /// Breakend records are lifted independently, and a mate position is checked in the same
+    /// way as the mate record. A breakend fails with BND_MATE_UNMAPPED or BND_MATE_MULTIMAP if
+    /// its mate record fails with NO_CHAIN or MULTIMAP, and bases of both mates are used for
+    /// the reverse liftOver and confidence checks, so MATEID of a lifted breakend does not refer
+    /// to a mate which failed for these reasons.
+    pub(super) fn lift_structural_variant<'a>(
+        &mut self,
+        record: &'a PartialVCFRecord,
+        original_chromosome: &str,
+        failure: &mut LiftOverFailure,
+    ) -> Result<Vec<Vec<VCFRecordWrapper<'a>>>, LiftOverError> {
+        if !self.check_original_reference(record, original_chromosome, failure)? {
+            return Ok(Vec::new());
+        }
+
+        let start = record.position() - 1;
+        let reference = record.reference();
+        let original_record = record.clone().complete_parse()?;
+        let is_breakend = record.alternative().iter().any(|x| is_breakend_allele(x));
+        let is_insertion = info_values(&original_record, b"SVTYPE")
+            .map(|x| x.iter().any(|y| y.as_ref() == b"INS"))
+            .unwrap_or(false)
+            || record.alternative().iter().all(|x| x.starts_with(b"<INS"));
+        let end = if is_breakend {
+            None
+        } else {
+            info_values(&original_record, b"END")
+                .and_then(|x| x.first())
+                .and_then(|x| parse_number::<u64>(x))
+        };
+        let svlen = info_values(&original_record, b"SVLEN");
+        let end_position = match end {
+            Some(end) => end.max(record.position()) - 1,
+            None if !is_breakend && !is_insertion => svlen
+                .and_then(|x| x.first())
+                .and_then(|x| parse_number::<i64>(x))
+                .map(|x| start + x.unsigned_abs())
+                .unwrap_or(start),
+            None => start,
+        };
+        let cipos = info_interval(&original_record, b"CIPOS");
+        let ciend = info_interval(&original_record, b"CIEND");
+
+        let start_candidates = self.lift_base(original_chromosome, start);
+        let end_candidates = if end_position != start {
+            self.lift_base(original_chromosome, end_position)
+        } else {
+            start_candidates.clone()
+        };
+
+        let mut succeeded_records = Vec::new();
+        'candidate: for lifted_start in start_candidates.iter() {
+            let lifted_end = if let Some(x) = end_candidates
+                .iter()
+                .find(|x| x.chain_index == lifted_start.chain_index)
+            {
+                x
+            } else {
+                self.add_tested_base(failure, lifted_start);
+                if end_candidates.is_empty() {
+                    failure.add_reason("SV_END_UNMAPPED");
+                } else if end_candidates.iter().any(|x| {
+                    x.chromosome == lifted_start.chromosome && x.strand != lifted_start.strand
+                }) {
+                    failure.add_reason("SV_ENDS_ON_DIFFERENT_STRANDS");
+                } else {
+                    failure.add_reason("SV_ENDS_ON_DIFFERENT_CHAINS");
+                }
+                continue;
+            };
+
+            // On reverse strand, the padding base is placed before the lifted END.
+            let reverse = !is_breakend && lifted_start.strand == Strand::Reverse;
+            let (new_start, new_end) = if reverse {
+                if lifted_end.position == 0 {
+                    failure.add_reason("SV_NO_PADDING_BASE");
+                    self.add_tested_base(failure, lifted_end);
+                    continue;
+                }
+                (lifted_end.position - 1, lifted_start.position)
+            } else {
+                (lifted_start.position, lifted_end.position + 1)
+            };
+
+            let new_reference = self.variant_lift.new_sequence_mut().get_sequence(
+                &lifted_start.chromosome,
+                new_start,
+                new_start + reference.len() as u64,
+            )?;
+
+            let mut alternative = Vec::new();
+            let mut lifted_mates = Vec::new();
+            for one in record.alternative() {
+                if let Some(breakend) = Breakend::parse(one) {
+                    let mate = if let Some(mate) = breakend.mate.as_ref() {
+                        match self.lift_breakend_mate(mate, lifted_start) {
+                            Ok(x) => {
+                                let mate = x.mate.clone();
+                                lifted_mates.push(x);
+                                Some(mate)
+                            }
+                            Err(reason) => {
+                                failure.add_reason(reason);
+                                self.add_tested_base(failure, lifted_start);
+                                continue 'candidate;
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    alternative.push(Cow::Owned(
+                        breakend
+                            .lift(lifted_start.strand, &new_reference, mate)
+                            .format(),
+                    ));
+                } else {
+                    alternative.push(one.clone());
+                }
+            }
+
+            let mut mate_reference_changed = Vec::new();
+            for one in lifted_mates.iter() {
+                let original_base = self.variant_lift.original_sequence_mut().get_sequence(
+                    &one.original_chromosome,
+                    one.original_position,
+                    one.original_position + 1,
+                )?;
+                let new_base = self.variant_lift.new_sequence_mut().get_sequence(
+                    &one.lifted.chromosome,
+                    one.lifted.position,
+                    one.lifted.position + 1,
+                )?;
+                mate_reference_changed.push(original_base != new_base);
+            }
+            let mut checked_bases = vec![CheckedBase {
+                original_chromosome,
+                original_position: start,
+                lifted: lifted_start,
+                reference_changed: new_reference != reference,
+            }];
+            if end_position != start {
+                checked_bases.push(CheckedBase {
+                    original_chromosome,
+                    original_position: end_position,
+                    lifted: lifted_end,
+                    reference_changed: false,
+                });
+            }
+            checked_bases.extend(lifted_mates.iter().zip(mate_reference_changed).map(
+                |(x, reference_changed)| CheckedBase {
+                    original_chromosome: &x.original_chromosome,
+                    original_position: x.original_position,
+                    lifted: &x.lifted,
+                    reference_changed,
+                },
+            ));
+            let check = if let Some(x) = self.check_lifted_bases(&checked_bases, failure) {
+                x
+            } else {
+                continue;
+            };
+
+            let mut new_record = original_record.clone();
+            new_record.contig = Cow::Owned(lifted_start.chromosome.as_bytes().to_vec());
+            new_record.position = new_start + 1;
+            new_record.reference = Cow::Owned(new_reference.clone());
+            new_record.alternative = alternative;
+
+            if end.is_some() {
+                set_info(
+                    &mut new_record,
+                    b"END",
+                    Some(vec![Cow::Owned(new_end.to_string().into_bytes())]),
+                );
+            }
+            if let Some(svlen) = svlen.filter(|_| !is_breakend && !is_insertion) {
+                let length = (new_end - new_start - 1) as i64;
+                let new_svlen = svlen
+                    .iter()
+                    .map(|x| match parse_number::<i64>(x) {
+                        Some(v) if v < 0 => Cow::Owned((-length).to_string().into_bytes()),
+                        Some(_) => Cow::Owned(length.to_string().into_bytes()),
+                        None => x.clone(),
+                    })
+                    .collect();
+                set_info(&mut new_record, b"SVLEN", Some(new_svlen));
+            }
+
+            let lifted_cipos = cipos.map(|x| {
+                self.lift_confidence_interval(original_chromosome, start, x, lifted_start)
+            });
+            let lifted_ciend = ciend.map(|x| {
+                self.lift_confidence_interval(original_chromosome, end_position, x, lifted_end)
+            });
+            let (new_cipos, new_ciend) = if reverse {
+                (lifted_ciend, lifted_cipos)
+            } else {
+                (lifted_cipos, lifted_ciend)
+            };
+            set_info(&mut new_record, b"CIPOS", new_cipos.map(format_interval));
+            set_info(&mut new_record, b"CIEND", new_ciend.map(format_interval));
+
+            new_record.info.push((
+                Cow::Owned(b"ORIGINAL_CHROM".to_vec()),
+                vec![Cow::Owned(record.contig().to_vec())],
+            ));
+            new_record.info.push((
+                Cow::Owned(b"ORIGINAL_POS".to_vec()),
+                vec![Cow::Owned(record.position().to_string().into_bytes())],
+            ));
+            new_record.info.push((
+                Cow::Owned(b"ORIGINAL_STRAND".to_vec()),
+                vec![Cow::Owned(lifted_start.strand.to_string().into_bytes())],
+            ));
+            if new_reference != reference {
+                new_record.info.push((
+                    Cow::Owned(b"ORIGINAL_REF".to_vec()),
+                    vec![Cow::Owned(reference.to_vec())],
+                ));
+            }
+
+            let length_changed =
+                !is_breakend && !is_insertion && end_position - start + 1 != new_end - new_start;
+            let mut new_record = VCFRecordWrapper::Complete(new_record);
+            self.annotate_lifted_record(&mut new_record, check, length_changed);
+            succeeded_records.push(vec![new_record]);
+        }
+
+        Ok(succeeded_records)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chain::ChainFile;
+    use crate::variantlift::VariantLiftOver;
+    use crate::vcflift::VCFLiftOverParameters;
+    use bio::io::fasta::IndexedReader;
+    use std::io::Cursor;
+
+    // original chr1:1-20 and 21-40 are lifted to new chr1:11-30 and 36-55 (5 bp insertion),
+    // chr1:51-70 to new chr2:1-20, and chr1:81-100 to reverse strand of new chr1:61-80.
+    const CHAIN: &[u8] = b"chain 100 chr1 100 + 0 40 chr1 80 + 10 55 1
+20\t0\t5
+20
+
+chain 100 chr1 100 + 50 70 chr2 20 + 0 20 2
+20
+
+chain 100 chr1 100 + 80 100 chr1 80 - 0 20 3
+20
+";
+    const ORIGINAL_FASTA: &[u8] = b">chr1
+GCTAAAGACAATTACATAACATACACGTCAGCACGAAACTTGTTGGCCCAGTGTGAATCGCTTAAGGGTTAAGTAAGTGTGATGCATACGCCTTTACTTG
+";
+    const ORIGINAL_FAI: &[u8] = b"chr1\t100\t6\t100\t101\n";
+    const NEW_FASTA: &[u8] = b">chr1
+CTGTGTCCACGCTAAAGACAATTACATAACGGGGGATACACGTCAGCACGAAACTTAATTCAAGTAAAGGCGTATGCATC
+>chr2
+GTGTGAATCGCTTAAGGGTT
+";
+    const NEW_FAI: &[u8] = b"chr1\t80\t6\t80\t81\nchr2\t20\t93\t20\t21\n";
+
+    // new chr2:1-19 is lifted back to wrong positions, original chr1:52-70
+    const REVERSE_CHAIN: &[u8] = b"chain 100 chr1 80 + 10 55 chr1 100 + 0 40 1
+20\t5\t0
+20
+
+chain 100 chr2 20 + 0 19 chr1 100 + 51 70 2
+19
+";
+
+    type SVLiftOver = VCFLiftOver<IndexedReader<Cursor<&'static [u8]>>>;
+
+    fn sv_liftover_with_param(
+        chain: &[u8],
+        param: VCFLiftOverParameters,
+    ) -> Result<SVLiftOver, LiftOverError> {
+        let variant_lift = VariantLiftOver::new(
+            ChainFile::load(chain)?,
+            IndexedReader::new(Cursor::new(ORIGINAL_FASTA), ORIGINAL_FAI)?,
+            IndexedReader::new(Cursor::new(NEW_FASTA), NEW_FAI)?,
+        );
+        Ok(VCFLiftOver::new(variant_lift, param))
+    }
+
+    fn sv_liftover() -> Result<SVLiftOver, LiftOverError> {
+        sv_liftover_with_param(CHAIN, VCFLiftOverParameters::new())
+    }
+
+    fn lift(
+        vcf_lift: &mut SVLiftOver,
+        line: &str,
+    ) -> Result<(Vec<String>, LiftOverFailure), LiftOverError> {
+        let line = format!("{}\n", line);
+        let record = PartialVCFRecord::parse_vcf(1, line.as_bytes())?;
+        let mut failure = LiftOverFailure::default();
+        let lifted = vcf_lift.lift_structural_variant(&record, "chr1", &mut failure)?;
+        let mut results = Vec::new();
+        for one in lifted.iter().flatten() {
+            let mut bytes = Vec::new();
+            one.write(&mut bytes)?;
+            results.push(String::from_utf8(bytes).unwrap().trim_end().to_string());
+        }
+        Ok((results, failure))
+    }
+
+    #[test]
+    fn test_lift_structural_variant() -> Result<(), LiftOverError> {
+        let mut vcf_lift = sv_liftover()?;
+
+        // END crosses the insertion in the chain, so SVLEN and CIEND are changed
+        let (lifted, failure) = lift(
+            &mut vcf_lift,
+            "chr1\t6\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=22;SVLEN=-16;CIPOS=-2,2;CIEND=-3,3",
+        )?;
+        assert!(failure.is_empty());
+        assert_eq!(
+            lifted,
+            vec!["chr1\t16\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=37;SVLEN=-21;CIPOS=-2,2;CIEND=-8,3;ORIGINAL_CHROM=chr1;ORIGINAL_POS=6;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=22"]
+        );
+
+        // the padding base is placed before END on reverse strand, and CIPOS and CIEND are swapped
+        let (lifted, failure) = lift(
+            &mut vcf_lift,
+            "chr1\t86\tdup\tA\t<DUP>\t.\t.\tSVTYPE=DUP;END=90;SVLEN=4;CIPOS=-1,2;CIEND=0,0",
+        )?;
+        assert!(failure.is_empty());
+        assert_eq!(
+            lifted,
+            vec!["chr1\t70\tdup\tG\t<DUP>\t.\t.\tSVTYPE=DUP;END=74;SVLEN=4;CIPOS=0,0;CIEND=-2,1;ORIGINAL_CHROM=chr1;ORIGINAL_POS=86;ORIGINAL_STRAND=-;ORIGINAL_REF=A;LIFT_CONFIDENCE=24"]
+        );
+
+        // mate position is lifted
+        let (lifted, failure) = lift(
+            &mut vcf_lift,
+            "chr1\t6\tbnd1\tA\tA[chr1:55[\t.\t.\tSVTYPE=BND;MATEID=bnd2",
+        )?;
+        assert!(failure.is_empty());
+        assert_eq!(
+            lifted,
+            vec!["chr1\t16\tbnd1\tA\tA[chr2:5[\t.\t.\tSVTYPE=BND;MATEID=bnd2;ORIGINAL_CHROM=chr1;ORIGINAL_POS=6;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=28"]
+        );
+
+        // a change of SV length is flagged
+        let mut vcf_lift = sv_liftover_with_param(
+            CHAIN,
+            VCFLiftOverParameters::new().flag_representation_change(true),
+        )?;
+        let (lifted, _) = lift(
+            &mut vcf_lift,
+            "chr1\t6\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=22",
+        )?;
+        assert_eq!(
+            lifted,
+            vec!["chr1\t16\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=37;ORIGINAL_CHROM=chr1;ORIGINAL_POS=6;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=22;REPRESENTATION_CHANGED=ALLELE_LENGTH_CHANGED"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_structural_variant_failure() -> Result<(), LiftOverError> {
+        let mut vcf_lift = sv_liftover()?;
+        for (line, reason) in [
+            (
+                "chr1\t6\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=45",
+                "SV_END_UNMAPPED",
+            ),
+            (
+                "chr1\t6\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=55",
+                "SV_ENDS_ON_DIFFERENT_CHAINS",
+            ),
+            (
+                "chr1\t6\tdel\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=86",
+                "SV_ENDS_ON_DIFFERENT_STRANDS",
+            ),
+            (
+                "chr1\t6\tbnd1\tA\tA[chr1:45[\t.\t.\tSVTYPE=BND;MATEID=bnd2",
+                "BND_MATE_UNMAPPED",
+            ),
+        ] {
+            let (lifted, failure) = lift(&mut vcf_lift, line)?;
+            assert!(lifted.is_empty(), "{}", line);
+            assert_eq!(failure.reasons, vec![reason], "{}", line);
+        }
+
+        // the mate of the breakend above is unmapped, and fails with NO_CHAIN in `lift_record`
+        let (lifted, failure) = lift(
+            &mut vcf_lift,
+            "chr1\t45\tbnd2\tG\t]chr1:6]G\t.\t.\tSVTYPE=BND;MATEID=bnd1",
+        )?;
+        assert!(lifted.is_empty());
+        assert!(failure.reasons.is_empty());
+
+        // no base is found before END on reverse strand
+        let mut vcf_lift = sv_liftover_with_param(
+            b"chain 100 chr1 100 + 80 100 chr1 80 - 60 80 3\n20\n",
+            VCFLiftOverParameters::new(),
+        )?;
+        let (lifted, failure) = lift(
+            &mut vcf_lift,
+            "chr1\t86\tdup\tA\t<DUP>\t.\t.\tSVTYPE=DUP;END=100",
+        )?;
+        assert!(lifted.is_empty());
+        assert_eq!(failure.reasons, vec!["SV_NO_PADDING_BASE"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_breakend_pair() -> Result<(), LiftOverError> {
+        const BND1: &str = "chr1\t6\tbnd1\tA\tA[chr1:55[\t.\t.\tSVTYPE=BND;MATEID=bnd2";
+        const BND2: &str = "chr1\t55\tbnd2\tG\t]chr1:6]G\t.\t.\tSVTYPE=BND;MATEID=bnd1";
+
+        // original chr1:51-70 is also lifted to new chr1:61-80
+        let chain = [
+            CHAIN,
+            b"\nchain 50 chr1 100 + 50 70 chr1 80 + 60 80 4\n20\n",
+        ]
+        .concat();
+
+        // a mate on the same contig is preferred in the same way as the mate record
+        let mut vcf_lift = sv_liftover_with_param(&chain, VCFLiftOverParameters::new())?;
+        let (lifted, failure) = lift(&mut vcf_lift, BND1)?;
+        assert!(failure.is_empty());
+        assert_eq!(
+            lifted,
+            vec!["chr1\t16\tbnd1\tA\tA[chr1:65[\t.\t.\tSVTYPE=BND;MATEID=bnd2;ORIGINAL_CHROM=chr1;ORIGINAL_POS=6;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=11"]
+        );
+
+        // the mate record fails with MULTIMAP, so the breakend also fails
+        let mut vcf_lift = sv_liftover_with_param(
+            &chain,
+            VCFLiftOverParameters::new().do_not_prefer_cis_contig_when_multimap(true),
+        )?;
+        let (lifted, failure) = lift(&mut vcf_lift, BND1)?;
+        assert!(lifted.is_empty());
+        assert_eq!(failure.reasons, vec!["BND_MATE_MULTIMAP"]);
+        let (lifted, _) = lift(&mut vcf_lift, BND2)?;
+        assert_eq!(lifted.len(), 2);
+
+        // both mates have the same confidence score
+        let mut vcf_lift = sv_liftover_with_param(CHAIN, VCFLiftOverParameters::new())?;
+        for line in [BND1, BND2] {
+            let (lifted, failure) = lift(&mut vcf_lift, line)?;
+            assert!(failure.is_empty());
+            assert_eq!(lifted.len(), 1);
+            assert!(lifted[0].ends_with(";LIFT_CONFIDENCE=28"), "{}", lifted[0]);
+        }
+        let mut vcf_lift =
+            sv_liftover_with_param(CHAIN, VCFLiftOverParameters::new().min_confidence(Some(29)))?;
+        for line in [BND1, BND2] {
+            let (lifted, failure) = lift(&mut vcf_lift, line)?;
+            assert!(lifted.is_empty());
+            assert_eq!(failure.reasons, vec!["LOW_CONFIDENCE"]);
+        }
+
+        // the mate of both records is not lifted back to the original position
+        for fail_reverse_lift_mismatch in [false, true] {
+            let reverse_lift = VariantLiftOver::new(
+                ChainFile::load(REVERSE_CHAIN)?,
+                IndexedReader::new(Cursor::new(NEW_FASTA), NEW_FAI)?,
+                IndexedReader::new(Cursor::new(ORIGINAL_FASTA), ORIGINAL_FAI)?,
+            );
+            let mut vcf_lift = sv_liftover_with_param(
+                CHAIN,
+                VCFLiftOverParameters::new().fail_reverse_lift_mismatch(fail_reverse_lift_mismatch),
+            )?
+            .verify_with_reverse_lift(reverse_lift);
+            for line in [BND1, BND2] {
+                let (lifted, failure) = lift(&mut vcf_lift, line)?;
+                if fail_reverse_lift_mismatch {
+                    assert!(lifted.is_empty());
+                    assert_eq!(failure.reasons, vec!["REVERSE_LIFT_MISMATCH"]);
+                } else {
+                    assert!(failure.is_empty());
+                    assert_eq!(lifted.len(), 1);
+                    assert!(
+                        lifted[0].contains(";REVERSE_LIFT_MISMATCH;"),
+                        "{}",
+                        lifted[0]
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_breakend_parse_and_format() {
+        for (allele, expected) in [
+            (
+                &b"G[chr2:321682["[..],
+                Breakend {
+                    sequence: b"G".to_vec(),
+                    sequence_first: true,
+                    mate: Some(BreakendMate {
+                        bracket: b'[',
+                        chromosome: b"chr2".to_vec(),
+                        position: 321682,
+                    }),
+                },
+            ),
+            (
+                &b"]HLA:A*01:01:01:01:100]TC"[..],
+                Breakend {
+                    sequence: b"TC".to_vec(),
+                    sequence_first: false,
+                    mate: Some(BreakendMate {
+                        bracket: b']',
+                        chromosome: b"HLA:A*01:01:01:01".to_vec(),
+                        position: 100,
+                    }),
+                },
+            ),
+            (
+                &b".A"[..],
+                Breakend {
+                    sequence: b"A".to_vec(),
+                    sequence_first: false,
+                    mate: None,
+                },
+            ),
+        ] {
+            let breakend = Breakend::parse(allele).unwrap();
+            assert_eq!(breakend, expected);
+            assert_eq!(breakend.format(), allele);
+        }
+
+        assert_eq!(Breakend::parse(b"<DEL>"), None);
+        assert_eq!(Breakend::parse(b"G[chr2:100"), None);
+        assert_eq!(Breakend::parse(b"[chr2:100["), None);
+    }
+
+    #[test]
+    fn test_breakend_lift() {
+        let breakend = Breakend::parse(b"GAC[chr2:100[").unwrap();
+        let mate = breakend.mate.clone();
+        assert_eq!(
+            breakend.lift(Strand::Forward, b"T", mate.clone()).format(),
+            b"TAC[chr2:100["
+        );
+        assert_eq!(
+            breakend.lift(Strand::Reverse, b"C", mate).format(),
+            b"[chr2:100[GTC"
+        );
+    }
+}
@@ -2,6 +2,7 @@ use super::*;
 use crate::chain::ChainFile;
 use bio::io::fasta::IndexedReader;
 use std::fs::File;
+use std::io::Cursor;
 use std::str;
 
 fn new_vcf_liftover(
@@ -18,11 +19,26 @@ fn new_vcf_liftover(
     ))
 }
 
+/// Remove `LIFT_CONFIDENCE` from a lifted record. Scores depend on gaps of left aligned chains,
+/// and they are tested with small chains in `confidence`, `gvcf` and `svlift`.
+fn without_confidence(record: &str) -> String {
+    match record.find(";LIFT_CONFIDENCE=") {
+        Some(start) => {
+            let end = record[start + 1..]
+                .find([';', '\t', '\n'])
+                .map(|x| x + start + 1)
+                .unwrap_or(record.len());
+            format!("{}{}", &record[..start], &record[end..])
+        }
+        None => record.to_string(),
+    }
+}
+
 #[test]
 fn test_header_lift() -> anyhow::Result<()> {
     let mut sample_header = &include_bytes!("testfiles/test-header.vcf")[..];
     let reader = VCFReader::new(&mut sample_header)?;
-    let vcf_lift = new_vcf_liftover(VCFLiftOverParameters::new())?;
+    let mut vcf_lift = new_vcf_liftover(VCFLiftOverParameters::new())?;
 
     let (_lifted_header, rewrite_target) = vcf_lift.lift_header(&reader.header)?;
 
@@ -66,6 +82,8 @@ fn test_header_lift() -> anyhow::Result<()> {
         .into_iter()
         .collect(),
         format_gt: false,
+        previous_lift_info: HashMap::new(),
+        allele_annotation: HashMap::new(),
     };
     assert_eq!(rewrite_target, expected);
 
@@ -92,10 +110,10 @@ fn test_lift_noswap_vcf() -> anyhow::Result<()> {
                 succeeded_records[0].write(&mut lifted_bytes)?;
                 assert_eq!(
                     str::from_utf8(&expected_bytes).unwrap(),
-                    str::from_utf8(&lifted_bytes).unwrap()
+                    without_confidence(str::from_utf8(&lifted_bytes).unwrap())
                 );
             }
-            VCFLiftOverResult::Failed(_) => panic!(),
+            VCFLiftOverResult::Failed(..) => panic!(),
         }
     }
 
@@ -122,12 +140,216 @@ fn test_lift_swap_vcf() -> anyhow::Result<()> {
                 succeeded_records[0].write(&mut lifted_bytes)?;
                 assert_eq!(
                     str::from_utf8(&expected_bytes).unwrap(),
-                    str::from_utf8(&lifted_bytes).unwrap()
+                    without_confidence(str::from_utf8(&lifted_bytes).unwrap())
                 );
             }
-            VCFLiftOverResult::Failed(_) => panic!(),
+            VCFLiftOverResult::Failed(..) => panic!(),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_lift_vcf_with_reverse_chain() -> anyhow::Result<()> {
+    let reverse_chain = ChainFile::load(
+        &include_bytes!("../../testfiles/genomes/chain/GRCh37-to-GRCh38.chr22.chain")[..],
+    )?;
+    let mut grch37 = IndexedReader::from_file(&"testfiles/genomes/GRCh37/GRCh37.chr22.genome.fa")?;
+    let mut grch38 = IndexedReader::from_file(&"testfiles/genomes/GRCh38/GRCh38.chr22.genome.fa")?;
+    let reverse_lift = VariantLiftOver::new(
+        reverse_chain.left_align(&mut grch37, &mut grch38)?,
+        grch37,
+        grch38,
+    );
+
+    let mut reader = VCFReader::new(&include_bytes!("testfiles/original.vcf")[..])?;
+    let mut vcf_lift =
+        new_vcf_liftover(VCFLiftOverParameters::new())?.verify_with_reverse_lift(reverse_lift);
+    let (lifted_header, rewrite_target) = vcf_lift.lift_header(&reader.header)?;
+    assert!(lifted_header
+        .header_items
+        .contains(&REVERSE_LIFT_MISMATCH_VCF_HEADER));
+
+    while let Some(record) = reader.next_record()? {
+        let is_snv =
+            record.reference().len() == 1 && record.alternative().iter().all(|x| x.len() == 1);
+        match vcf_lift.lift_record(&record, &rewrite_target)? {
+            VCFLiftOverResult::Succeeded(succeeded_records) => {
+                if is_snv {
+                    let mut lifted_bytes: Vec<u8> = Vec::new();
+                    succeeded_records[0].write(&mut lifted_bytes)?;
+                    assert!(!str::from_utf8(&lifted_bytes)
+                        .unwrap()
+                        .contains("REVERSE_LIFT_MISMATCH"));
+                }
+            }
+            VCFLiftOverResult::Failed(..) => panic!(),
         }
     }
 
     Ok(())
 }
+
+#[test]
+fn test_lift_vcf_reverse_lift_mismatch() -> anyhow::Result<()> {
+    const CHAIN: &[u8] = b"chain 100 chr1 20 + 0 20 chr1 20 + 0 20 1
+20
+";
+    // new chr1 is lifted back to original chr2
+    const WRONG_REVERSE_CHAIN: &[u8] = b"chain 100 chr1 20 + 0 20 chr2 20 + 0 20 1
+20
+";
+    const ORIGINAL_FASTA: &[u8] = b">chr1\nACGTAAAACGTACGTACGTA\n>chr2\nACGTAAAACGTACGTACGTA\n";
+    const ORIGINAL_FAI: &[u8] = b"chr1\t20\t6\t20\t21\nchr2\t20\t33\t20\t21\n";
+    const NEW_FASTA: &[u8] = b">chr1\nACGTAAAACGTACGTACGTA\n";
+    const NEW_FAI: &[u8] = b"chr1\t20\t6\t20\t21\n";
+    // the deletion is not left aligned
+    const VCF: &[u8] = b"##fileformat=VCFv4.2
+##contig=<ID=chr1,length=20>
+##contig=<ID=chr2,length=20>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t7\ta\tAA\tA\t.\t.\t.
+chr1\t10\tb\tG\tT\t.\t.\t.
+";
+
+    let lift =
+        |reverse_chain: &[u8], param: VCFLiftOverParameters| -> anyhow::Result<(String, String)> {
+            let variant_lift = VariantLiftOver::new(
+                ChainFile::load(CHAIN)?,
+                IndexedReader::new(Cursor::new(ORIGINAL_FASTA), ORIGINAL_FAI)?,
+                IndexedReader::new(Cursor::new(NEW_FASTA), NEW_FAI)?,
+            );
+            let reverse_lift = VariantLiftOver::new(
+                ChainFile::load(reverse_chain)?,
+                IndexedReader::new(Cursor::new(NEW_FASTA), NEW_FAI)?,
+                IndexedReader::new(Cursor::new(ORIGINAL_FASTA), ORIGINAL_FAI)?,
+            );
+            let mut vcf_lift =
+                VCFLiftOver::new(variant_lift, param).verify_with_reverse_lift(reverse_lift);
+            let mut success = Vec::new();
+            let mut failed = Vec::new();
+            vcf_lift.lift_vcf(VCF, &mut success, &mut failed, None::<File>, None::<File>)?;
+            let records = |vcf: Vec<u8>| {
+                String::from_utf8(vcf)
+                    .unwrap()
+                    .lines()
+                    .filter(|x| !x.starts_with('#'))
+                    .map(|x| {
+                        let columns: Vec<_> = x.split('\t').collect();
+                        [columns[0], columns[1], columns[3], columns[4], columns[7]].join(" ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            Ok((records(success), records(failed)))
+        };
+
+    let (success, failed) = lift(CHAIN, VCFLiftOverParameters::new())?;
+    assert_eq!(
+        success,
+        "chr1 4 TA T ORIGINAL_CHROM=chr1;ORIGINAL_POS=7;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=32
+chr1 10 G T ORIGINAL_CHROM=chr1;ORIGINAL_POS=10;ORIGINAL_STRAND=+;LIFT_CONFIDENCE=38"
+    );
+    assert_eq!(failed, "");
+
+    let (success, failed) = lift(WRONG_REVERSE_CHAIN, VCFLiftOverParameters::new())?;
+    assert_eq!(
+        success,
+        "chr1 4 TA T ORIGINAL_CHROM=chr1;ORIGINAL_POS=7;ORIGINAL_STRAND=+;REVERSE_LIFT_MISMATCH;LIFT_CONFIDENCE=32
+chr1 10 G T ORIGINAL_CHROM=chr1;ORIGINAL_POS=10;ORIGINAL_STRAND=+;REVERSE_LIFT_MISMATCH;LIFT_CONFIDENCE=38"
+    );
+    assert_eq!(failed, "");
+
+    let (success, failed) = lift(
+        WRONG_REVERSE_CHAIN,
+        VCFLiftOverParameters::new().fail_reverse_lift_mismatch(true),
+    )?;
+    assert_eq!(success, "");
+    assert_eq!(
+        failed,
+        "chr1 7 AA A FAILED_REASON=REVERSE_LIFT_MISMATCH;TESTED_CHROM=chr1;TESTED_START=4;TESTED_END=5;TESTED_CHAIN=.
+chr1 10 G T FAILED_REASON=REVERSE_LIFT_MISMATCH;TESTED_CHROM=chr1;TESTED_START=10;TESTED_END=10;TESTED_CHAIN=."
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_lift_vcf_collision_with_split() -> anyhow::Result<()> {
+    // original chr1 and chr2 are lifted to the same region of new chr1
+    const CHAIN: &[u8] = b"chain 100 chr1 20 + 0 20 chr1 20 + 0 20 1
+20
+
+chain 100 chr2 20 + 0 20 chr1 20 + 0 20 2
+20
+";
+    const ORIGINAL_FASTA: &[u8] = b">chr1\nACGTACGTACGTACGTACGT\n>chr2\nACGTACGTACGTACGTACGT\n";
+    const ORIGINAL_FAI: &[u8] = b"chr1\t20\t6\t20\t21\nchr2\t20\t33\t20\t21\n";
+    const NEW_FASTA: &[u8] = b">chr1\nACGTACGTACGTACGTACGT\n";
+    const NEW_FAI: &[u8] = b"chr1\t20\t6\t20\t21\n";
+    const VCF: &[u8] = b"##fileformat=VCFv4.2
+##contig=<ID=chr1,length=20>
+##contig=<ID=chr2,length=20>
+##INFO=<ID=AC,Number=A,Type=Integer,Description=\"Allele count\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t5\ta\tA\tG\t.\t.\tAC=1
+chr1\t5\ta\tA\tC\t.\t.\tAC=2
+chr1\t10\tb\tC\tT\t.\t.\tAC=3
+chr2\t5\tc\tA\tG\t.\t.\tAC=4
+";
+
+    let lift = |policy: CollisionPolicy| -> anyhow::Result<(String, String)> {
+        let variant_lift = VariantLiftOver::new(
+            ChainFile::load(CHAIN)?,
+            IndexedReader::new(Cursor::new(ORIGINAL_FASTA), ORIGINAL_FAI)?,
+            IndexedReader::new(Cursor::new(NEW_FASTA), NEW_FAI)?,
+        );
+        let mut vcf_lift = VCFLiftOver::new(
+            variant_lift,
+            VCFLiftOverParameters::new()
+                .merge_multi_allelic(true)
+                .split_multi_allelic(true)
+                .sort(true)
+                .collision_policy(Some(policy)),
+        );
+        let mut success = Vec::new();
+        let mut failed = Vec::new();
+        let summary =
+            vcf_lift.lift_vcf(VCF, &mut success, &mut failed, None::<File>, None::<File>)?;
+        let records = |vcf: Vec<u8>| {
+            String::from_utf8(vcf)
+                .unwrap()
+                .lines()
+                .filter(|x| !x.starts_with('#'))
+                .map(|x| x.split('\t').take(5).collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert_eq!(
+            summary.total.mapped + summary.total.unmapped,
+            3,
+            "{:?}",
+            policy
+        );
+        Ok((records(success), records(failed)))
+    };
+
+    // all lifted records of chr1:5 are removed with the collided A>G record
+    let (success, failed) = lift(CollisionPolicy::Fail)?;
+    assert_eq!(success, "chr1 10 b C T");
+    assert_eq!(failed, "chr1 5 a A G,C\nchr2 5 c A G");
+
+    let (success, failed) = lift(CollisionPolicy::KeepFirst)?;
+    assert_eq!(success, "chr1 5 a A G\nchr1 5 a A C\nchr1 10 b C T");
+    assert_eq!(failed, "chr2 5 c A G");
+
+    let (success, failed) = lift(CollisionPolicy::KeepAll)?;
+    assert_eq!(
+        success,
+        "chr1 5 a A G\nchr1 5 a A C\nchr1 5 c A G\nchr1 10 b C T"
+    );
+    assert_eq!(failed, "");
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Reciprocal liftOver verification. A lifted variant is lifted back with a reverse chain and
+//! compared with the original variant.
+
+use super::VCFRecordWrapper;
+use crate::poslift::PositionLiftOver;
+use crate::variantlift::{LiftedVariant, VariantLiftOver};
+use crate::{GenomeSequence, LiftOverError, Variant};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+pub(super) const REVERSE_LIFT_MISMATCH: &str = "REVERSE_LIFT_MISMATCH";
+
+/// All alleles of a lifted variant including the original reference allele.
+fn lifted_alleles(variant: &LiftedVariant) -> BTreeSet<&[u8]> {
+    let mut alleles: BTreeSet<&[u8]> = variant.alternative.iter().map(|x| &x[..]).collect();
+    alleles.insert(&variant.reference);
+    if variant.reference_changed {
+        alleles.insert(&variant.original_reference);
+    }
+    alleles
+}
+
+/// Returns true if `lifted` is lifted back to the position and the alleles of `original`.
+///
+/// Reverse lifted variants are left aligned in the original assembly, so `original` is also
+/// left aligned before comparison. `original` is compared as is if its reference allele does not
+/// match the original assembly.
+pub(super) fn returns_to_original<G: GenomeSequence>(
+    reverse_lift: &mut VariantLiftOver<G>,
+    original: &Variant,
+    lifted: &LiftedVariant,
+    acceptable_deletion: u64,
+    acceptable_insertion: u64,
+) -> Result<bool, LiftOverError> {
+    let lifted_variant = Variant {
+        chromosome: lifted.chromosome.clone(),
+        position: lifted.position,
+        reference: lifted.reference.clone(),
+        alternative: lifted_alleles(lifted)
+            .into_iter()
+            .filter(|x| *x != &lifted.reference[..])
+            .map(|x| x.to_vec())
+            .collect(),
+    };
+
+    let original = match original.normalize(reverse_lift.new_sequence_mut()) {
+        Ok(normalized) => normalized,
+        Err(LiftOverError::DifferentReference) => original.clone(),
+        Err(e) => return Err(e),
+    };
+    let mut original_alleles: BTreeSet<&[u8]> =
+        original.alternative.iter().map(|x| &x[..]).collect();
+    original_alleles.insert(&original.reference);
+
+    Ok(reverse_lift
+        .lift_variant(&lifted_variant, acceptable_deletion, acceptable_insertion)?
+        .iter()
+        .filter_map(|x| x.as_ref().ok())
+        .any(|x| {
+            x.chromosome == original.chromosome
+                && x.position == original.position
+                && lifted_alleles(x) == original_alleles
+        }))
+}
+
+/// Returns true if a base at `lifted_position` is lifted back to `original_position`.
+pub(super) fn position_returns_to_original(
+    reverse_position_lift: &PositionLiftOver,
+    original_chromosome: &str,
+    original_position: u64,
+    lifted_chromosome: &str,
+    lifted_position: u64,
+) -> bool {
+    reverse_position_lift
+        .lift_position(lifted_chromosome, lifted_position)
+        .iter()
+        .any(|x| x.chromosome.name == original_chromosome && x.position == original_position)
+}
+
+/// Add `REVERSE_LIFT_MISMATCH` flag into INFO column.
+pub(super) fn add_mismatch_info(record: &mut VCFRecordWrapper) {
+    match record {
+        VCFRecordWrapper::Partial(ref mut p) => {
+            if p.unparsed_info == &b"."[..] {
+                p.unparsed_info = Cow::Borrowed(b"");
+            } else {
+                p.unparsed_info.to_mut().push(b';');
+            }
+            p.unparsed_info
+                .to_mut()
+                .extend_from_slice(REVERSE_LIFT_MISMATCH.as_bytes());
+        }
+        VCFRecordWrapper::Complete(ref mut c) => {
+            c.info.push((
+                Cow::Owned(REVERSE_LIFT_MISMATCH.as_bytes().to_vec()),
+                vec![],
+            ));
+        }
+    }
+}
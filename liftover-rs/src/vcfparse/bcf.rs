@@ -0,0 +1,1118 @@
+//! BCF (version 2.2) reader and writer.
+//!
+//! Records are converted to and from `CompleteVCFRecord` with a dictionary created from
+//! `##FILTER`, `##INFO`, `##FORMAT` and `##contig` header lines.
+
+use super::{
+    CompleteVCFRecord, PartialVCFRecord, VCFHeader, VCFHeaderItem, VCFParseError, VCFReader,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::str;
+
+pub const BCF_MAGIC: &[u8] = b"BCF\x02\x02";
+
+const TYPE_NULL: u8 = 0;
+const TYPE_INT8: u8 = 1;
+const TYPE_INT16: u8 = 2;
+const TYPE_INT32: u8 = 3;
+const TYPE_FLOAT: u8 = 5;
+const TYPE_CHAR: u8 = 7;
+
+const FLOAT_MISSING: u32 = 0x7f80_0001;
+const FLOAT_END_OF_VECTOR: u32 = 0x7f80_0002;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BCFValueType {
+    Integer,
+    Float,
+    Flag,
+    Character,
+    String,
+}
+
+impl BCFValueType {
+    fn parse(value: &[u8]) -> Option<BCFValueType> {
+        match value {
+            b"Integer" => Some(BCFValueType::Integer),
+            b"Float" => Some(BCFValueType::Float),
+            b"Flag" => Some(BCFValueType::Flag),
+            b"Character" => Some(BCFValueType::Character),
+            b"String" => Some(BCFValueType::String),
+            _ => None,
+        }
+    }
+}
+
+/// Dictionaries of strings (FILTER, INFO and FORMAT IDs) and contigs.
+///
+/// `PASS` is always the first string. `IDX` attributes of header lines are used if found.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BCFHeaderDictionary {
+    strings: Vec<Vec<u8>>,
+    string_index: HashMap<Vec<u8>, usize>,
+    contigs: Vec<Vec<u8>>,
+    contig_index: HashMap<Vec<u8>, usize>,
+    info_types: HashMap<Vec<u8>, BCFValueType>,
+    format_types: HashMap<Vec<u8>, BCFValueType>,
+}
+
+fn add_dictionary_entry(
+    names: &mut Vec<Vec<u8>>,
+    index: &mut HashMap<Vec<u8>, usize>,
+    name: &[u8],
+    idx: Option<usize>,
+) {
+    if index.contains_key(name) {
+        return;
+    }
+    let idx = idx.unwrap_or(names.len());
+    if names.len() <= idx {
+        names.resize(idx + 1, Vec::new());
+    }
+    names[idx] = name.to_vec();
+    index.insert(name.to_vec(), idx);
+}
+
+impl BCFHeaderDictionary {
+    pub fn new(header: &VCFHeader) -> BCFHeaderDictionary {
+        let mut dictionary = BCFHeaderDictionary::default();
+        add_dictionary_entry(
+            &mut dictionary.strings,
+            &mut dictionary.string_index,
+            b"PASS",
+            None,
+        );
+        for item in header.header_items.iter() {
+            let id = if let Some(id) = item.detail.get(&b"ID"[..]) {
+                id
+            } else {
+                continue;
+            };
+            let idx = item
+                .detail
+                .get(&b"IDX"[..])
+                .and_then(|x| str::from_utf8(x).ok())
+                .and_then(|x| x.parse().ok());
+            let value_type = item
+                .detail
+                .get(&b"Type"[..])
+                .and_then(|x| BCFValueType::parse(x));
+            match &item.key[..] {
+                b"FILTER" | b"INFO" | b"FORMAT" => {
+                    add_dictionary_entry(
+                        &mut dictionary.strings,
+                        &mut dictionary.string_index,
+                        id,
+                        idx,
+                    );
+                    if let Some(value_type) = value_type {
+                        if item.key == b"INFO" {
+                            dictionary.info_types.insert(id.to_vec(), value_type);
+                        } else if item.key == b"FORMAT" {
+                            dictionary.format_types.insert(id.to_vec(), value_type);
+                        }
+                    }
+                }
+                b"contig" => add_dictionary_entry(
+                    &mut dictionary.contigs,
+                    &mut dictionary.contig_index,
+                    id,
+                    idx,
+                ),
+                _ => (),
+            }
+        }
+        dictionary
+    }
+
+    fn string(&self, index: i32) -> Result<&[u8], VCFParseError> {
+        self.strings
+            .get(index as usize)
+            .filter(|x| !x.is_empty())
+            .map(|x| &x[..])
+            .ok_or(VCFParseError::InvalidBCF("unknown string index"))
+    }
+
+    fn contig(&self, index: i32) -> Result<&[u8], VCFParseError> {
+        self.contigs
+            .get(index as usize)
+            .filter(|x| !x.is_empty())
+            .map(|x| &x[..])
+            .ok_or(VCFParseError::InvalidBCF("unknown contig index"))
+    }
+
+    fn string_index(&self, kind: &'static str, name: &[u8]) -> Result<i32, VCFParseError> {
+        self.string_index
+            .get(name)
+            .map(|x| *x as i32)
+            .ok_or_else(|| not_defined(kind, name))
+    }
+
+    fn contig_index(&self, name: &[u8]) -> Result<i32, VCFParseError> {
+        self.contig_index
+            .get(name)
+            .map(|x| *x as i32)
+            .ok_or_else(|| not_defined("contig", name))
+    }
+}
+
+fn not_defined(kind: &'static str, name: &[u8]) -> VCFParseError {
+    VCFParseError::NotDefinedInHeader {
+        kind,
+        name: String::from_utf8_lossy(name).to_string(),
+    }
+}
+
+/// Remove `IDX` attribute, which is only meaningful in BCF, from a header line.
+fn remove_idx(item: &mut VCFHeaderItem) {
+    if item.detail.remove(&b"IDX"[..]).is_none() {
+        return;
+    }
+    if let Some(start) = item.value.windows(5).position(|x| x == b",IDX=") {
+        let end = item.value[start + 1..]
+            .iter()
+            .position(|x| *x == b',' || *x == b'>')
+            .map(|x| x + start + 1)
+            .unwrap_or_else(|| item.value.len());
+        item.value.drain(start..end);
+    }
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<(VCFHeader, BCFHeaderDictionary), VCFParseError> {
+    let mut magic = [0u8; 5];
+    reader.read_exact(&mut magic)?;
+    if magic != BCF_MAGIC {
+        return Err(VCFParseError::InvalidBCF("unsupported BCF version"));
+    }
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let mut text = vec![0u8; u32::from_le_bytes(length) as usize];
+    reader.read_exact(&mut text)?;
+    while text.last() == Some(&0) {
+        text.pop();
+    }
+    if text.last() != Some(&b'\n') {
+        text.push(b'\n');
+    }
+
+    let mut header = VCFReader::new(&text[..])?.header;
+    let dictionary = BCFHeaderDictionary::new(&header);
+    header.header_items.iter_mut().for_each(remove_idx);
+    Ok((header, dictionary))
+}
+
+fn write_header<W: Write>(writer: &mut W, header: &VCFHeader) -> io::Result<()> {
+    let mut text = Vec::new();
+    header.write(&mut text)?;
+    text.push(0);
+    writer.write_all(BCF_MAGIC)?;
+    writer.write_all(&(text.len() as u32).to_le_bytes())?;
+    writer.write_all(&text)?;
+    Ok(())
+}
+
+/// Read a record. `None` is returned at the end of file.
+fn read_record<R: Read>(
+    reader: &mut R,
+    dictionary: &BCFHeaderDictionary,
+    samples: usize,
+    line: u32,
+    buffer: &mut Vec<u8>,
+) -> Result<Option<CompleteVCFRecord<'static>>, VCFParseError> {
+    let mut length = [0u8; 8];
+    let mut read_bytes = 0;
+    while read_bytes < length.len() {
+        match reader.read(&mut length[read_bytes..]) {
+            Ok(0) if read_bytes == 0 => return Ok(None),
+            Ok(0) => return Err(VCFParseError::InvalidBCF("truncated record")),
+            Ok(n) => read_bytes += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let shared_length = u32::from_le_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    let indiv_length = u32::from_le_bytes([length[4], length[5], length[6], length[7]]) as usize;
+    buffer.resize(shared_length + indiv_length, 0);
+    reader.read_exact(buffer)?;
+    let (shared, indiv) = buffer.split_at(shared_length);
+    decode_record(dictionary, samples, line, shared, indiv).map(Some)
+}
+
+struct RecordData<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RecordData<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], VCFParseError> {
+        if self.data.len() < length {
+            return Err(VCFParseError::InvalidBCF("truncated record"));
+        }
+        let (value, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn u32(&mut self) -> Result<u32, VCFParseError> {
+        let value = self.take(4)?;
+        Ok(u32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    /// Read a type descriptor, and return a type and the number of values.
+    fn type_descriptor(&mut self) -> Result<(u8, usize), VCFParseError> {
+        let descriptor = self.take(1)?[0];
+        let mut count = (descriptor >> 4) as usize;
+        if count == 15 {
+            count = self
+                .typed_integers()?
+                .first()
+                .copied()
+                .flatten()
+                .filter(|x| *x >= 0)
+                .ok_or(VCFParseError::InvalidBCF("invalid vector length"))?
+                as usize;
+        }
+        Ok((descriptor & 0x0f, count))
+    }
+
+    /// Read integers. Values after end of vector are dropped, and missing values are `None`.
+    fn integers(
+        &mut self,
+        value_type: u8,
+        count: usize,
+    ) -> Result<Vec<Option<i32>>, VCFParseError> {
+        let width = match value_type {
+            TYPE_INT8 => 1,
+            TYPE_INT16 => 2,
+            TYPE_INT32 => 4,
+            _ => return Err(VCFParseError::InvalidBCF("integer is expected")),
+        };
+        let data = self.take(width * count)?;
+        let mut values = Vec::new();
+        for one in data.chunks(width) {
+            let (value, missing) = match width {
+                1 => (one[0] as i8 as i32, i8::MIN as i32),
+                2 => (i16::from_le_bytes([one[0], one[1]]) as i32, i16::MIN as i32),
+                _ => (
+                    i32::from_le_bytes([one[0], one[1], one[2], one[3]]),
+                    i32::MIN,
+                ),
+            };
+            if value == missing + 1 {
+                break;
+            }
+            values.push(if value == missing { None } else { Some(value) });
+        }
+        Ok(values)
+    }
+
+    fn floats(&mut self, count: usize) -> Result<Vec<Option<f32>>, VCFParseError> {
+        let data = self.take(4 * count)?;
+        let mut values = Vec::new();
+        for one in data.chunks(4) {
+            let value = u32::from_le_bytes([one[0], one[1], one[2], one[3]]);
+            if value == FLOAT_END_OF_VECTOR {
+                break;
+            }
+            values.push(if value == FLOAT_MISSING {
+                None
+            } else {
+                Some(f32::from_bits(value))
+            });
+        }
+        Ok(values)
+    }
+
+    fn typed_integers(&mut self) -> Result<Vec<Option<i32>>, VCFParseError> {
+        let (value_type, count) = self.type_descriptor()?;
+        if value_type == TYPE_NULL {
+            return Ok(Vec::new());
+        }
+        self.integers(value_type, count)
+    }
+
+    fn typed_key(&mut self) -> Result<i32, VCFParseError> {
+        self.typed_integers()?
+            .first()
+            .copied()
+            .flatten()
+            .ok_or(VCFParseError::InvalidBCF("invalid key"))
+    }
+
+    fn typed_string(&mut self) -> Result<&'a [u8], VCFParseError> {
+        let (value_type, count) = self.type_descriptor()?;
+        if value_type != TYPE_CHAR && count != 0 {
+            return Err(VCFParseError::InvalidBCF("string is expected"));
+        }
+        Ok(trim_nul(self.take(count)?))
+    }
+
+    /// Read `count` values of `value_type` as VCF text values.
+    fn values(
+        &mut self,
+        value_type: u8,
+        count: usize,
+    ) -> Result<Vec<Cow<'static, [u8]>>, VCFParseError> {
+        let values = match value_type {
+            TYPE_NULL => return Ok(Vec::new()),
+            TYPE_INT8 | TYPE_INT16 | TYPE_INT32 => self
+                .integers(value_type, count)?
+                .into_iter()
+                .map(|x| match x {
+                    Some(x) => x.to_string().into_bytes(),
+                    None => b".".to_vec(),
+                })
+                .collect(),
+            TYPE_FLOAT => self
+                .floats(count)?
+                .into_iter()
+                .map(|x| match x {
+                    Some(x) => x.to_string().into_bytes(),
+                    None => b".".to_vec(),
+                })
+                .collect(),
+            TYPE_CHAR => self
+                .take(count)
+                .map(trim_nul)?
+                .split(|x| *x == b',')
+                .map(|x| x.to_vec())
+                .collect(),
+            _ => return Err(VCFParseError::InvalidBCF("unknown value type")),
+        };
+        Ok(missing_if_empty(values))
+    }
+
+    /// Read genotypes of a sample as VCF text.
+    fn genotype(&mut self, value_type: u8, count: usize) -> Result<Vec<u8>, VCFParseError> {
+        let mut genotype = Vec::new();
+        for (i, value) in self.integers(value_type, count)?.into_iter().enumerate() {
+            let value = value.unwrap_or(0);
+            if i != 0 {
+                genotype.push(if value & 1 == 1 { b'|' } else { b'/' });
+            }
+            if value >> 1 == 0 {
+                genotype.push(b'.');
+            } else {
+                genotype.extend_from_slice(((value >> 1) - 1).to_string().as_bytes());
+            }
+        }
+        if genotype.is_empty() {
+            genotype.push(b'.');
+        }
+        Ok(genotype)
+    }
+}
+
+fn trim_nul(value: &[u8]) -> &[u8] {
+    let length = value.iter().position(|x| *x == 0).unwrap_or(value.len());
+    &value[..length]
+}
+
+fn missing_if_empty(values: Vec<Vec<u8>>) -> Vec<Cow<'static, [u8]>> {
+    if values.is_empty() || (values.len() == 1 && values[0].is_empty()) {
+        vec![Cow::Borrowed(b".")]
+    } else {
+        values.into_iter().map(Cow::Owned).collect()
+    }
+}
+
+fn decode_record(
+    dictionary: &BCFHeaderDictionary,
+    samples: usize,
+    line: u32,
+    shared: &[u8],
+    indiv: &[u8],
+) -> Result<CompleteVCFRecord<'static>, VCFParseError> {
+    let mut data = RecordData { data: shared };
+    let contig = dictionary.contig(data.u32()? as i32)?.to_vec();
+    let position = data.u32()? as i32 as i64 + 1;
+    let _rlen = data.u32()?;
+    let qual = data.u32()?;
+    let allele_info = data.u32()?;
+    let format_sample = data.u32()?;
+    let (allele_count, info_count) = (allele_info >> 16, allele_info & 0xffff);
+    let (format_count, sample_count) = (format_sample >> 24, format_sample & 0xff_ffff);
+    if sample_count as usize != samples {
+        return Err(VCFParseError::InvalidBCF(
+            "number of samples is not matched with header",
+        ));
+    }
+
+    let id = data.typed_string()?;
+    let mut alleles = Vec::new();
+    for _ in 0..allele_count {
+        alleles.push(Cow::Owned(data.typed_string()?.to_vec()));
+    }
+    if alleles.is_empty() {
+        return Err(VCFParseError::InvalidBCF("no reference allele"));
+    }
+    let reference = alleles.remove(0);
+    if alleles.is_empty() {
+        alleles.push(Cow::Borrowed(&b"."[..]));
+    }
+
+    let mut filter = Vec::new();
+    for (i, one) in data.typed_integers()?.into_iter().flatten().enumerate() {
+        if i != 0 {
+            filter.push(b';');
+        }
+        filter.extend_from_slice(dictionary.string(one)?);
+    }
+    if filter.is_empty() {
+        filter.push(b'.');
+    }
+
+    let mut info = Vec::new();
+    for _ in 0..info_count {
+        let key = dictionary.string(data.typed_key()?)?.to_vec();
+        let (value_type, count) = data.type_descriptor()?;
+        let values = if count == 0 {
+            Vec::new()
+        } else {
+            data.values(value_type, count)?
+        };
+        info.push((Cow::Owned(key), values));
+    }
+
+    let mut format = Vec::new();
+    let mut call: Vec<Vec<Vec<Cow<[u8]>>>> = vec![Vec::new(); samples];
+    let mut data = RecordData { data: indiv };
+    for _ in 0..format_count {
+        let key = dictionary.string(data.typed_key()?)?;
+        let (value_type, count) = data.type_descriptor()?;
+        for one_sample in call.iter_mut() {
+            if key == b"GT" && value_type != TYPE_CHAR {
+                one_sample.push(vec![Cow::Owned(data.genotype(value_type, count)?)]);
+            } else {
+                one_sample.push(data.values(value_type, count)?);
+            }
+        }
+        format.push(Cow::Owned(key.to_vec()));
+    }
+
+    Ok(CompleteVCFRecord {
+        line,
+        contig: Cow::Owned(contig),
+        position: position as u64,
+        id: if id.is_empty() {
+            Cow::Borrowed(b".")
+        } else {
+            Cow::Owned(id.to_vec())
+        },
+        reference,
+        alternative: alleles,
+        qual: if qual == FLOAT_MISSING {
+            Cow::Borrowed(b".")
+        } else {
+            Cow::Owned(f32::from_bits(qual).to_string().into_bytes())
+        },
+        filter: Cow::Owned(filter),
+        info,
+        format,
+        call,
+    })
+}
+
+fn write_type_descriptor(buffer: &mut Vec<u8>, value_type: u8, count: usize) {
+    if count < 15 {
+        buffer.push(((count as u8) << 4) | value_type);
+    } else {
+        buffer.push(0xf0 | value_type);
+        write_typed_integers(buffer, &[Some(count as i32)]);
+    }
+}
+
+/// The smallest integer type to store values. Some of the smallest values are reserved for
+/// missing and end of vector.
+fn integer_type(values: &[Option<i32>]) -> u8 {
+    let (min, max) = values
+        .iter()
+        .flatten()
+        .fold((0, 0), |(min, max), x| (min.min(*x), max.max(*x)));
+    if min >= i8::MIN as i32 + 8 && max <= i8::MAX as i32 {
+        TYPE_INT8
+    } else if min >= i16::MIN as i32 + 8 && max <= i16::MAX as i32 {
+        TYPE_INT16
+    } else {
+        TYPE_INT32
+    }
+}
+
+/// Write `length` integers. Missing values are written for `None`, and the rest are filled with
+/// end of vector.
+fn write_integers(buffer: &mut Vec<u8>, value_type: u8, values: &[Option<i32>], length: usize) {
+    let missing = match value_type {
+        TYPE_INT8 => i8::MIN as i32,
+        TYPE_INT16 => i16::MIN as i32,
+        _ => i32::MIN,
+    };
+    for i in 0..length {
+        let value = match values.get(i) {
+            Some(Some(x)) => *x,
+            Some(None) => missing,
+            None => missing + 1,
+        };
+        match value_type {
+            TYPE_INT8 => buffer.push(value as i8 as u8),
+            TYPE_INT16 => buffer.extend_from_slice(&(value as i16).to_le_bytes()),
+            _ => buffer.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+fn write_floats(buffer: &mut Vec<u8>, values: &[Option<f32>], length: usize) {
+    for i in 0..length {
+        let value = match values.get(i) {
+            Some(Some(x)) => x.to_bits(),
+            Some(None) => FLOAT_MISSING,
+            None => FLOAT_END_OF_VECTOR,
+        };
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn write_typed_integers(buffer: &mut Vec<u8>, values: &[Option<i32>]) {
+    if values.is_empty() {
+        buffer.push(TYPE_NULL);
+        return;
+    }
+    let value_type = integer_type(values);
+    write_type_descriptor(buffer, value_type, values.len());
+    write_integers(buffer, value_type, values, values.len());
+}
+
+fn write_typed_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    write_type_descriptor(buffer, TYPE_CHAR, value.len());
+    buffer.extend_from_slice(value);
+}
+
+fn parse_integers(values: &[Cow<[u8]>]) -> Result<Vec<Option<i32>>, VCFParseError> {
+    values
+        .iter()
+        .map(|x| {
+            if x.as_ref() == b"." {
+                Ok(None)
+            } else {
+                Ok(Some(str::from_utf8(x)?.parse()?))
+            }
+        })
+        .collect()
+}
+
+fn parse_floats(values: &[Cow<[u8]>]) -> Result<Vec<Option<f32>>, VCFParseError> {
+    values
+        .iter()
+        .map(|x| {
+            if x.as_ref() == b"." {
+                Ok(None)
+            } else {
+                Ok(Some(str::from_utf8(x)?.parse()?))
+            }
+        })
+        .collect()
+}
+
+fn join_values(values: &[Cow<[u8]>]) -> Vec<u8> {
+    values.join(&b","[..])
+}
+
+/// Encode a genotype such as `0|1` or `./.`.
+fn parse_genotype(genotype: &[u8]) -> Result<Vec<Option<i32>>, VCFParseError> {
+    let mut values = Vec::new();
+    let mut phased = 0;
+    let mut rest = genotype;
+    loop {
+        let end = rest
+            .iter()
+            .position(|x| *x == b'/' || *x == b'|')
+            .unwrap_or(rest.len());
+        let allele = if &rest[..end] == b"." {
+            0
+        } else {
+            str::from_utf8(&rest[..end])?.parse::<i32>()? + 1
+        };
+        values.push(Some((allele << 1) | phased));
+        phased = match rest.get(end) {
+            Some(b'|') => 1,
+            Some(_) => 0,
+            None => break,
+        };
+        rest = &rest[end + 1..];
+    }
+    Ok(values)
+}
+
+fn encode_record(
+    dictionary: &BCFHeaderDictionary,
+    samples: usize,
+    record: &CompleteVCFRecord,
+    shared: &mut Vec<u8>,
+    indiv: &mut Vec<u8>,
+) -> Result<(), VCFParseError> {
+    shared.clear();
+    indiv.clear();
+
+    let position = record.position as i32 - 1;
+    let mut rlen = record.reference.len() as i32;
+    if let Some(end) = record.info.iter().find(|x| x.0.as_ref() == b"END") {
+        if let Some(Some(end)) = parse_integers(&end.1)?.first() {
+            rlen = end - position;
+        }
+    }
+    let alternative: &[Cow<[u8]>] =
+        if record.alternative.len() == 1 && record.alternative[0].as_ref() == b"." {
+            &[]
+        } else {
+            &record.alternative
+        };
+
+    shared.extend_from_slice(&dictionary.contig_index(&record.contig)?.to_le_bytes());
+    shared.extend_from_slice(&position.to_le_bytes());
+    shared.extend_from_slice(&rlen.to_le_bytes());
+    let qual = match record.qual.as_ref() {
+        b"." | b"" => FLOAT_MISSING,
+        qual => str::from_utf8(qual)?.parse::<f32>()?.to_bits(),
+    };
+    shared.extend_from_slice(&qual.to_le_bytes());
+    shared.extend_from_slice(
+        &((((alternative.len() + 1) as u32) << 16) | record.info.len() as u32).to_le_bytes(),
+    );
+    shared
+        .extend_from_slice(&(((record.format.len() as u32) << 24) | samples as u32).to_le_bytes());
+
+    write_typed_string(
+        shared,
+        if record.id.as_ref() == b"." {
+            b""
+        } else {
+            &record.id
+        },
+    );
+    write_typed_string(shared, &record.reference);
+    for one in alternative {
+        write_typed_string(shared, one);
+    }
+    let filter = match record.filter.as_ref() {
+        b"." | b"" => Vec::new(),
+        filter => filter
+            .split(|x| *x == b';')
+            .map(|x| dictionary.string_index("FILTER", x).map(Some))
+            .collect::<Result<_, _>>()?,
+    };
+    write_typed_integers(shared, &filter);
+
+    for (key, values) in record.info.iter() {
+        write_typed_integers(shared, &[Some(dictionary.string_index("INFO", key)?)]);
+        match dictionary.info_types.get(key.as_ref()) {
+            Some(BCFValueType::Flag) => shared.push(TYPE_NULL),
+            Some(BCFValueType::Integer) => write_typed_integers(shared, &parse_integers(values)?),
+            Some(BCFValueType::Float) => {
+                let values = parse_floats(values)?;
+                write_type_descriptor(shared, TYPE_FLOAT, values.len());
+                write_floats(shared, &values, values.len());
+            }
+            Some(BCFValueType::Character) | Some(BCFValueType::String) => {
+                write_typed_string(shared, &join_values(values))
+            }
+            None => return Err(not_defined("INFO", key)),
+        }
+    }
+
+    let missing: Vec<Cow<[u8]>> = vec![Cow::Borrowed(b".")];
+    for (i, key) in record.format.iter().enumerate() {
+        write_typed_integers(indiv, &[Some(dictionary.string_index("FORMAT", key)?)]);
+        let values: Vec<&[Cow<[u8]>]> = (0..samples)
+            .map(|x| {
+                record
+                    .call
+                    .get(x)
+                    .and_then(|y| y.get(i))
+                    .map(|y| &y[..])
+                    .unwrap_or(&missing[..])
+            })
+            .collect();
+        let value_type = if key.as_ref() == b"GT" {
+            BCFValueType::Integer
+        } else {
+            *dictionary
+                .format_types
+                .get(key.as_ref())
+                .ok_or_else(|| not_defined("FORMAT", key))?
+        };
+        match value_type {
+            BCFValueType::Integer => {
+                let values = values
+                    .iter()
+                    .map(|x| {
+                        if key.as_ref() == b"GT" {
+                            parse_genotype(&join_values(x))
+                        } else {
+                            parse_integers(x)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let length = values.iter().map(|x| x.len()).max().unwrap_or(0);
+                let value_type = integer_type(&values.concat());
+                write_type_descriptor(indiv, value_type, length);
+                for one in values.iter() {
+                    write_integers(indiv, value_type, one, length);
+                }
+            }
+            BCFValueType::Float => {
+                let values = values
+                    .iter()
+                    .map(|x| parse_floats(x))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let length = values.iter().map(|x| x.len()).max().unwrap_or(0);
+                write_type_descriptor(indiv, TYPE_FLOAT, length);
+                for one in values.iter() {
+                    write_floats(indiv, one, length);
+                }
+            }
+            BCFValueType::Character | BCFValueType::String => {
+                let values: Vec<_> = values.iter().map(|x| join_values(x)).collect();
+                let length = values.iter().map(|x| x.len()).max().unwrap_or(0);
+                write_type_descriptor(indiv, TYPE_CHAR, length);
+                for one in values.iter() {
+                    indiv.extend_from_slice(one);
+                    indiv.resize(indiv.len() + length - one.len(), 0);
+                }
+            }
+            BCFValueType::Flag => return Err(VCFParseError::InvalidBCF("flag in FORMAT")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Read records of a BCF file as `CompleteVCFRecord`.
+///
+/// Use `VCFReader::from_bcf` to lift or normalize records of a BCF file.
+pub struct BCFReader<R: Read> {
+    reader: R,
+    pub header: VCFHeader,
+    pub dictionary: BCFHeaderDictionary,
+    current_record: u32,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> BCFReader<io::BufReader<R>> {
+    pub fn new(reader: R) -> Result<BCFReader<io::BufReader<R>>, VCFParseError> {
+        BCFReader::from_reader(io::BufReader::new(reader))
+    }
+}
+
+impl<R: Read> BCFReader<R> {
+    /// Create a reader without wrapping `reader` with `BufReader`.
+    pub fn from_reader(mut reader: R) -> Result<BCFReader<R>, VCFParseError> {
+        let (header, dictionary) = read_header(&mut reader)?;
+        Ok(BCFReader {
+            reader,
+            header,
+            dictionary,
+            current_record: 0,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn next_record(&mut self) -> Result<Option<CompleteVCFRecord<'static>>, VCFParseError> {
+        self.current_record += 1;
+        read_record(
+            &mut self.reader,
+            &self.dictionary,
+            self.header.samples.len(),
+            self.current_record,
+            &mut self.buffer,
+        )
+    }
+}
+
+/// Write records as uncompressed BCF. Use a BGZF writer to create `.bcf` files.
+pub struct BCFWriter<W: Write> {
+    pub header: VCFHeader,
+    dictionary: BCFHeaderDictionary,
+    writer: W,
+    shared: Vec<u8>,
+    indiv: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BCFWriter<W> {
+    pub fn new(mut writer: W, header: VCFHeader) -> Result<BCFWriter<W>, VCFParseError> {
+        write_header(&mut writer, &header)?;
+        Ok(BCFWriter {
+            dictionary: BCFHeaderDictionary::new(&header),
+            header,
+            writer,
+            shared: Vec::new(),
+            indiv: Vec::new(),
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn write_record<R: super::VCFRecord>(&mut self, record: &R) -> Result<(), VCFParseError> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        record.write(&mut buffer)?;
+        let result = PartialVCFRecord::parse_vcf(0, &buffer)
+            .and_then(|x| x.complete_parse())
+            .and_then(|x| self.write_complete_record(&x));
+        self.buffer = buffer;
+        result
+    }
+
+    pub fn write_complete_record(
+        &mut self,
+        record: &CompleteVCFRecord,
+    ) -> Result<(), VCFParseError> {
+        encode_record(
+            &self.dictionary,
+            self.header.samples.len(),
+            record,
+            &mut self.shared,
+            &mut self.indiv,
+        )?;
+        self.writer
+            .write_all(&(self.shared.len() as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&(self.indiv.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&self.shared)?;
+        self.writer.write_all(&self.indiv)?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// A writer to convert VCF text into BCF.
+///
+/// Header lines are buffered until `#CHROM` line is written, and each record line is encoded
+/// when a line break is written. All contigs, FILTER, INFO and FORMAT should be defined in the
+/// header.
+pub struct BCFTextWriter<W: Write> {
+    writer: Option<W>,
+    bcf_writer: Option<BCFWriter<W>>,
+    header: Vec<u8>,
+    partial_line: Vec<u8>,
+    current_line: u32,
+}
+
+fn into_io_error(e: VCFParseError) -> io::Error {
+    match e {
+        VCFParseError::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+impl<W: Write> BCFTextWriter<W> {
+    pub fn new(writer: W) -> BCFTextWriter<W> {
+        BCFTextWriter {
+            writer: Some(writer),
+            bcf_writer: None,
+            header: Vec::new(),
+            partial_line: Vec::new(),
+            current_line: 0,
+        }
+    }
+
+    fn push_line(&mut self, line: &[u8]) -> Result<(), VCFParseError> {
+        self.current_line += 1;
+        if let Some(bcf_writer) = self.bcf_writer.as_mut() {
+            let record = PartialVCFRecord::parse_vcf(self.current_line, line)?.complete_parse()?;
+            return bcf_writer.write_complete_record(&record);
+        }
+
+        self.header.extend_from_slice(line);
+        if line.starts_with(b"#CHROM") {
+            let header = VCFReader::new(&self.header[..])?.header;
+            let writer = self.writer.take().expect("BCF header was already written");
+            self.bcf_writer = Some(BCFWriter::new(writer, header)?);
+        } else if !line.starts_with(b"##") {
+            return Err(VCFParseError::HeaderParseError {
+                line: self.current_line,
+                column: 0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Return an inner writer. An error is returned if a header was not completed.
+    pub fn into_inner(self) -> io::Result<W> {
+        if !self.partial_line.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "VCF line is not terminated",
+            ));
+        }
+        self.bcf_writer.map(|x| x.into_inner()).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "VCF header is not completed")
+        })
+    }
+}
+
+impl<W: Write> Write for BCFTextWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(index) = rest.iter().position(|x| *x == b'\n') {
+            let mut line = std::mem::take(&mut self.partial_line);
+            line.extend_from_slice(&rest[..=index]);
+            self.push_line(&line).map_err(into_io_error)?;
+            rest = &rest[index + 1..];
+        }
+        self.partial_line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match (self.bcf_writer.as_mut(), self.writer.as_mut()) {
+            (Some(writer), _) => writer.writer.flush(),
+            (None, Some(writer)) => writer.flush(),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vcfparse::VCFRecord;
+
+    const HEADER: &[u8] = b"##fileformat=VCFv4.2
+##FILTER=<ID=PASS,Description=\"All filters passed\">
+##FILTER=<ID=q10,Description=\"Quality below 10\">
+##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">
+##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">
+##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP\">
+##INFO=<ID=GENE,Number=.,Type=String,Description=\"Gene\">
+##INFO=<ID=END,Number=1,Type=Integer,Description=\"End position\">
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+##FORMAT=<ID=AD,Number=R,Type=Integer,Description=\"Allele depth\">
+##FORMAT=<ID=GL,Number=G,Type=Float,Description=\"Genotype likelihood\">
+##FORMAT=<ID=FT,Number=1,Type=String,Description=\"Filter\">
+##contig=<ID=chr1,length=248956422>
+##contig=<ID=chr2,length=242193529>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2
+";
+
+    const RECORDS: &[u8] = b"chr1\t100\trs1\tA\tG,T\t30\tPASS\tDP=10;AF=0.5,0.25;DB;GENE=VERY_LONG_GENE_NAME,X\tGT:AD:GL:FT\t0|1:1,2,3:-0.5,.,-1:PASS\t1/2:100000,.,3:-2,-3,-4,-5,-6,-7:q10
+chr1\t200\t.\tAC\tA\t.\t.\tDP=-300\tGT:AD\t./.:.\t2:40000,5
+chr2\t300\t.\tN\t.\t12.5\tq10\tEND=400\tGT\t0/0\t0|0
+chr2\t400\t.\tT\t<DEL>\t.\tPASS\t.\tGT\t.\t1
+";
+
+    #[test]
+    fn test_encode_record() -> Result<(), VCFParseError> {
+        let header = VCFReader::new(HEADER)?.header;
+        let dictionary = BCFHeaderDictionary::new(&header);
+        let record = PartialVCFRecord::parse_vcf(1, b"chr1\t100\trs1\tA\tG\t30\tPASS\tDP=10;DB\n")?
+            .complete_parse()?;
+        let mut shared = Vec::new();
+        let mut indiv = Vec::new();
+        encode_record(&dictionary, 0, &record, &mut shared, &mut indiv)?;
+        assert_eq!(
+            shared,
+            vec![
+                0, 0, 0, 0, // CHROM
+                99, 0, 0, 0, // POS
+                1, 0, 0, 0, // rlen
+                0, 0, 0xf0, 0x41, // QUAL
+                2, 0, 2, 0, // n_info, n_allele
+                0, 0, 0, 0, // n_sample, n_fmt
+                0x37, b'r', b's', b'1', // ID
+                0x17, b'A', 0x17, b'G', // alleles
+                0x11, 0, // FILTER
+                0x11, 2, 0x11, 10, // DP=10
+                0x11, 4, 0x00, // DB
+            ]
+        );
+        assert!(indiv.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bcf_roundtrip() -> Result<(), VCFParseError> {
+        let mut writer = BCFTextWriter::new(Vec::new());
+        writer.write_all(HEADER)?;
+        writer.write_all(RECORDS)?;
+        let bcf = writer.into_inner()?;
+        assert!(bcf.starts_with(BCF_MAGIC));
+
+        let mut reader = VCFReader::new(&bcf[..])?;
+        let header = VCFReader::new(HEADER)?.header;
+        assert_eq!(reader.header.header_items, header.header_items);
+        assert_eq!(reader.header.samples, header.samples);
+        let mut text = Vec::new();
+        let mut lines = RECORDS.split_inclusive(|x| *x == b'\n');
+        while let Some(record) = reader.next_record()? {
+            let line = lines.next().unwrap();
+            assert_eq!(record, PartialVCFRecord::parse_vcf(record.line, line)?);
+            record.write(&mut text)?;
+        }
+        assert_eq!(str::from_utf8(&text)?, str::from_utf8(RECORDS)?);
+
+        let mut reader = BCFReader::new(&bcf[..])?;
+        let record = reader.next_record()?.unwrap();
+        assert_eq!(record.position, 100);
+        assert_eq!(
+            record.call[1][1],
+            vec![
+                Cow::Borrowed(&b"100000"[..]),
+                Cow::Borrowed(&b"."[..]),
+                Cow::Borrowed(&b"3"[..])
+            ]
+        );
+        assert_eq!(reader.next_record()?.unwrap().position, 200);
+        assert_eq!(reader.next_record()?.unwrap().position, 300);
+        assert_eq!(reader.next_record()?.unwrap().position, 400);
+        assert_eq!(reader.next_record()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undefined_contig() -> Result<(), VCFParseError> {
+        let mut writer = BCFTextWriter::new(Vec::new());
+        writer.write_all(HEADER)?;
+        let error = writer
+            .write_all(b"chr3\t100\t.\tA\tG\t.\t.\t.\tGT\t0/1\t0/0\n")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "contig chr3 is not defined in header");
+        Ok(())
+    }
+
+    #[test]
+    fn test_dictionary_idx() -> Result<(), VCFParseError> {
+        let mut item = VCFHeaderItem::parse(
+            b"##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\",IDX=3>\n",
+            1,
+        )?;
+        let header = VCFHeader {
+            header_items: vec![
+                VCFHeaderItem::parse(b"##FILTER=<ID=PASS,Description=\"All\",IDX=0>\n", 1)?,
+                item.clone(),
+                VCFHeaderItem::parse(
+                    b"##INFO=<ID=AF,Number=A,Type=Float,Description=\"AF\">\n",
+                    1,
+                )?,
+                VCFHeaderItem::parse(b"##contig=<ID=chr1,IDX=1>\n", 1)?,
+            ],
+            samples: Vec::new(),
+        };
+        let dictionary = BCFHeaderDictionary::new(&header);
+        assert_eq!(dictionary.string_index("INFO", b"PASS")?, 0);
+        assert_eq!(dictionary.string_index("INFO", b"DP")?, 3);
+        assert_eq!(dictionary.string_index("INFO", b"AF")?, 4);
+        assert_eq!(dictionary.contig(1)?, b"chr1");
+        assert!(dictionary.contig(0).is_err());
+
+        remove_idx(&mut item);
+        assert_eq!(
+            str::from_utf8(&item.value)?,
+            "<ID=DP,Number=1,Type=Integer,Description=\"Depth\">"
+        );
+        assert_eq!(item.detail.get(&b"IDX"[..]), None);
+        Ok(())
+    }
+}
@@ -69,6 +69,9 @@ impl<'a> VCFRecord for CompleteVCFRecord<'a> {
 
         if should_write_info {
             writer.write_all(b"\t")?;
+            if self.info.is_empty() {
+                writer.write_all(b".")?;
+            }
             for (i, one) in self.info.iter().enumerate() {
                 if i != 0 {
                     writer.write_all(b";")?;
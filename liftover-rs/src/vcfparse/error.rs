@@ -21,6 +21,10 @@ pub enum VCFParseError {
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("parse error")]
     ParseError,
+    #[error("{kind} {name} is not defined in header")]
+    NotDefinedInHeader { kind: &'static str, name: String },
+    #[error("Invalid BCF: {0}")]
+    InvalidBCF(&'static str),
 }
 
 impl From<nom::Err<(&[u8], nom::error::ErrorKind)>> for VCFParseError {
@@ -1,9 +1,11 @@
+mod bcf;
 mod completerecord;
 mod error;
 mod partialrecord;
 mod reader;
 mod writer;
 
+pub use bcf::{BCFHeaderDictionary, BCFReader, BCFTextWriter, BCFValueType, BCFWriter, BCF_MAGIC};
 pub use completerecord::CompleteVCFRecord;
 pub use error::{as_header_error, as_record_error, VCFParseError};
 pub use partialrecord::PartialVCFRecord;
@@ -140,5 +142,25 @@ pub struct VCFHeader {
     pub samples: Vec<Vec<u8>>,
 }
 
+impl VCFHeader {
+    /// Write header lines and `#CHROM` line.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for one_item in self.header_items.iter() {
+            one_item.write(writer)?;
+        }
+        if self.samples.is_empty() {
+            writer.write_all(b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+        } else {
+            writer.write_all(b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT")?;
+            for one in self.samples.iter() {
+                writer.write_all(b"\t")?;
+                writer.write_all(one)?;
+            }
+        }
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test;
@@ -140,6 +140,36 @@ impl<'a> PartialVCFRecord<'a> {
         }
     }
 
+    /// Create a record from a decoded record without parsing VCF text again. VCF text of the
+    /// record is written into `buffer` to be used as `raw`.
+    pub fn from_complete(
+        record: CompleteVCFRecord<'a>,
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<PartialVCFRecord<'a>, VCFParseError> {
+        buffer.clear();
+        record.write(buffer)?;
+        let raw = &buffer[..];
+        // INFO is the 8th column, and FORMAT and calls follow it
+        let mut columns = raw[..raw.len() - 1].splitn(9, |x| *x == b'\t').skip(7);
+        let unparsed_info = columns.next().unwrap_or(&[]);
+        let other = columns.next().unwrap_or(&[]);
+
+        Ok(PartialVCFRecord {
+            raw,
+            line: record.line,
+            contig: record.contig,
+            position: record.position,
+            id: record.id,
+            reference: record.reference,
+            alternative: record.alternative,
+            qual: record.qual,
+            filter: record.filter,
+            original_unparsed_info: unparsed_info,
+            unparsed_info: Cow::Borrowed(unparsed_info),
+            other,
+        })
+    }
+
     pub fn complete_parse(self) -> Result<CompleteVCFRecord<'a>, VCFParseError> {
         let raw = self.raw;
         let line = self.line;
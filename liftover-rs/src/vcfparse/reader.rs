@@ -1,18 +1,29 @@
+use super::bcf::{BCFReader, BCF_MAGIC};
 use super::{as_header_error, PartialVCFRecord, VCFHeader, VCFHeaderItem, VCFParseError};
 use nom::bytes::complete::{tag, take_till};
 use nom::multi::many0;
 use std::io::{self, BufRead};
 
+enum VCFInput<R: BufRead> {
+    Text(R),
+    Binary(Box<BCFReader<R>>),
+}
+
 pub struct VCFReader<R: BufRead> {
-    reader: R,
+    input: VCFInput<R>,
     pub header: VCFHeader,
     current_line: u32,
     buffer: Vec<u8>,
 }
 
 impl<R: io::Read> VCFReader<io::BufReader<R>> {
+    /// Create a reader for VCF or BCF. BCF is detected by its magic.
     pub fn new(reader: R) -> Result<VCFReader<io::BufReader<R>>, VCFParseError> {
         let mut reader = io::BufReader::new(reader);
+        if reader.fill_buf()?.starts_with(BCF_MAGIC) {
+            return Ok(VCFReader::from_bcf(BCFReader::from_reader(reader)?));
+        }
+
         let mut current_line = 0;
         let mut header_items = Vec::new();
         let mut samples = Vec::new();
@@ -44,7 +55,7 @@ impl<R: io::Read> VCFReader<io::BufReader<R>> {
         }
 
         Ok(VCFReader {
-            reader,
+            input: VCFInput::Text(reader),
             header: VCFHeader {
                 header_items,
                 samples,
@@ -53,15 +64,39 @@ impl<R: io::Read> VCFReader<io::BufReader<R>> {
             buffer: Vec::new(),
         })
     }
+}
+
+impl<R: BufRead> VCFReader<R> {
+    /// Read records from a BCF reader. Typed records are not parsed again, but VCF text of
+    /// each record is kept as `PartialVCFRecord::raw`.
+    pub fn from_bcf(reader: BCFReader<R>) -> VCFReader<R> {
+        VCFReader {
+            header: reader.header.clone(),
+            input: VCFInput::Binary(Box::new(reader)),
+            current_line: 0,
+            buffer: Vec::new(),
+        }
+    }
 
     pub fn next_record(&mut self) -> Result<Option<PartialVCFRecord>, VCFParseError> {
         self.buffer.clear();
         self.current_line += 1;
-        let read_bytes = self.reader.read_until(b'\n', &mut self.buffer)?;
-        if read_bytes == 0 {
-            Ok(None)
-        } else {
-            PartialVCFRecord::parse_vcf(self.current_line, &self.buffer).map(Some)
+        match &mut self.input {
+            VCFInput::Text(reader) => {
+                let read_bytes = reader.read_until(b'\n', &mut self.buffer)?;
+                if read_bytes == 0 {
+                    Ok(None)
+                } else {
+                    PartialVCFRecord::parse_vcf(self.current_line, &self.buffer).map(Some)
+                }
+            }
+            VCFInput::Binary(reader) => match reader.next_record()? {
+                Some(record) => Ok(Some(PartialVCFRecord::from_complete(
+                    record,
+                    &mut self.buffer,
+                )?)),
+                None => Ok(None),
+            },
         }
     }
 }
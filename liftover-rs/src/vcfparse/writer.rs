@@ -11,19 +11,7 @@ pub struct VCFWriter<W: Write> {
 impl<W: Write> VCFWriter<io::BufWriter<W>> {
     pub fn new(writer: W, header: VCFHeader) -> Result<VCFWriter<io::BufWriter<W>>, VCFParseError> {
         let mut writer = io::BufWriter::new(writer);
-        for one_item in header.header_items.iter() {
-            one_item.write(&mut writer)?;
-        }
-        if header.samples.is_empty() {
-            writer.write_all(b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
-        } else {
-            writer.write_all(b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT")?;
-            for one in header.samples.iter() {
-                writer.write_all(b"\t")?;
-                writer.write_all(one)?;
-            }
-        }
-        writer.write_all(b"\n")?;
+        header.write(&mut writer)?;
 
         Ok(VCFWriter {
             header,
@@ -40,4 +28,12 @@ impl<W: Write> VCFWriter<W> {
         self.writer.write_all(&self.buffer)?;
         Ok(())
     }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
 }
@@ -1,10 +1,9 @@
 use crate::utils::{create, open};
 use anyhow::Context;
-use bio::io::fasta::IndexedReader;
 use clap::Args;
 use liftover::chain::{Chain, Strand};
 use liftover::LiftOverError;
-use liftover::{reverse_acid, reverse_complement, GenomeSequence};
+use liftover::{reverse_acid, reverse_complement, GenomeFile, GenomeSequence};
 use log::warn;
 use std::io;
 use std::io::Write;
@@ -46,14 +45,14 @@ pub struct Chain2BedVcf {
         long = "original",
         alias = "reference",
         short = 'r',
-        help = "Original assembly FASTA (.fai file is required)"
+        help = "Original assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
     )]
     original_sequence: String,
     #[arg(
         long = "new",
         alias = "query",
         short = 'q',
-        help = "New assembly FASTA (.fai file is required)"
+        help = "New assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
     )]
     new_sequence: String,
     #[arg(
@@ -92,9 +91,9 @@ fn chain_to_bed_vcf_helper(
     new_bed_path: &str,
     sv_len: usize,
 ) -> anyhow::Result<()> {
-    let mut original_sequence = IndexedReader::from_file(&original_sequence_path)
+    let mut original_sequence = GenomeFile::open(original_sequence_path)
         .with_context(|| format!("Cannot open original sequence: {original_sequence_path}"))?;
-    let mut new_sequence = IndexedReader::from_file(&new_sequence_path)
+    let mut new_sequence = GenomeFile::open(new_sequence_path)
         .with_context(|| format!("Cannot open query sequence: {new_sequence_path}"))?;
     let chain_file = liftover::chain::ChainFile::load(
         open(chain_path).with_context(|| format!("Cannot open chain file: {chain_path}"))?,
@@ -1,10 +1,10 @@
 use super::Command;
 use crate::cli::validate_integer;
+use anyhow::Context;
 use autocompress::{create, open, CompressionLevel};
-use bio::io::fasta::IndexedReader;
 use clap::{App, Arg, ArgMatches};
 use liftover::chain::{Chain, Chromosome, Strand};
-use liftover::{reverse_complement, GenomeSequence};
+use liftover::{reverse_complement, GenomeFile, GenomeSequence};
 use log::{debug, trace};
 use std::io::{self, Write};
 use std::str;
@@ -60,7 +60,7 @@ impl Command for Chain2ChunkBed {
                     .short("r")
                     .takes_value(true)
                     .required(true)
-                    .help("Reference/new sequence FASTA (.fai file is required)"),
+                    .help("Reference/new sequence FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"),
             )
             .arg(
                 Arg::with_name("query-sequence")
@@ -68,7 +68,7 @@ impl Command for Chain2ChunkBed {
                     .short("q")
                     .takes_value(true)
                     .required(true)
-                    .help("Query/original sequence FASTA (.fai file is required)"),
+                    .help("Query/original sequence FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"),
             ).arg(
                 Arg::with_name("svlen")
                     .long("svlen")
@@ -111,10 +111,10 @@ fn chain_to_chunk_bed_helper(
     debug!("     Reference VCF: {:?}", reference_vcf_path);
     debug!("         Query VCF: {:?}", query_vcf_path);
 
-    let mut reference_sequence =
-        IndexedReader::from_file(&reference_sequence_path).expect("Cannot open reference sequence");
-    let mut query_sequence =
-        IndexedReader::from_file(&query_sequence_path).expect("Cannot open query sequence");
+    let mut reference_sequence = GenomeFile::open(reference_sequence_path)
+        .with_context(|| format!("Cannot open reference sequence: {reference_sequence_path}"))?;
+    let mut query_sequence = GenomeFile::open(query_sequence_path)
+        .with_context(|| format!("Cannot open query sequence: {query_sequence_path}"))?;
 
     let chain_file =
         liftover::chain::ChainFile::load(open(chain_path).expect("Cannot open chain file"))
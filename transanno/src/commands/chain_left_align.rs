@@ -1,8 +1,7 @@
 use crate::utils::{create, open};
 use anyhow::Context;
-use bio::io::fasta::IndexedReader;
 use clap::Args;
-use liftover::chain;
+use liftover::{chain, GenomeFile};
 use log::info;
 
 #[derive(Debug, Clone, Args)]
@@ -13,14 +12,14 @@ pub struct ChainLeftAlign {
     #[arg(help = "Output chain file", short = 'o', long = "output")]
     output: String,
     #[arg(
-        help = "Original assembly FASTA (.fai file is required)",
+        help = "Original assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file",
         short = 'r',
         long = "original",
         alias = "reference"
     )]
     original_sequence: String,
     #[arg(
-        help = "New assembly FASTA (.fai file is required)",
+        help = "New assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file",
         short = 'q',
         long = "new",
         alias = "query"
@@ -35,14 +34,13 @@ impl ChainLeftAlign {
             .with_context(|| format!("Cannot create input chain file: {}", self.original_chain))?;
         let mut output_file = create(&self.output)
             .with_context(|| format!("Cannot create output chain file: {}", self.output))?;
-        let mut original_seq =
-            IndexedReader::from_file(&self.original_sequence).with_context(|| {
-                format!(
-                    "Cannot load original assembly FASTA: {}",
-                    self.original_sequence
-                )
-            })?;
-        let mut new_seq = IndexedReader::from_file(&self.new_sequence)
+        let mut original_seq = GenomeFile::open(&self.original_sequence).with_context(|| {
+            format!(
+                "Cannot load original assembly FASTA: {}",
+                self.original_sequence
+            )
+        })?;
+        let mut new_seq = GenomeFile::open(&self.new_sequence)
             .with_context(|| format!("Cannot load new assembly FASTA: {}", self.new_sequence))?;
         let chain_data = chain::ChainFile::load(chain_file).expect("Failed to parse chain file");
         let left_aligned = chain_data.left_align(&mut original_seq, &mut new_seq)?;
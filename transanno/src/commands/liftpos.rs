@@ -0,0 +1,334 @@
+use crate::utils::{create, open};
+use anyhow::Context;
+use clap::Args;
+use liftover::chain::Strand;
+use liftover::poslift::PositionLiftOver;
+use serde_json::json;
+use std::io::{self, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+enum OutputFormat {
+    Tsv,
+    Json,
+}
+
+#[derive(Debug, Clone, Args)]
+#[command(
+    about = "Lift genomic positions or regions",
+    long_about = r#"Lift genomic positions or regions
+
+Coordinates are 1-based and regions include both ends (e.g. chr1:12345 or chr2:100-200).
+When no coordinates are given as arguments, one coordinate per line is read from standard input.
+"#
+)]
+pub struct LiftPos {
+    #[arg(long = "chain", short = 'c', help = "chain file")]
+    chain: String,
+    #[arg(help = "Positions (chr1:12345) or regions (chr2:100-200) to lift")]
+    coordinates: Vec<String>,
+    #[arg(
+        long = "format",
+        short = 'f',
+        default_value = "tsv",
+        help = "Output format (JSON is written as one object per line)"
+    )]
+    format: OutputFormat,
+    #[arg(
+        long = "output",
+        short = 'o',
+        help = "Output path (default: standard output)"
+    )]
+    output: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Coordinate {
+    /// zero based position
+    Position { chromosome: String, position: u64 },
+    /// zero based half-open region
+    Region {
+        chromosome: String,
+        start: u64,
+        end: u64,
+    },
+}
+
+impl Coordinate {
+    pub(crate) fn parse(text: &str) -> anyhow::Result<Coordinate> {
+        let (chromosome, location) = text
+            .trim()
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid coordinate: {}", text))?;
+        if chromosome.is_empty() {
+            return Err(anyhow::anyhow!("Invalid coordinate: {}", text));
+        }
+        let location = location.replace(',', "");
+        if let Some((start, end)) = location.split_once('-') {
+            let start: u64 = start
+                .parse()
+                .with_context(|| format!("Invalid start position: {}", text))?;
+            let end: u64 = end
+                .parse()
+                .with_context(|| format!("Invalid end position: {}", text))?;
+            if start == 0 || end < start {
+                return Err(anyhow::anyhow!("Invalid region: {}", text));
+            }
+            Ok(Coordinate::Region {
+                chromosome: chromosome.to_string(),
+                start: start - 1,
+                end,
+            })
+        } else {
+            let position: u64 = location
+                .parse()
+                .with_context(|| format!("Invalid position: {}", text))?;
+            if position == 0 {
+                return Err(anyhow::anyhow!("Invalid position: {}", text));
+            }
+            Ok(Coordinate::Position {
+                chromosome: chromosome.to_string(),
+                position: position - 1,
+            })
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LiftedCoordinate {
+    pub chromosome: String,
+    /// zero based start position
+    pub start: u64,
+    pub end: u64,
+    pub strand: Strand,
+    pub chain_id: String,
+}
+
+pub(crate) fn lift_coordinate(
+    position_lift: &PositionLiftOver,
+    coordinate: &Coordinate,
+) -> Vec<LiftedCoordinate> {
+    let chain_list = position_lift.chain_list();
+    match coordinate {
+        Coordinate::Position {
+            chromosome,
+            position,
+        } => position_lift
+            .lift_position(chromosome, *position)
+            .into_iter()
+            .map(|x| LiftedCoordinate {
+                chromosome: x.chromosome.name.to_string(),
+                start: x.position,
+                end: x.position + 1,
+                strand: x.strand,
+                chain_id: chain_list[x.chain_index].chain_id.to_string(),
+            })
+            .collect(),
+        Coordinate::Region {
+            chromosome,
+            start,
+            end,
+        } => position_lift
+            .lift_region(chromosome, *start..*end)
+            .into_iter()
+            .map(|x| LiftedCoordinate {
+                chromosome: x.chromosome.name.to_string(),
+                start: x.start,
+                end: x.end,
+                strand: x.strand,
+                chain_id: chain_list[x.chain_index].chain_id.to_string(),
+            })
+            .collect(),
+    }
+}
+
+impl LiftPos {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let position_lift =
+            PositionLiftOver::load(open(&self.chain).context("Failed to open chain file")?)
+                .context("Failed parse chain file.")?;
+        let mut writer: Box<dyn Write> = if let Some(output) = self.output.as_ref() {
+            Box::new(io::BufWriter::new(
+                create(output).with_context(|| format!("Failed to create {}", output))?,
+            ))
+        } else {
+            Box::new(io::BufWriter::new(io::stdout()))
+        };
+
+        if self.format == OutputFormat::Tsv {
+            writeln!(
+                writer,
+                "#QUERY\tCHROM\tSTART\tEND\tSTRAND\tCHAIN_ID\tCANDIDATES"
+            )?;
+        }
+
+        if self.coordinates.is_empty() {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                let line = line?;
+                let query = line.trim();
+                if query.is_empty() || query.starts_with('#') {
+                    continue;
+                }
+                self.write_one(&position_lift, query, &mut writer)?;
+            }
+        } else {
+            for query in self.coordinates.iter() {
+                self.write_one(&position_lift, query, &mut writer)?;
+            }
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn write_one(
+        &self,
+        position_lift: &PositionLiftOver,
+        query: &str,
+        writer: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        let coordinate = Coordinate::parse(query)?;
+        let lifted = lift_coordinate(position_lift, &coordinate);
+        match self.format {
+            OutputFormat::Tsv => {
+                if lifted.is_empty() {
+                    writeln!(writer, "{}\t.\t.\t.\t.\t.\t0", query)?;
+                }
+                for one in lifted.iter() {
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        query,
+                        one.chromosome,
+                        one.start + 1,
+                        one.end,
+                        one.strand,
+                        one.chain_id,
+                        lifted.len()
+                    )?;
+                }
+            }
+            OutputFormat::Json => {
+                let candidates: Vec<_> = lifted
+                    .iter()
+                    .map(|x| {
+                        json!({
+                            "chromosome": x.chromosome,
+                            "start": x.start + 1,
+                            "end": x.end,
+                            "strand": x.strand.to_string(),
+                            "chain_id": x.chain_id,
+                        })
+                    })
+                    .collect();
+                serde_json::to_writer(
+                    &mut *writer,
+                    &json!({
+                        "query": query,
+                        "candidates": candidates,
+                    }),
+                )?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_coordinate() -> anyhow::Result<()> {
+        assert_eq!(
+            Coordinate::parse("chr1:12345")?,
+            Coordinate::Position {
+                chromosome: "chr1".to_string(),
+                position: 12344
+            }
+        );
+        assert_eq!(
+            Coordinate::parse("chr2:100-200")?,
+            Coordinate::Region {
+                chromosome: "chr2".to_string(),
+                start: 99,
+                end: 200
+            }
+        );
+        assert_eq!(
+            Coordinate::parse("HLA-A*01:01:01:01:1,000")?,
+            Coordinate::Position {
+                chromosome: "HLA-A*01:01:01:01".to_string(),
+                position: 999
+            }
+        );
+        assert!(Coordinate::parse("chr1").is_err());
+        assert!(Coordinate::parse("chr1:0").is_err());
+        assert!(Coordinate::parse("chr1:200-100").is_err());
+        assert!(Coordinate::parse(":100").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_liftpos() -> anyhow::Result<()> {
+        std::fs::create_dir_all("../target/test-output/pos")?;
+        let cli = Cli::parse_from([
+            "transanno",
+            "liftpos",
+            "--chain",
+            "../liftover-rs/testfiles/genomes/chain/GRCh38-to-GRCh37.chr22.chain",
+            "--output",
+            "../target/test-output/pos/liftpos.tsv",
+            "chr22:17513945",
+            "chr22:17513945-17514000",
+            "chr1:100",
+        ]);
+        cli.command.run()?;
+        assert_eq!(
+            std::fs::read_to_string("../target/test-output/pos/liftpos.tsv")?,
+            "#QUERY\tCHROM\tSTART\tEND\tSTRAND\tCHAIN_ID\tCANDIDATES
+chr22:17513945\tchr22\t17992974\t17992974\t+\t14\t1
+chr22:17513945-17514000\tchr22\t17992974\t17993029\t+\t14\t1
+chr1:100\t.\t.\t.\t.\t.\t0
+"
+        );
+
+        let cli = Cli::parse_from([
+            "transanno",
+            "liftpos",
+            "--chain",
+            "../liftover-rs/testfiles/genomes/chain/GRCh38-to-GRCh37.chr22.revcomp.chain",
+            "--format",
+            "json",
+            "--output",
+            "../target/test-output/pos/liftpos.jsonl",
+            "chr22:17513945",
+            "chr22:17513945-17514000",
+            "chr1:100",
+        ]);
+        cli.command.run()?;
+        let lines: Vec<serde_json::Value> =
+            std::fs::read_to_string("../target/test-output/pos/liftpos.jsonl")?
+                .lines()
+                .map(serde_json::from_str)
+                .collect::<Result<_, _>>()?;
+        assert_eq!(
+            lines,
+            vec![
+                json!({
+                    "query": "chr22:17513945",
+                    "candidates": [{"chromosome": "chr22", "start": 33311593, "end": 33311593, "strand": "-", "chain_id": "13"}],
+                }),
+                json!({
+                    "query": "chr22:17513945-17514000",
+                    "candidates": [{"chromosome": "chr22", "start": 33311538, "end": 33311593, "strand": "-", "chain_id": "13"}],
+                }),
+                json!({"query": "chr1:100", "candidates": []}),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
+use crate::tabix;
 use crate::utils::{create, open};
 use anyhow::Context;
-use bio::io::fasta::IndexedReader;
 use clap::Args;
-use liftover::{chain, variantlift, vcflift, LiftOverError};
-use log::info;
+use liftover::vcfparse::{BCFTextWriter, VCFReader};
+use liftover::{chain, normalize, variantlift, vcflift, GenomeFile, LiftOverError};
+use log::{info, warn};
+use std::io::{Read, Write};
 
 #[derive(Debug, Clone, Args)]
 #[command(about = "LiftOver VCF file")]
@@ -12,50 +14,72 @@ pub struct LiftVcf {
         long = "original-assembly",
         alias = "reference",
         short = 'r',
-        help = "Original assembly FASTA (.fai file is required)"
+        help = "Original assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
     )]
     original_sequence: String,
     #[arg(
         long = "new-assembly",
         alias = "query",
         short = 'q',
-        help = "New assembly FASTA (.fai file is required)"
+        help = "New assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
     )]
     new_sequence: String,
     #[arg(long, short, help = "chain file")]
     chain: String,
-    #[arg(long, short, help = "input VCF file to liftOver")]
+    #[arg(long, short, help = "input VCF or BCF file to liftOver")]
     vcf: String,
     #[arg(
         long,
         short,
-        help = "output VCF file for succeeded to liftOver records (This file is not sorted)"
+        help = "output VCF file for succeeded to liftOver records. BCF is written if a file name ends with .bcf (This file is not sorted unless --sort is specified)"
     )]
     output: String,
     #[arg(short, long, help = "Output TSV file of liftOver summary")]
     summary_output: Option<String>,
+    #[arg(long = "summary-json", help = "Output JSON file of liftOver summary")]
+    summary_json: Option<String>,
     #[arg(
         long = "fail",
         short = 'f',
-        help = "output VCF file for failed to liftOver records"
+        help = "output VCF file for failed to liftOver records. BCF is written if a file name ends with .bcf"
     )]
     fail: String,
+    #[arg(
+        long = "failure-report",
+        help = "Output TSV file of failed records with reasons and tested regions"
+    )]
+    failure_report: Option<String>,
+    #[arg(
+        long = "config",
+        help = "TOML file of liftOver parameters. Keys are the same as fields of VCFLiftOverParameters (e.g. do_not_swap_ref_alt = true). Command line options are applied after this file"
+    )]
+    config: Option<String>,
+    #[arg(
+        long = "preset",
+        value_parser = clap::builder::PossibleValuesParser::new(vcflift::PRESETS),
+        help = "Use preset parameters"
+    )]
+    preset: Option<String>,
+    #[arg(
+        long = "auto-preset",
+        conflicts_with = "preset",
+        help = "Select a preset from ##source header of input VCF (ClinVar, COSMIC or gnomAD). Selected preset and effective parameters are logged as a warning"
+    )]
+    auto_preset: bool,
     #[arg(help = "Allow multi-map", long = "allow-multi-map", short = 'm')]
     allow_multimap: bool,
     #[arg(
-        help = "length of acceptable deletion",
+        help = "length of acceptable deletion [default: 3]",
         long = "acceptable-deletion",
-        short = 'd',
-        default_value = "3"
+        short = 'd'
     )]
-    acceptable_deletion: u64,
+    acceptable_deletion: Option<u64>,
     #[arg(
-        help = "length of acceptable insertion",
+        help = "length of acceptable insertion [default: 3]",
         long = "acceptable-insertion",
-        short = 'i',
-        default_value = "3"
+        short = 'i'
     )]
-    acceptable_insertion: u64,
+    acceptable_insertion: Option<u64>,
     #[arg(
         long = "no-rewrite-format",
         help = "Do not rewrite order of FORMAT tags"
@@ -98,15 +122,116 @@ pub struct LiftVcf {
         help = "Ignore length mismatch between chain and fasta file"
     )]
     ignore_fasta_length_mismatch: bool,
+    #[arg(
+        long = "merge-multi-allelic",
+        help = "Merge consecutive records at the same position into one multi-allelic record before liftOver to rewrite allele frequency/count correctly"
+    )]
+    merge_multi_allelic: bool,
+    #[arg(
+        long = "split-multi-allelic",
+        help = "Split merged multi-allelic records into bi-allelic records after liftOver"
+    )]
+    split_multi_allelic: bool,
+    #[arg(
+        long = "gvcf",
+        help = "Input is gVCF. Split reference blocks at chain boundaries and keep <NON_REF> alleles"
+    )]
+    gvcf: bool,
+    #[arg(
+        long = "sort",
+        help = "Sort output VCF file by contig order in the new assembly and position"
+    )]
+    sort: bool,
+    #[arg(
+        long = "sort-buffer-size",
+        help = "Memory size in MB to buffer records while sorting. Temporary files are created in TMPDIR if records exceed this size [default: 512]"
+    )]
+    sort_buffer_size: Option<usize>,
+    #[arg(
+        long = "index",
+        help = "Create tabix index (or CSI index for long contigs) for output VCF file. Output file name should end with .gz"
+    )]
+    index: bool,
+    #[arg(
+        long = "only-used-contigs",
+        help = "Write ##contig lines only for contigs which variants can be lifted to"
+    )]
+    only_used_contigs: bool,
+    #[arg(
+        long = "contig-md5",
+        help = "Compute MD5 of new assembly contigs for ##contig lines. The whole new assembly is read"
+    )]
+    write_contig_md5: bool,
+    #[arg(
+        long = "verify-with-reverse-chain",
+        help = "Chain file from the new assembly to the original assembly. Lifted variants are lifted back with this chain, and variants which do not return to the original position and alleles are marked with REVERSE_LIFT_MISMATCH"
+    )]
+    reverse_chain: Option<String>,
+    #[arg(
+        long = "fail-reverse-lift-mismatch",
+        requires = "reverse_chain",
+        help = "Write variants which do not return to the original position and alleles into failed VCF"
+    )]
+    fail_reverse_lift_mismatch: bool,
+    #[arg(
+        long = "accept-iupac-ref",
+        help = "Replace IUPAC codes in REF with bases of the original assembly instead of failing. Repaired records are marked with REPAIRED_REF"
+    )]
+    accept_iupac_reference: bool,
+    #[arg(
+        long = "repair-ref",
+        help = "Replace REF which does not match the original assembly with bases of the original assembly instead of failing. REF and ALT are swapped if ALT is equal to the original assembly. Repaired records are marked with REPAIRED_REF"
+    )]
+    repair_reference: bool,
+    #[arg(
+        long = "flag-representation-change",
+        help = "Mark lifted variants whose position or allele length differs from a direct coordinate shift because of normalization in the new assembly with REPRESENTATION_CHANGED and POSITION_SHIFT"
+    )]
+    flag_representation_change: bool,
+    #[arg(
+        long = "min-confidence",
+        help = "Fail variants whose confidence score in LIFT_CONFIDENCE INFO is lower than this value with LOW_CONFIDENCE. The score from 0 to 100 is computed from distance to the nearest chain gap, chain score, aligned block size, number of candidate chains and whether REF changed"
+    )]
+    min_confidence: Option<u32>,
+    #[arg(
+        long = "collision",
+        value_parser = clap::builder::PossibleValuesParser::new(vcflift::COLLISION_POLICIES),
+        help = "Resolve lifted variants from different original records at the same position with the same alleles. keep-all adds COLLISION flag, keep-first keeps the first record, and fail moves all records into failed VCF. Requires --sort"
+    )]
+    collision: Option<String>,
+    #[arg(
+        long = "normalize-direction",
+        value_parser = clap::builder::PossibleValuesParser::new(normalize::NORMALIZE_DIRECTIONS),
+        help = "Shift lifted indels to the left-most (VCF convention) or the right-most (HGVS 3' rule) position [default: left]"
+    )]
+    normalize_direction: Option<String>,
+    #[arg(
+        long = "drop-info",
+        value_delimiter = ',',
+        help = "Comma separated INFO tags to remove from lifted records (e.g. CLNHGVS)"
+    )]
+    drop_info: Vec<String>,
+    #[arg(
+        long = "stale-info",
+        value_delimiter = ',',
+        help = "Comma separated INFO tags to keep as is with a note in the header that they are not updated by liftOver"
+    )]
+    stale_info: Vec<String>,
+    #[arg(
+        long = "rewrite-allele-info",
+        value_delimiter = ',',
+        help = "Comma separated INFO tags of VEP CSQ or snpEff ANN style annotations. Allele sub-fields are rewritten with lifted alleles"
+    )]
+    rewrite_allele_info: Vec<String>,
 }
 
 impl LiftVcf {
     pub fn run(&self) -> anyhow::Result<()> {
         info!("start loading chain and fasta");
-        let mut original_seq = IndexedReader::from_file(&self.original_sequence)
+        let mut original_seq = GenomeFile::open(&self.original_sequence)
             .context("Failed to load original assembly FASTA")?;
-        let mut new_seq = IndexedReader::from_file(&self.new_sequence)
-            .context("Failed to load new assembly FASTA")?;
+        let mut new_seq =
+            GenomeFile::open(&self.new_sequence).context("Failed to load new assembly FASTA")?;
         let chain = chain::ChainFile::load(open(&self.chain)?)?
             .left_align(&mut original_seq, &mut new_seq)
             .context("Failed to load chain file")?;
@@ -128,31 +253,73 @@ impl LiftVcf {
             }
         }
 
+        let vcf_reader = VCFReader::new(
+            open(&self.vcf).with_context(|| format!("Failed to open input VCF: {}", self.vcf))?,
+        )
+        .context("Failed to read header of input VCF")?;
+        let auto_preset = if self.auto_preset {
+            vcflift::VCFLiftOverParameters::preset_from_header(&vcf_reader.header)
+        } else {
+            None
+        };
+        let param = self.parameters(auto_preset)?;
+        if param.split_multi_allelic && !param.merge_multi_allelic {
+            return Err(anyhow::anyhow!(
+                "--split-multi-allelic requires --merge-multi-allelic"
+            ));
+        }
+        if self.index && !param.sort {
+            return Err(anyhow::anyhow!("--index requires --sort"));
+        }
+        if param.collision_policy.is_some() && !param.sort {
+            return Err(anyhow::anyhow!("--collision requires --sort"));
+        }
+        let param_text = toml::to_string(&param).context("Failed to serialize parameters")?;
+        if let Some(preset) = auto_preset {
+            warn!(
+                "preset \"{}\" was selected from ##source header. liftOver parameters:\n{}",
+                preset, param_text
+            );
+        } else {
+            info!("liftOver parameters:\n{}", param_text);
+        }
+
         let variant_liftover = variantlift::VariantLiftOver::new(chain, original_seq, new_seq);
-        let mut vcf_lift = vcflift::VCFLiftOver::new(
-            variant_liftover,
-            vcflift::VCFLiftOverParameters::new()
-                .allow_multimap(self.allow_multimap)
-                .acceptable_deletion(self.acceptable_deletion)
-                .acceptable_insertion(self.acceptable_insertion)
-                .do_not_rewrite_info(self.do_not_rewrite_info)
-                //.do_not_rewrite_format(self.do_not_)
-                .do_not_rewrite_gt(self.do_not_rewrite_gt)
-                .do_not_rewrite_allele_frequency(self.do_not_rewrite_allele_frequency)
-                .do_not_rewrite_allele_count(self.do_not_rewrite_allele_count)
-                .do_not_swap_ref_alt(self.do_not_swap_ref_alt)
-                .do_not_left_align_chain_file(self.do_not_left_align_chain)
-                .do_not_use_dot_when_alt_equal_to_ref(self.do_not_use_dot_when_alt_equal_to_ref)
-                .do_not_prefer_cis_contig_when_multimap(
-                    self.do_not_prefer_cis_contig_when_multimap,
-                ),
-        );
+        let mut vcf_lift = vcflift::VCFLiftOver::new(variant_liftover, param)
+            .info_handling(self.info_handling())
+            .provenance(
+                vcflift::VCFLiftOverProvenance::new()
+                    .program(concat!("transanno ", env!("CARGO_PKG_VERSION")))
+                    .chain(&self.chain)
+                    .command_line(&std::env::args().collect::<Vec<_>>().join(" "))
+                    .original_assembly(&self.original_sequence)
+                    .new_assembly(&self.new_sequence),
+            );
+        if let Some(reverse_chain) = self.reverse_chain.as_ref() {
+            let mut original_seq = GenomeFile::open(&self.new_sequence)
+                .context("Failed to load new assembly FASTA")?;
+            let mut new_seq = GenomeFile::open(&self.original_sequence)
+                .context("Failed to load original assembly FASTA")?;
+            let chain = chain::ChainFile::load(open(reverse_chain)?)?
+                .left_align(&mut original_seq, &mut new_seq)
+                .context("Failed to load reverse chain file")?;
+            vcf_lift = vcf_lift.verify_with_reverse_lift(variantlift::VariantLiftOver::new(
+                chain,
+                original_seq,
+                new_seq,
+            ));
+        }
         info!("chain file and fasta files were loaded");
 
-        let uncompressed_reader = open(&self.vcf).expect("Cannot open input VCF");
-        let success_writer = create(&self.output).expect("Cannot create output VCF");
-        let failed_writer =
-            create(&self.fail).expect("Cannot create output VCF for failed records");
+        if self.index && !self.output.ends_with(".gz") {
+            return Err(anyhow::anyhow!(
+                "Output file name should end with .gz to create index"
+            ));
+        }
+
+        let mut success_writer = create_vcf(&self.output).expect("Cannot create output VCF");
+        let mut failed_writer =
+            create_vcf(&self.fail).expect("Cannot create output VCF for failed records");
         let summary_writer = if let Some(summary_output) = self.summary_output.as_ref() {
             Some(create(summary_output).with_context(|| {
                 format!("Failed to open summary output file: {}", summary_output)
@@ -160,22 +327,188 @@ impl LiftVcf {
         } else {
             None
         };
+        let failure_report_writer = if let Some(failure_report) = self.failure_report.as_ref() {
+            Some(create(failure_report).with_context(|| {
+                format!("Failed to open failure report file: {}", failure_report)
+            })?)
+        } else {
+            None
+        };
 
-        vcf_lift.lift_vcf(
-            uncompressed_reader,
-            success_writer,
-            failed_writer,
+        let summary = vcf_lift.lift_vcf_reader(
+            vcf_reader,
+            &mut success_writer,
+            &mut failed_writer,
             summary_writer,
+            failure_report_writer,
         )?;
+        success_writer
+            .finish()
+            .with_context(|| format!("Failed to write output VCF: {}", self.output))?;
+        failed_writer
+            .finish()
+            .with_context(|| format!("Failed to write output VCF: {}", self.fail))?;
+        if let Some(summary_json) = self.summary_json.as_ref() {
+            summary
+                .write_json(create(summary_json).with_context(|| {
+                    format!("Failed to open summary output file: {}", summary_json)
+                })?)
+                .with_context(|| format!("Failed to write summary: {}", summary_json))?;
+        }
+
+        if self.index {
+            let index_path = tabix::create_index(&self.output)
+                .with_context(|| format!("Failed to create index for {}", self.output))?;
+            info!("index was created: {}", index_path.display());
+        }
         Ok(())
     }
+
+    fn info_handling(&self) -> vcflift::VCFLiftOverInfoHandling {
+        let mut info_handling = vcflift::VCFLiftOverInfoHandling::new();
+        for (tags, handling) in [
+            (&self.drop_info, vcflift::InfoHandling::Drop),
+            (&self.stale_info, vcflift::InfoHandling::MarkStale),
+            (
+                &self.rewrite_allele_info,
+                vcflift::InfoHandling::RewriteAllele,
+            ),
+        ] {
+            for one in tags {
+                info_handling = info_handling.tag(one, handling);
+            }
+        }
+        info_handling
+    }
+
+    /// Build parameters from a preset, a configuration file and command line options in this
+    /// order. `auto_preset` is used if `--preset` is not specified.
+    fn parameters(
+        &self,
+        auto_preset: Option<&str>,
+    ) -> anyhow::Result<vcflift::VCFLiftOverParameters> {
+        let mut param = self
+            .preset
+            .as_deref()
+            .or(auto_preset)
+            .map(|x| vcflift::VCFLiftOverParameters::preset(x).unwrap())
+            .unwrap_or_default();
+
+        if let Some(config) = self.config.as_ref() {
+            let mut config_text = String::new();
+            open(config)
+                .with_context(|| format!("Failed to open config file: {}", config))?
+                .read_to_string(&mut config_text)?;
+            let mut table: toml::Table = toml::to_string(&param)?.parse()?;
+            table.extend(
+                config_text
+                    .parse::<toml::Table>()
+                    .with_context(|| format!("Failed to parse config file: {}", config))?,
+            );
+            param = toml::Value::Table(table)
+                .try_into()
+                .with_context(|| format!("Invalid parameter in config file: {}", config))?;
+        }
+
+        param.allow_multimap |= self.allow_multimap;
+        if let Some(acceptable_deletion) = self.acceptable_deletion {
+            param.acceptable_deletion = acceptable_deletion;
+        }
+        if let Some(acceptable_insertion) = self.acceptable_insertion {
+            param.acceptable_insertion = acceptable_insertion;
+        }
+        param.do_not_rewrite_info |= self.do_not_rewrite_info;
+        param.do_not_rewrite_gt |= self.do_not_rewrite_gt;
+        param.do_not_rewrite_allele_frequency |= self.do_not_rewrite_allele_frequency;
+        param.do_not_rewrite_allele_count |= self.do_not_rewrite_allele_count;
+        param.do_not_swap_ref_alt |= self.do_not_swap_ref_alt;
+        param.do_not_left_align_chain_file |= self.do_not_left_align_chain;
+        param.do_not_use_dot_when_alt_equal_to_ref |= self.do_not_use_dot_when_alt_equal_to_ref;
+        param.do_not_prefer_cis_contig_when_multimap |= self.do_not_prefer_cis_contig_when_multimap;
+        param.merge_multi_allelic |= self.merge_multi_allelic;
+        param.split_multi_allelic |= self.split_multi_allelic;
+        param.gvcf |= self.gvcf;
+        param.sort |= self.sort;
+        if let Some(sort_buffer_size) = self.sort_buffer_size {
+            param.sort_buffer_size = sort_buffer_size * 1024 * 1024;
+        }
+        param.only_used_contigs |= self.only_used_contigs;
+        param.write_contig_md5 |= self.write_contig_md5;
+        param.fail_reverse_lift_mismatch |= self.fail_reverse_lift_mismatch;
+        param.accept_iupac_reference |= self.accept_iupac_reference;
+        param.repair_reference |= self.repair_reference;
+        param.flag_representation_change |= self.flag_representation_change;
+        if let Some(min_confidence) = self.min_confidence {
+            param.min_confidence = Some(min_confidence);
+        }
+        if let Some(collision) = self.collision.as_ref() {
+            param.collision_policy = Some(collision.parse().map_err(anyhow::Error::msg)?);
+        }
+        if let Some(normalize_direction) = self.normalize_direction.as_ref() {
+            param.normalize_direction = normalize_direction.parse().map_err(anyhow::Error::msg)?;
+        }
+        Ok(param)
+    }
+}
+
+/// A writer for VCF or BCF output. Call [`VCFOutput::finish`] to check BCF output was completed.
+pub(super) enum VCFOutput<W: Write> {
+    Text(W),
+    Binary(Box<BCFTextWriter<W>>),
+}
+
+impl<W: Write> VCFOutput<W> {
+    /// Flush and return an inner writer. An error is returned if BCF output is incomplete.
+    pub(super) fn finish(self) -> std::io::Result<W> {
+        match self {
+            VCFOutput::Text(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+            VCFOutput::Binary(writer) => {
+                let mut writer = writer.into_inner()?;
+                writer.flush()?;
+                Ok(writer)
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for VCFOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            VCFOutput::Text(writer) => writer.write(buf),
+            VCFOutput::Binary(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            VCFOutput::Text(writer) => writer.flush(),
+            VCFOutput::Binary(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Create a writer for VCF. VCF text is converted into BCF if a file name ends with `.bcf`.
+pub(super) fn create_vcf(path: &str) -> std::io::Result<VCFOutput<impl Write + '_>> {
+    let writer = create(path)?;
+    Ok(if path.ends_with(".bcf") {
+        VCFOutput::Binary(Box::new(BCFTextWriter::new(writer)))
+    } else {
+        VCFOutput::Text(writer)
+    })
 }
 
 #[cfg(test)]
 mod test {
     use clap::Parser;
+    use liftover::vcflift::VCFLiftOverParameters;
 
+    use super::{create_vcf, LiftVcf};
+    use crate::commands::Commands;
     use crate::Cli;
+    use std::io::Write;
 
     #[test]
     fn test_vcflift() -> anyhow::Result<()> {
@@ -198,10 +531,70 @@ mod test {
             "../target/test-output/vcf/1kGP-subset-liftover.fail.vcf.gz",
             "--summary-output",
             "../target/test-output/vcf/1kGP-subset-liftover.summary.txt",
+            "--summary-json",
+            "../target/test-output/vcf/1kGP-subset-liftover.summary.json",
         ]);
 
         cli.command.run()?;
 
         Ok(())
     }
+
+    fn parse_liftvcf(options: &[&str]) -> Result<LiftVcf, clap::Error> {
+        let mut args = vec![
+            "transanno",
+            "liftvcf",
+            "--chain",
+            "test.chain",
+            "--vcf",
+            "test.vcf",
+            "--original-assembly",
+            "original.fa",
+            "--new-assembly",
+            "new.fa",
+            "--output",
+            "success.vcf",
+            "--fail",
+            "fail.vcf",
+        ];
+        args.extend_from_slice(options);
+        match Cli::try_parse_from(&args)?.command {
+            Commands::Liftvcf(x) => Ok(x),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parameters() -> anyhow::Result<()> {
+        let default = VCFLiftOverParameters::new();
+        let liftvcf = parse_liftvcf(&[])?;
+        assert_eq!(liftvcf.parameters(None)?, default);
+        assert_eq!(
+            liftvcf.parameters(Some("gnomad"))?,
+            VCFLiftOverParameters::preset("gnomad").unwrap()
+        );
+
+        let liftvcf = parse_liftvcf(&["--preset", "clinvar", "--allow-multi-map"])?;
+        let param = liftvcf.parameters(Some("gnomad"))?;
+        assert!(param.do_not_swap_ref_alt);
+        assert!(param.allow_multimap);
+        assert!(!param.merge_multi_allelic);
+
+        assert!(parse_liftvcf(&["--preset", "clinvar", "--auto-preset"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_incomplete_bcf() -> anyhow::Result<()> {
+        std::fs::create_dir_all("../target/test-output/vcf")?;
+
+        let mut writer = create_vcf("../target/test-output/vcf/incomplete.bcf")?;
+        writer.write_all(b"##fileformat=VCFv4.2\n")?;
+        assert!(writer.finish().is_err());
+
+        let mut writer = create_vcf("../target/test-output/vcf/incomplete.vcf")?;
+        writer.write_all(b"##fileformat=VCFv4.2\n")?;
+        writer.finish()?;
+        Ok(())
+    }
 }
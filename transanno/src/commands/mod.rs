@@ -4,8 +4,11 @@ mod chain_left_align;
 // mod generate_completions;
 mod liftbed;
 mod liftgene;
+mod liftpos;
 mod liftvcf;
 mod minimap2chain;
+mod normalize;
+mod serve;
 
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum Commands {
@@ -15,6 +18,9 @@ pub enum Commands {
     Minimap2chain(minimap2chain::Minimap2Chain),
     Liftvcf(liftvcf::LiftVcf),
     Liftbed(liftbed::LiftBed),
+    Liftpos(liftpos::LiftPos),
+    Normalize(normalize::Normalize),
+    Serve(serve::Serve),
 }
 
 impl Commands {
@@ -26,6 +32,9 @@ impl Commands {
             Commands::Minimap2chain(x) => x.run(),
             Commands::Liftvcf(x) => x.run(),
             Commands::Liftbed(x) => x.run(),
+            Commands::Liftpos(x) => x.run(),
+            Commands::Normalize(x) => x.run(),
+            Commands::Serve(x) => x.run(),
         }
     }
 }
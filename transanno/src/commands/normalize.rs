@@ -0,0 +1,169 @@
+use super::liftvcf::create_vcf;
+use crate::utils::{create, open};
+use anyhow::Context;
+use clap::Args;
+use liftover::normalize::{VCFNormalizeParameters, VCFNormalizer, NORMALIZE_DIRECTIONS};
+use liftover::GenomeFile;
+use log::info;
+
+#[derive(Debug, Clone, Args)]
+#[command(about = "Normalize, split and decompose variants in VCF file")]
+pub struct Normalize {
+    #[arg(
+        long = "reference",
+        short = 'r',
+        help = "Reference FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
+    )]
+    reference: String,
+    #[arg(long, short, help = "input VCF or BCF file to normalize")]
+    vcf: String,
+    #[arg(
+        long,
+        short,
+        help = "output VCF file. BCF is written if a file name ends with .bcf"
+    )]
+    output: String,
+    #[arg(
+        long = "fail",
+        short = 'f',
+        help = "output VCF file for records with unmatched reference allele or unknown contig. These records are written into the output VCF file without changes if not specified"
+    )]
+    fail: Option<String>,
+    #[arg(
+        long = "report",
+        help = "Output TSV file of changed records with original and new representation"
+    )]
+    report: Option<String>,
+    #[arg(
+        long = "split-multi-allelic",
+        help = "Split multi-allelic records into bi-allelic records"
+    )]
+    split_multi_allelic: bool,
+    #[arg(
+        long = "decompose",
+        help = "Decompose MNPs and complex variants into SNVs and an indel"
+    )]
+    decompose: bool,
+    #[arg(
+        long = "sort",
+        help = "Sort output VCF file by contig order and position"
+    )]
+    sort: bool,
+    #[arg(
+        long = "sort-buffer-size",
+        help = "Memory size in MB to buffer records while sorting. Temporary files are created in TMPDIR if records exceed this size [default: 512]"
+    )]
+    sort_buffer_size: Option<usize>,
+    #[arg(
+        long = "direction",
+        value_parser = clap::builder::PossibleValuesParser::new(NORMALIZE_DIRECTIONS),
+        default_value = "left",
+        help = "Shift indels to the left-most (VCF convention) or the right-most (HGVS 3' rule) position"
+    )]
+    direction: String,
+}
+
+impl Normalize {
+    pub fn run(&self) -> anyhow::Result<()> {
+        info!("start loading fasta");
+        let reference =
+            GenomeFile::open(&self.reference).context("Failed to load reference FASTA")?;
+        let mut param = VCFNormalizeParameters::new()
+            .split_multi_allelic(self.split_multi_allelic)
+            .decompose(self.decompose)
+            .sort(self.sort)
+            .direction(self.direction.parse().map_err(anyhow::Error::msg)?);
+        if let Some(sort_buffer_size) = self.sort_buffer_size {
+            param = param.sort_buffer_size(sort_buffer_size * 1024 * 1024);
+        }
+        let mut normalizer = VCFNormalizer::new(reference, param);
+
+        let reader = open(&self.vcf).with_context(|| format!("Cannot open {}", self.vcf))?;
+        let mut writer =
+            create_vcf(&self.output).with_context(|| format!("Cannot create {}", self.output))?;
+        let mut failed_writer = if let Some(fail) = self.fail.as_ref() {
+            Some(create_vcf(fail).with_context(|| format!("Cannot create {}", fail))?)
+        } else {
+            None
+        };
+        let report_writer = if let Some(report) = self.report.as_ref() {
+            Some(
+                create(report)
+                    .with_context(|| format!("Failed to open report file: {}", report))?,
+            )
+        } else {
+            None
+        };
+
+        normalizer.normalize_vcf(reader, &mut writer, failed_writer.as_mut(), report_writer)?;
+        writer
+            .finish()
+            .with_context(|| format!("Failed to write {}", self.output))?;
+        if let Some(failed_writer) = failed_writer {
+            failed_writer
+                .finish()
+                .context("Failed to write output VCF for failed records")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use crate::Cli;
+
+    #[test]
+    fn test_normalize() -> anyhow::Result<()> {
+        std::fs::create_dir_all("../target/test-output/normalize")?;
+
+        let cli = Cli::parse_from([
+            "transanno",
+            "normalize",
+            "--reference",
+            "testfiles/normalize/reference.fa",
+            "--vcf",
+            "testfiles/normalize/input.vcf",
+            "--output",
+            "../target/test-output/normalize/normalized.vcf",
+            "--report",
+            "../target/test-output/normalize/normalized.tsv",
+            "--split-multi-allelic",
+            "--decompose",
+        ]);
+
+        cli.command.run()?;
+
+        let output = std::fs::read_to_string("../target/test-output/normalize/normalized.vcf")?;
+        assert_eq!(
+            output
+                .lines()
+                .filter(|x| !x.starts_with('#'))
+                .collect::<Vec<_>>(),
+            vec![
+                "chr1\t1\tsnv\tG\tA\t.\t.\tAF=0.1\tGT\t0/1",
+                "chr1\t4\tdel\tCT\tC\t.\t.\tAF=0.1;OLD_VARIANT=chr1:9:TT/T\tGT\t0/1",
+                "chr1\t11\tmulti\tG\tA\t.\t.\tAF=0.1;OLD_VARIANT=chr1:11:G/A/GC\tGT\t0/1",
+                "chr1\t11\tmulti\tG\tGC\t.\t.\tAF=0.2;OLD_VARIANT=chr1:11:G/A/GC\tGT\t0/1",
+                "chr1\t21\tmnp\tT\tC\t.\t.\tOLD_VARIANT=chr1:21:TAC/CAT\tGT\t0/1",
+                "chr1\t23\tmnp\tC\tT\t.\t.\tOLD_VARIANT=chr1:21:TAC/CAT\tGT\t0/1",
+            ]
+        );
+        assert!(output.contains("##INFO=<ID=OLD_VARIANT,"));
+
+        let report = std::fs::read_to_string("../target/test-output/normalize/normalized.tsv")?;
+        assert_eq!(
+            report,
+            "Line\tChromosome\tPosition\tReference\tAlternative\tNew position\tNew reference\tNew alternative\tChanges
+7\tchr1\t9\tTT\tT\t4\tCT\tC\tNORMALIZED
+8\tchr1\t11\tG\tA,GC\t11\tG\tA\tSPLIT
+8\tchr1\t11\tG\tA,GC\t11\tG\tGC\tSPLIT
+9\tchr1\t21\tTAC\tCAT\t21\tT\tC\tDECOMPOSED
+9\tchr1\t21\tTAC\tCAT\t23\tC\tT\tDECOMPOSED
+"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+use crate::utils::open;
+use anyhow::Context;
+use clap::Args;
+use liftover::poslift::RegionChangeOp;
+use liftover::variantlift::error::VariantLiftOverError;
+use liftover::variantlift::VariantLiftOver;
+use liftover::{chain, GenomeFile, GenomeSequence, LiftOverError, Variant};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Args)]
+#[command(
+    about = "Run local HTTP/JSON liftover server",
+    long_about = r#"Run local HTTP/JSON liftover server
+
+Chain and FASTA files are loaded once and kept in memory.
+Send POST requests with a JSON object or an array of JSON objects.
+Coordinates are 1-based and regions include both ends.
+
+POST /position {"chromosome": "chr1", "position": 12345}
+POST /region   {"chromosome": "chr1", "start": 100, "end": 200}
+POST /variant  {"chromosome": "chr1", "position": 12345, "reference": "A", "alternative": ["T"]}
+"#
+)]
+pub struct Serve {
+    #[arg(
+        long = "original-assembly",
+        alias = "reference",
+        short = 'r',
+        help = "Original assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
+    )]
+    original_sequence: String,
+    #[arg(
+        long = "new-assembly",
+        alias = "query",
+        short = 'q',
+        help = "New assembly FASTA (.fai file is required. .gzi file is also required if compressed with bgzip) or 2bit file"
+    )]
+    new_sequence: String,
+    #[arg(long, short, help = "chain file")]
+    chain: String,
+    #[arg(
+        long,
+        short,
+        default_value = "127.0.0.1:8080",
+        help = "Address and port to listen"
+    )]
+    bind: String,
+    #[arg(
+        help = "length of acceptable deletion",
+        long = "acceptable-deletion",
+        short = 'd',
+        default_value = "3"
+    )]
+    acceptable_deletion: u64,
+    #[arg(
+        help = "length of acceptable insertion",
+        long = "acceptable-insertion",
+        short = 'i',
+        default_value = "3"
+    )]
+    acceptable_insertion: u64,
+}
+
+impl Serve {
+    pub fn run(&self) -> anyhow::Result<()> {
+        info!("start loading chain and fasta");
+        let mut original_seq = GenomeFile::open(&self.original_sequence)
+            .context("Failed to load original assembly FASTA")?;
+        let mut new_seq =
+            GenomeFile::open(&self.new_sequence).context("Failed to load new assembly FASTA")?;
+        let chain = chain::ChainFile::load(open(&self.chain)?)?
+            .left_align(&mut original_seq, &mut new_seq)
+            .context("Failed to load chain file")?;
+        let mut lift_server = LiftServer::new(
+            VariantLiftOver::new(chain, original_seq, new_seq),
+            self.acceptable_deletion,
+            self.acceptable_insertion,
+        );
+        info!("chain file and fasta files were loaded");
+
+        let server = tiny_http::Server::http(&self.bind)
+            .map_err(|e| anyhow::anyhow!("Failed to listen {}: {}", self.bind, e))?;
+        info!("Listening on http://{}", self.bind);
+
+        for mut request in server.incoming_requests() {
+            let mut body = Vec::new();
+            let (status, value) = if *request.method() != tiny_http::Method::Post {
+                (405, json!({"error": "Only POST method is supported"}))
+            } else if let Err(e) = request.as_reader().read_to_end(&mut body) {
+                (
+                    400,
+                    json!({"error": format!("Failed to read request: {}", e)}),
+                )
+            } else {
+                match lift_server.handle(request.url(), &body) {
+                    Ok(value) => (200, value),
+                    Err(ServeError::NotFound) => (404, json!({"error": "Not found"})),
+                    Err(ServeError::BadRequest(e)) => (400, json!({ "error": e })),
+                    Err(ServeError::LiftOverError(e)) => {
+                        warn!("Failed to lift: {}", e);
+                        (500, json!({"error": e.to_string()}))
+                    }
+                }
+            };
+
+            let response = tiny_http::Response::from_string(value.to_string())
+                .with_status_code(status)
+                .with_header(
+                    tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .unwrap(),
+                );
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to send response: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum ServeError {
+    NotFound,
+    BadRequest(String),
+    LiftOverError(LiftOverError),
+}
+
+impl From<LiftOverError> for ServeError {
+    fn from(e: LiftOverError) -> Self {
+        ServeError::LiftOverError(e)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PositionRequest {
+    chromosome: String,
+    position: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RegionRequest {
+    chromosome: String,
+    start: u64,
+    end: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VariantRequest {
+    chromosome: String,
+    position: u64,
+    reference: String,
+    alternative: Vec<String>,
+}
+
+#[derive(Debug)]
+struct LiftServer<G: GenomeSequence> {
+    variant_lift: VariantLiftOver<G>,
+    acceptable_deletion: u64,
+    acceptable_insertion: u64,
+}
+
+impl<G: GenomeSequence> LiftServer<G> {
+    fn new(
+        variant_lift: VariantLiftOver<G>,
+        acceptable_deletion: u64,
+        acceptable_insertion: u64,
+    ) -> Self {
+        LiftServer {
+            variant_lift,
+            acceptable_deletion,
+            acceptable_insertion,
+        }
+    }
+
+    fn handle(&mut self, path: &str, body: &[u8]) -> Result<Value, ServeError> {
+        let path = path.split('?').next().unwrap_or_default();
+        match path.trim_end_matches('/') {
+            "/position" => handle_many(body, |x: PositionRequest| self.lift_position(&x)),
+            "/region" => handle_many(body, |x: RegionRequest| self.lift_region(&x)),
+            "/variant" => handle_many(body, |x: VariantRequest| self.lift_variant(&x)),
+            _ => Err(ServeError::NotFound),
+        }
+    }
+
+    fn lift_position(&mut self, request: &PositionRequest) -> Result<Value, ServeError> {
+        if request.position == 0 {
+            return Err(ServeError::BadRequest(
+                "position should be 1-based".to_string(),
+            ));
+        }
+        let position_lift = self.variant_lift.position_liftover();
+        let results: Vec<_> = position_lift
+            .lift_position(&request.chromosome, request.position - 1)
+            .into_iter()
+            .map(|x| {
+                json!({
+                    "chromosome": x.chromosome.name,
+                    "position": x.position + 1,
+                    "strand": x.strand.to_string(),
+                    "chain_index": x.chain_index,
+                    "chain_id": position_lift.chain_list()[x.chain_index].chain_id,
+                })
+            })
+            .collect();
+        Ok(json!({
+            "chromosome": request.chromosome,
+            "position": request.position,
+            "results": results,
+        }))
+    }
+
+    fn lift_region(&mut self, request: &RegionRequest) -> Result<Value, ServeError> {
+        if request.start == 0 || request.end < request.start {
+            return Err(ServeError::BadRequest(format!(
+                "Invalid region: {}:{}-{}",
+                request.chromosome, request.start, request.end
+            )));
+        }
+        let position_lift = self.variant_lift.position_liftover();
+        let results: Vec<_> = position_lift
+            .lift_region(&request.chromosome, (request.start - 1)..request.end)
+            .into_iter()
+            .map(|x| {
+                let changes: Vec<_> = x
+                    .changes
+                    .iter()
+                    .map(|y| match y {
+                        RegionChangeOp::Aligned(l) => json!({ "aligned": l }),
+                        RegionChangeOp::Insertion(l) => json!({ "insertion": l }),
+                        RegionChangeOp::Deletion(l) => json!({ "deletion": l }),
+                    })
+                    .collect();
+                json!({
+                    "chromosome": x.chromosome.name,
+                    "start": x.start + 1,
+                    "end": x.end,
+                    "strand": x.strand.to_string(),
+                    "changes": changes,
+                    "chain_index": x.chain_index,
+                    "chain_id": position_lift.chain_list()[x.chain_index].chain_id,
+                })
+            })
+            .collect();
+        Ok(json!({
+            "chromosome": request.chromosome,
+            "start": request.start,
+            "end": request.end,
+            "results": results,
+        }))
+    }
+
+    fn lift_variant(&mut self, request: &VariantRequest) -> Result<Value, ServeError> {
+        if request.position == 0 {
+            return Err(ServeError::BadRequest(
+                "position should be 1-based".to_string(),
+            ));
+        }
+        let alternative: Vec<_> = request
+            .alternative
+            .iter()
+            .map(|x| x.to_ascii_uppercase().into_bytes())
+            .collect();
+        let variant = Variant {
+            chromosome: request.chromosome.to_string(),
+            position: request.position - 1,
+            reference: request.reference.to_ascii_uppercase().into_bytes(),
+            alternative,
+        };
+        let results: Vec<_> = self
+            .variant_lift
+            .lift_variant(
+                &variant,
+                self.acceptable_deletion,
+                self.acceptable_insertion,
+            )?
+            .into_iter()
+            .map(|x| match x {
+                Ok(v) => json!({
+                    "chromosome": v.chromosome,
+                    "position": v.position + 1,
+                    "strand": v.strand.to_string(),
+                    "original_reference": String::from_utf8_lossy(&v.original_reference),
+                    "reference": String::from_utf8_lossy(&v.reference),
+                    "alternative": v
+                        .alternative
+                        .iter()
+                        .map(|y| String::from_utf8_lossy(y).to_string())
+                        .collect::<Vec<_>>(),
+                    "reference_changed": v.reference_changed,
+                }),
+                Err(e) => json!({
+                    "error": match e {
+                        VariantLiftOverError::UnacceptableLargeDeletion { .. } => {
+                            "UNACCEPTABLE_LARGE_DELETION"
+                        }
+                        VariantLiftOverError::UnacceptableLargeInsertion { .. } => {
+                            "UNACCEPTABLE_LARGE_INSERTION"
+                        }
+                        VariantLiftOverError::UnknownSequenceName(_) => "UNKNOWN_SEQUENCE_NAME",
+                        VariantLiftOverError::ReferenceSequenceIsNotMatch => "UNEXPECTED_REF",
+                    },
+                    "message": e.to_string(),
+                }),
+            })
+            .collect();
+        Ok(json!({
+            "chromosome": request.chromosome,
+            "position": request.position,
+            "reference": request.reference,
+            "alternative": request.alternative,
+            "results": results,
+        }))
+    }
+}
+
+fn handle_many<'de, T: Deserialize<'de>>(
+    body: &'de [u8],
+    mut f: impl FnMut(T) -> Result<Value, ServeError>,
+) -> Result<Value, ServeError> {
+    let request: OneOrMany<T> =
+        serde_json::from_slice(body).map_err(|e| ServeError::BadRequest(e.to_string()))?;
+    match request {
+        OneOrMany::One(x) => f(x),
+        OneOrMany::Many(x) => Ok(Value::Array(
+            x.into_iter().map(f).collect::<Result<Vec<_>, _>>()?,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bio::io::fasta::IndexedReader;
+    use std::fs::File;
+
+    fn new_lift_server() -> anyhow::Result<LiftServer<IndexedReader<File>>> {
+        let mut grch37 = IndexedReader::from_file(
+            &"../liftover-rs/testfiles/genomes/GRCh37/GRCh37.chr22.genome.fa",
+        )?;
+        let mut grch38 = IndexedReader::from_file(
+            &"../liftover-rs/testfiles/genomes/GRCh38/GRCh38.chr22.genome.fa",
+        )?;
+        let chain = chain::ChainFile::load(open(
+            "../liftover-rs/testfiles/genomes/chain/GRCh38-to-GRCh37.chr22.chain",
+        )?)?
+        .left_align(&mut grch38, &mut grch37)?;
+        Ok(LiftServer::new(
+            VariantLiftOver::new(chain, grch38, grch37),
+            3,
+            3,
+        ))
+    }
+
+    #[test]
+    fn test_serve_handle() -> anyhow::Result<()> {
+        let mut lift_server = new_lift_server()?;
+
+        let value = lift_server
+            .handle(
+                "/position",
+                br#"{"chromosome": "chr22", "position": 17513945}"#,
+            )
+            .unwrap();
+        assert_eq!(value["results"][0]["chromosome"], "chr22");
+        assert_eq!(value["results"][0]["position"], 17992974);
+
+        let value = lift_server
+            .handle(
+                "/region",
+                br#"[{"chromosome": "chr22", "start": 17513945, "end": 17514000}]"#,
+            )
+            .unwrap();
+        assert_eq!(value[0]["results"][0]["start"], 17992974);
+        assert_eq!(value[0]["results"][0]["end"], 17993029);
+
+        let value = lift_server
+            .handle(
+                "/position/?format=json",
+                br#"{"chromosome": "chr22", "position": 17513945}"#,
+            )
+            .unwrap();
+        assert_eq!(value["results"][0]["position"], 17992974);
+
+        assert!(matches!(
+            lift_server.handle("/unknown", b"{}"),
+            Err(ServeError::NotFound)
+        ));
+        assert!(matches!(
+            lift_server.handle("/unknown?/position", b"{}"),
+            Err(ServeError::NotFound)
+        ));
+        assert!(matches!(
+            lift_server.handle("/position", b"{"),
+            Err(ServeError::BadRequest(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 pub mod commands;
+mod tabix;
 mod utils;
 
 use clap::Parser;
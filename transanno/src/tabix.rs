@@ -0,0 +1,463 @@
+//! Create a tabix (.tbi) or CSI (.csi) index for a sorted bgzip-compressed VCF file.
+
+use anyhow::{anyhow, Context};
+use bgzip::{BGZFReader, BGZFWriter, Compression};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{prelude::*, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str;
+
+const MIN_SHIFT: u32 = 14;
+const TBI_DEPTH: u32 = 5;
+/// Bins spanning less than this compressed size are merged into a parent bin
+const MIN_MARKER_DISTANCE: u64 = 0x10000;
+/// Tabix header: format (VCF), sequence column, begin column, end column, meta character and lines to skip
+const TABIX_CONFIG: [i32; 6] = [2, 1, 2, 0, b'#' as i32, 0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    Tbi,
+    Csi,
+}
+
+impl IndexFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            IndexFormat::Tbi => "tbi",
+            IndexFormat::Csi => "csi",
+        }
+    }
+}
+
+/// Same as `bgzip::tabix::reg2bin`, but with a depth for CSI indexes of long contigs.
+/// (`bgzip::csi` is not public.)
+fn reg2bin(beg: u64, end: u64, min_shift: u32, depth: u32) -> u32 {
+    let end = end - 1;
+    let mut level = depth;
+    let mut shift = min_shift;
+    let mut first_bin = ((1u64 << (3 * depth)) - 1) / 7;
+    while level > 0 {
+        if beg >> shift == end >> shift {
+            return (first_bin + (beg >> shift)) as u32;
+        }
+        level -= 1;
+        shift += 3;
+        first_bin -= 1 << (3 * level);
+    }
+    0
+}
+
+/// Return a first position of a bin
+fn bin_start(bin: u32, min_shift: u32, depth: u32) -> u64 {
+    let bin = bin as u64;
+    let mut level = 0;
+    while ((1u64 << (3 * (level + 1))) - 1) / 7 <= bin {
+        level += 1;
+    }
+    let first_bin = ((1u64 << (3 * level)) - 1) / 7;
+    (bin - first_bin) << (min_shift + 3 * (depth - level))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Bin {
+    /// smallest virtual offset of records in the first window of this bin (used in CSI)
+    loffset: u64,
+    chunks: Vec<(u64, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReferenceIndex {
+    name: Vec<u8>,
+    bins: BTreeMap<u32, Bin>,
+    /// smallest virtual offset of records in each 16kbp window
+    linear: Vec<u64>,
+    first_offset: u64,
+    last_offset: u64,
+    mapped: u64,
+    last_position: u64,
+}
+
+impl ReferenceIndex {
+    fn new(name: &[u8]) -> Self {
+        ReferenceIndex {
+            name: name.to_vec(),
+            bins: BTreeMap::new(),
+            linear: Vec::new(),
+            first_offset: u64::MAX,
+            last_offset: 0,
+            mapped: 0,
+            last_position: 0,
+        }
+    }
+
+    fn add(&mut self, beg: u64, end: u64, start_offset: u64, end_offset: u64, depth: u32) {
+        let chunks = &mut self
+            .bins
+            .entry(reg2bin(beg, end, MIN_SHIFT, depth))
+            .or_default()
+            .chunks;
+        match chunks.last_mut() {
+            Some(last) if last.1 == start_offset => last.1 = end_offset,
+            _ => chunks.push((start_offset, end_offset)),
+        }
+
+        let last_window = ((end - 1) >> MIN_SHIFT) as usize;
+        if self.linear.len() <= last_window {
+            self.linear.resize(last_window + 1, u64::MAX);
+        }
+        for one in self.linear[(beg >> MIN_SHIFT) as usize..=last_window].iter_mut() {
+            if *one == u64::MAX {
+                *one = start_offset;
+            }
+        }
+
+        self.first_offset = self.first_offset.min(start_offset);
+        self.last_offset = self.last_offset.max(end_offset);
+        self.mapped += 1;
+        self.last_position = beg;
+    }
+
+    /// Fill empty windows of the linear index, and merge small bins as htslib does.
+    fn finish(&mut self, depth: u32) {
+        let mut previous = self.first_offset;
+        for one in self.linear.iter_mut() {
+            if *one == u64::MAX {
+                *one = previous;
+            }
+            previous = *one;
+        }
+
+        for (bin, one) in self.bins.iter_mut() {
+            let window = (bin_start(*bin, MIN_SHIFT, depth) >> MIN_SHIFT) as usize;
+            one.loffset = self.linear.get(window).copied().unwrap_or(0);
+        }
+
+        // merge a bin into its parent if the bin spans less than 64kb of compressed data
+        for level in (1..=depth).rev() {
+            let first_bin = (((1u64 << (3 * level)) - 1) / 7) as u32;
+            let small_bins: Vec<u32> = self
+                .bins
+                .range(first_bin..)
+                .filter(|(_, x)| {
+                    (x.chunks.last().unwrap().1 >> 16) - (x.chunks[0].0 >> 16) < MIN_MARKER_DISTANCE
+                })
+                .map(|(bin, _)| *bin)
+                .collect();
+            for bin in small_bins {
+                let parent = (bin - 1) >> 3;
+                if self.bins.contains_key(&parent) {
+                    let chunks = self.bins.remove(&bin).unwrap().chunks;
+                    self.bins.get_mut(&parent).unwrap().chunks.extend(chunks);
+                }
+            }
+            for one in self.bins.values_mut() {
+                one.chunks.sort_unstable();
+            }
+        }
+
+        // merge chunks starting from the same BGZF block
+        for one in self.bins.values_mut() {
+            let mut merged: Vec<(u64, u64)> = Vec::new();
+            for chunk in one.chunks.iter() {
+                match merged.last_mut() {
+                    Some(last) if last.1 >> 16 >= chunk.0 >> 16 => last.1 = last.1.max(chunk.1),
+                    _ => merged.push(*chunk),
+                }
+            }
+            one.chunks = merged;
+        }
+    }
+}
+
+/// Parse a VCF record and returns contig, zero-based start and end positions.
+fn parse_record(line: &[u8]) -> Option<(&[u8], u64, u64)> {
+    let mut columns = line.split(|x| *x == b'\t');
+    let contig = columns.next()?;
+    let position: u64 = str::from_utf8(columns.next()?).ok()?.parse().ok()?;
+    let reference = columns.nth(1)?;
+    let info = columns.nth(3);
+
+    let beg = position.checked_sub(1)?;
+    let mut end = beg + reference.len().max(1) as u64;
+    if let Some(info_end) = info.and_then(|x| {
+        x.split(|y| *y == b';')
+            .find(|y| y.starts_with(b"END="))
+            .and_then(|y| str::from_utf8(&y[4..]).ok())
+            .and_then(|y| y.parse::<u64>().ok())
+    }) {
+        end = end.max(info_end);
+    }
+    Some((contig, beg, end))
+}
+
+/// Build index with given depth of bins. Returns `None` if a record ends beyond the index range.
+fn build_index(path: &Path, depth: u32) -> anyhow::Result<Option<Vec<ReferenceIndex>>> {
+    let context = || format!("Failed to read {}", path.display());
+    let mut reader = BGZFReader::new(File::open(path)?).with_context(context)?;
+    let mut references: Vec<ReferenceIndex> = Vec::new();
+    let mut line = Vec::new();
+    let mut line_start = reader.bgzf_pos();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).with_context(context)? == 0 {
+            break;
+        }
+        // Load the next block, so an offset at the end of a block points to the start of the
+        // next block. The reader must not be read after the EOF marker.
+        let at_eof = reader.fill_buf().with_context(context)?.is_empty();
+        let line_end = reader.bgzf_pos();
+        if line.ends_with(b"\n") {
+            line.pop();
+        }
+        if !line.starts_with(b"#")
+            && !line.is_empty()
+            && !index_record(&mut references, &line, line_start, line_end, depth)?
+        {
+            return Ok(None);
+        }
+        if at_eof {
+            break;
+        }
+        line_start = line_end;
+    }
+
+    for one in references.iter_mut() {
+        one.finish(depth);
+    }
+    Ok(Some(references))
+}
+
+/// Add a VCF record to the index of its contig. Returns `false` if the record ends beyond the
+/// index range.
+fn index_record(
+    references: &mut Vec<ReferenceIndex>,
+    line: &[u8],
+    line_start: u64,
+    line_end: u64,
+    depth: u32,
+) -> anyhow::Result<bool> {
+    let (contig, beg, end) = parse_record(line)
+        .ok_or_else(|| anyhow!("Cannot parse VCF record: {}", String::from_utf8_lossy(line)))?;
+    if references.last().map(|x| x.name.as_slice()) != Some(contig) {
+        if references.iter().any(|x| x.name == contig) {
+            return Err(anyhow!(
+                "VCF file is not sorted: {} appears again",
+                String::from_utf8_lossy(contig)
+            ));
+        }
+        references.push(ReferenceIndex::new(contig));
+    }
+    let reference = references.last_mut().unwrap();
+    if beg < reference.last_position {
+        return Err(anyhow!(
+            "VCF file is not sorted: {}:{}",
+            String::from_utf8_lossy(contig),
+            beg + 1
+        ));
+    }
+    if end > 1 << (MIN_SHIFT + 3 * depth) {
+        return Ok(false);
+    }
+    reference.add(beg, end, line_start, line_end, depth);
+    Ok(true)
+}
+
+fn write_tabix_config(data: &mut Vec<u8>, references: &[ReferenceIndex]) {
+    for one in TABIX_CONFIG.iter() {
+        data.extend_from_slice(&one.to_le_bytes());
+    }
+    let names: Vec<u8> = references
+        .iter()
+        .flat_map(|x| x.name.iter().copied().chain([0]))
+        .collect();
+    data.extend_from_slice(&(names.len() as i32).to_le_bytes());
+    data.extend_from_slice(&names);
+}
+
+fn write_chunks(data: &mut Vec<u8>, chunks: &[(u64, u64)]) {
+    data.extend_from_slice(&(chunks.len() as i32).to_le_bytes());
+    for (start, end) in chunks {
+        data.extend_from_slice(&start.to_le_bytes());
+        data.extend_from_slice(&end.to_le_bytes());
+    }
+}
+
+fn serialize_index(references: &[ReferenceIndex], format: IndexFormat, depth: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    match format {
+        IndexFormat::Tbi => {
+            data.extend_from_slice(b"TBI\x01");
+            data.extend_from_slice(&(references.len() as i32).to_le_bytes());
+            write_tabix_config(&mut data, references);
+        }
+        IndexFormat::Csi => {
+            let mut aux = Vec::new();
+            write_tabix_config(&mut aux, references);
+            data.extend_from_slice(b"CSI\x01");
+            data.extend_from_slice(&(MIN_SHIFT as i32).to_le_bytes());
+            data.extend_from_slice(&(depth as i32).to_le_bytes());
+            data.extend_from_slice(&(aux.len() as i32).to_le_bytes());
+            data.extend_from_slice(&aux);
+            data.extend_from_slice(&(references.len() as i32).to_le_bytes());
+        }
+    }
+
+    let pseudo_bin = (((1u64 << (3 * (depth + 1))) - 1) / 7 + 1) as u32;
+    for one in references {
+        data.extend_from_slice(&(one.bins.len() as i32 + 1).to_le_bytes());
+        for (bin, content) in one.bins.iter() {
+            data.extend_from_slice(&bin.to_le_bytes());
+            if format == IndexFormat::Csi {
+                data.extend_from_slice(&content.loffset.to_le_bytes());
+            }
+            write_chunks(&mut data, &content.chunks);
+        }
+
+        // pseudo bin for the number of records
+        data.extend_from_slice(&pseudo_bin.to_le_bytes());
+        if format == IndexFormat::Csi {
+            data.extend_from_slice(&0u64.to_le_bytes());
+        }
+        write_chunks(
+            &mut data,
+            &[(one.first_offset, one.last_offset), (one.mapped, 0)],
+        );
+
+        if format == IndexFormat::Tbi {
+            data.extend_from_slice(&(one.linear.len() as i32).to_le_bytes());
+            for offset in one.linear.iter() {
+                data.extend_from_slice(&offset.to_le_bytes());
+            }
+        }
+    }
+    // number of records without coordinates
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data
+}
+
+/// Create an index for a sorted bgzip-compressed VCF file. A tabix index is created if all
+/// positions are smaller than 2^29, otherwise a CSI index is created.
+/// Returns a path to the created index.
+pub fn create_index<P: AsRef<Path>>(path: P) -> anyhow::Result<PathBuf> {
+    let path = path.as_ref();
+    let mut depth = TBI_DEPTH;
+    let references = loop {
+        if let Some(references) = build_index(path, depth)? {
+            break references;
+        }
+        // retry with CSI index and deeper bins for long contigs
+        depth += 1;
+    };
+    let format = if depth == TBI_DEPTH {
+        IndexFormat::Tbi
+    } else {
+        IndexFormat::Csi
+    };
+
+    let mut index_path = path.as_os_str().to_owned();
+    index_path.push(".");
+    index_path.push(format.extension());
+    let index_path = PathBuf::from(index_path);
+    let mut writer = BGZFWriter::new(
+        BufWriter::new(File::create(&index_path)?),
+        Compression::default(),
+    );
+    writer
+        .write_all(&serialize_index(&references, format, depth))
+        .and_then(|_| writer.close())
+        .with_context(|| format!("Failed to write index: {}", index_path.display()))?;
+    Ok(index_path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_bgzf(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+        let mut writer = BGZFWriter::new(File::create(path)?, Compression::default());
+        writer.write_all(data)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_reg2bin() {
+        for (beg, end) in [(0, 1), (16384, 16385), (16000, 17000), (0, 1 << 29)] {
+            assert_eq!(
+                reg2bin(beg, end, MIN_SHIFT, TBI_DEPTH),
+                bgzip::tabix::reg2bin(beg as u32, end as u32)
+            );
+        }
+        assert_eq!(reg2bin(16000, 17000, MIN_SHIFT, TBI_DEPTH), 585);
+        assert_eq!(
+            reg2bin(1 << 29, (1 << 29) + 1, MIN_SHIFT, TBI_DEPTH + 1),
+            37449 + 32768
+        );
+        assert_eq!(bin_start(4682, MIN_SHIFT, TBI_DEPTH), 16384);
+        assert_eq!(bin_start(586, MIN_SHIFT, TBI_DEPTH), 131072);
+        assert_eq!(bin_start(0, MIN_SHIFT, TBI_DEPTH), 0);
+    }
+
+    #[test]
+    fn test_create_index() -> anyhow::Result<()> {
+        std::fs::create_dir_all("../target/test-output/tabix")?;
+        let vcf_path = Path::new("../target/test-output/tabix/sorted.vcf.gz");
+        write_bgzf(
+            vcf_path,
+            b"##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr1\t100\t.\tA\tG\t.\t.\t.\nchr1\t20000\t.\tAT\t<DEL>\t.\t.\tEND=40000\nchr2\t1\t.\tC\tT\t.\t.\t.\n",
+        )?;
+        let index_path = create_index(vcf_path)?;
+        assert_eq!(
+            index_path,
+            Path::new("../target/test-output/tabix/sorted.vcf.gz.tbi")
+        );
+
+        let mut index = Vec::new();
+        BGZFReader::new(File::open(&index_path)?)?.read_to_end(&mut index)?;
+        assert_eq!(&index[..4], b"TBI\x01");
+        assert_eq!(&index[4..8], &2i32.to_le_bytes());
+        assert_eq!(&index[32..36], &10i32.to_le_bytes());
+        assert_eq!(&index[36..46], b"chr1\0chr2\0");
+
+        let unsorted_path = Path::new("../target/test-output/tabix/unsorted.vcf.gz");
+        write_bgzf(
+            unsorted_path,
+            b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr1\t100\t.\tA\tG\t.\t.\t.\nchr2\t1\t.\tC\tT\t.\t.\t.\nchr1\t200\t.\tA\tG\t.\t.\t.\n",
+        )?;
+        assert!(create_index(unsorted_path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_index() -> anyhow::Result<()> {
+        std::fs::create_dir_all("../target/test-output/tabix")?;
+        let vcf_path = Path::new("../target/test-output/tabix/build.vcf.gz");
+        let header = b"#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+        write_bgzf(
+            vcf_path,
+            &[
+                &header[..],
+                b"chr1\t100\t.\tA\tG\t.\t.\t.\nchr1\t20000\t.\tAT\t<DEL>\t.\t.\tEND=40000\n",
+            ]
+            .concat(),
+        )?;
+        let references = build_index(vcf_path, TBI_DEPTH)?.unwrap();
+        assert_eq!(references.len(), 1);
+        let first = header.len() as u64;
+        let second = first + 21;
+        // small bins are merged into the parent bin
+        assert_eq!(references[0].bins.keys().collect::<Vec<_>>(), vec![&585]);
+        let chunks = &references[0].bins[&585].chunks;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].0, first);
+        // an offset at the end of a block points to the start of the next block
+        assert_eq!(chunks[0].1 & 0xffff, 0);
+        assert_eq!(references[0].linear, vec![first, second, second]);
+        assert_eq!(references[0].mapped, 2);
+        Ok(())
+    }
+}
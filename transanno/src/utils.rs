@@ -9,7 +9,7 @@ pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<impl Read> {
 pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<impl Write> {
     let extension = path.as_ref().extension().and_then(|x| x.to_str());
     let writer: Box<dyn Write> = match extension {
-        Some("gz") => Box::new(bgzip::BGZFWriter::new(
+        Some("gz") | Some("bcf") => Box::new(bgzip::BGZFWriter::new(
             File::create(path)?,
             bgzip::Compression::default(),
         )),